@@ -0,0 +1,146 @@
+use ecolor::Color32;
+
+use crate::{
+    color::{
+        gamut::{hsv_to_linear_srgb, linear_srgb_to_color32, map_to_gamut, Gamut, GamutMapping},
+        oklab::{linear_srgb_to_oklab, oklab_to_linear_srgb},
+    },
+    colormaps::COLORMAP_FIT_SETTINGS,
+    common::SplineMode,
+    compare::ComparedGradient,
+    error::Result,
+    gradient_fit::{fit_control_points, FitResult, FitSettings},
+    ui_egui::previewer::CurveSampler,
+};
+
+/// Positions both gradients are sampled at before blending and fitting.
+const BLEND_SAMPLES: usize = 128;
+
+/// `count` evenly spaced colors along the gradient as OKLab, as the previewer samples it.
+pub fn resample_oklab(gradient: &ComparedGradient, count: usize) -> Vec<[f32; 3]> {
+    CurveSampler::with_gradient_mode(
+        &gradient.control_points,
+        gradient.spline_mode,
+        gradient.gradient_mode,
+    )
+    .sample_n(count)
+    .iter()
+    .map(|val| linear_srgb_to_oklab(hsv_to_linear_srgb(val)))
+    .collect()
+}
+
+/// OKLab to sRGB, chroma is reduced until the color fits.
+fn oklab_to_color32(lab: [f32; 3]) -> Color32 {
+    linear_srgb_to_color32(map_to_gamut(
+        oklab_to_linear_srgb(lab),
+        Gamut::Srgb,
+        GamutMapping::OklchChroma,
+    ))
+}
+
+/// The gradient `t` of the way from `a` to `b`. Both are sampled densely, each pair of colors
+/// is blended along a straight OKLab line and the result is fitted with `spline_mode` control
+/// points, so at either end the fit reproduces that gradient within the fit tolerance.
+pub fn blend_gradients(
+    a: &ComparedGradient,
+    b: &ComparedGradient,
+    t: f32,
+    spline_mode: SplineMode,
+) -> Result<FitResult> {
+    let t = t.clamp(0.0, 1.0);
+    let samples: Vec<Color32> = resample_oklab(a, BLEND_SAMPLES)
+        .into_iter()
+        .zip(resample_oklab(b, BLEND_SAMPLES))
+        .map(|(a, b)| oklab_to_color32(std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)))
+        .collect();
+    let settings = FitSettings {
+        spline_mode,
+        ..COLORMAP_FIT_SETTINGS
+    };
+    fit_control_points(&samples, &settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::GradientMode,
+        compare::GradientComparison,
+        datatypes::hsv_key_value::HsvKeyValue,
+        gradient_extract::values_to_control_points,
+        test_utils::{compared_gradient, wrapping_control_points, IMPLEMENTED_SPLINE_MODES},
+    };
+
+    fn night() -> ComparedGradient {
+        compared_gradient(values_to_control_points(
+            [
+                HsvKeyValue::new(0.6, 0.1, 0.65),
+                HsvKeyValue::new(0.4, 0.3, 0.7),
+            ],
+            SplineMode::HermiteBezier,
+        ))
+    }
+
+    /// Largest ΔE between the gradients along t, as the previewer shows them.
+    fn max_delta_e(a: &ComparedGradient, b: &ComparedGradient) -> f32 {
+        GradientComparison::new(a, b, GamutMapping::Clip)
+            .max_delta_e()
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn endpoints_reproduce_the_gradients() {
+        let day = compared_gradient(wrapping_control_points());
+        let night = night();
+        for spline_mode in IMPLEMENTED_SPLINE_MODES {
+            for (t, original) in [(0.0, &day), (1.0, &night)] {
+                let blended = blend_gradients(&day, &night, t, spline_mode).unwrap();
+                let blended = ComparedGradient {
+                    control_points: blended.control_points,
+                    spline_mode,
+                    gradient_mode: GradientMode::Open,
+                };
+                let error = max_delta_e(&blended, original);
+                // The fit's tolerance is against 8 bit samples, allow for their rounding
+                assert!(
+                    error <= COLORMAP_FIT_SETTINGS.tolerance + 0.5,
+                    "{:?} at t {}: max ΔE {}",
+                    spline_mode,
+                    t,
+                    error
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn halfway_is_halfway_in_oklab() {
+        let day = compared_gradient(wrapping_control_points());
+        let night = night();
+        let halfway = compared_gradient(
+            blend_gradients(&day, &night, 0.5, SplineMode::HermiteBezier)
+                .unwrap()
+                .control_points,
+        );
+
+        let count = 16;
+        let lightness = |gradient: &ComparedGradient| -> Vec<f32> {
+            resample_oklab(gradient, count)
+                .iter()
+                .map(|lab| lab[0])
+                .collect()
+        };
+        let (day, night, halfway) = (lightness(&day), lightness(&night), lightness(&halfway));
+        for i in 0..count {
+            let expected = (day[i] + night[i]) * 0.5;
+            assert!(
+                (halfway[i] - expected).abs() < 0.02,
+                "#{}: {} expected {}",
+                i,
+                halfway[i],
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod contrast;
+pub mod cvd;
+pub mod delta_e;
+pub mod distinct;
+pub mod gamut;
+pub mod oklab;
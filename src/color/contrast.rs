@@ -0,0 +1,207 @@
+use ecolor::Color32;
+
+/// WCAG 2.1 relative luminance of an sRGB color.
+pub fn relative_luminance(color: Color32) -> f32 {
+    fn channel(c: u8) -> f32 {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    0.2126 * channel(color.r()) + 0.7152 * channel(color.g()) + 0.0722 * channel(color.b())
+}
+
+/// WCAG 2.1 contrast ratio, 1.0 (no contrast) to 21.0 (black on white). Symmetric.
+pub fn wcag_contrast_ratio(a: Color32, b: Color32) -> f32 {
+    let l_a = relative_luminance(a);
+    let l_b = relative_luminance(b);
+    (l_a.max(l_b) + 0.05) / (l_a.min(l_b) + 0.05)
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord)]
+pub enum WcagLevel {
+    Fail,
+    AaLarge,
+    Aa,
+    Aaa,
+}
+
+impl WcagLevel {
+    pub fn from_ratio(ratio: f32) -> Self {
+        if ratio >= 7.0 {
+            WcagLevel::Aaa
+        } else if ratio >= 4.5 {
+            WcagLevel::Aa
+        } else if ratio >= 3.0 {
+            WcagLevel::AaLarge
+        } else {
+            WcagLevel::Fail
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WcagLevel::Fail => "Fail",
+            WcagLevel::AaLarge => "AA Large",
+            WcagLevel::Aa => "AA",
+            WcagLevel::Aaa => "AAA",
+        }
+    }
+}
+
+/// APCA lightness contrast (Lc) of `text` drawn on `background`, version 0.0.98G-4g.
+/// Positive for dark text on light background, negative for light text on dark background.
+pub fn apca_contrast(text: Color32, background: Color32) -> f32 {
+    const MAIN_TRC: f32 = 2.4;
+    const NORM_BG: f32 = 0.56;
+    const NORM_TXT: f32 = 0.57;
+    const REV_TXT: f32 = 0.62;
+    const REV_BG: f32 = 0.65;
+    const BLK_THRS: f32 = 0.022;
+    const BLK_CLMP: f32 = 1.414;
+    const SCALE_BOW: f32 = 1.14;
+    const SCALE_WOB: f32 = 1.14;
+    const LO_BOW_OFFSET: f32 = 0.027;
+    const LO_WOB_OFFSET: f32 = 0.027;
+    const DELTA_Y_MIN: f32 = 0.0005;
+    const LO_CLIP: f32 = 0.1;
+
+    fn screen_luminance(color: Color32) -> f32 {
+        let y = 0.2126729 * (color.r() as f32 / 255.0).powf(MAIN_TRC)
+            + 0.7151522 * (color.g() as f32 / 255.0).powf(MAIN_TRC)
+            + 0.0721750 * (color.b() as f32 / 255.0).powf(MAIN_TRC);
+        if y < BLK_THRS {
+            y + (BLK_THRS - y).powf(BLK_CLMP)
+        } else {
+            y
+        }
+    }
+
+    let y_txt = screen_luminance(text);
+    let y_bg = screen_luminance(background);
+    if (y_bg - y_txt).abs() < DELTA_Y_MIN {
+        return 0.0;
+    }
+
+    let lc = if y_bg > y_txt {
+        let sapc = (y_bg.powf(NORM_BG) - y_txt.powf(NORM_TXT)) * SCALE_BOW;
+        if sapc < LO_CLIP {
+            0.0
+        } else {
+            sapc - LO_BOW_OFFSET
+        }
+    } else {
+        let sapc = (y_bg.powf(REV_BG) - y_txt.powf(REV_TXT)) * SCALE_WOB;
+        if sapc > -LO_CLIP {
+            0.0
+        } else {
+            sapc + LO_WOB_OFFSET
+        }
+    };
+
+    lc * 100.0
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord)]
+pub enum ApcaLevel {
+    Fail,
+    NonText,
+    LargeText,
+    ContentText,
+    BodyText,
+}
+
+impl ApcaLevel {
+    /// Levels from the APCA bronze simple mode, the sign (polarity) is ignored.
+    pub fn from_lc(lc: f32) -> Self {
+        let lc = lc.abs();
+        if lc >= 75.0 {
+            ApcaLevel::BodyText
+        } else if lc >= 60.0 {
+            ApcaLevel::ContentText
+        } else if lc >= 45.0 {
+            ApcaLevel::LargeText
+        } else if lc >= 30.0 {
+            ApcaLevel::NonText
+        } else {
+            ApcaLevel::Fail
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ApcaLevel::Fail => "Fail",
+            ApcaLevel::NonText => "Non-text",
+            ApcaLevel::LargeText => "Large",
+            ApcaLevel::ContentText => "Content",
+            ApcaLevel::BodyText => "Body",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(v: u8) -> Color32 {
+        Color32::from_gray(v)
+    }
+
+    #[test]
+    fn wcag_reference_ratios() {
+        assert!((wcag_contrast_ratio(Color32::BLACK, Color32::WHITE) - 21.0).abs() < 1e-4);
+        assert!((wcag_contrast_ratio(Color32::WHITE, Color32::WHITE) - 1.0).abs() < 1e-6);
+        // The classic "just fails AA" gray
+        let ratio = wcag_contrast_ratio(gray(0x77), Color32::WHITE);
+        assert!((ratio - 4.48).abs() < 0.01, "{}", ratio);
+        assert_eq!(WcagLevel::from_ratio(ratio), WcagLevel::AaLarge);
+        assert_eq!(ratio, wcag_contrast_ratio(Color32::WHITE, gray(0x77)));
+    }
+
+    #[test]
+    fn apca_reference_values() {
+        // From the APCA 0.0.98G-4g reference implementation
+        for (text, background, expected) in [
+            (gray(0x88), Color32::WHITE, 63.056),
+            (Color32::WHITE, gray(0x88), -68.541),
+            (Color32::BLACK, gray(0xaa), 58.146),
+            (gray(0xaa), Color32::BLACK, -56.241),
+            (Color32::BLACK, Color32::WHITE, 106.04),
+            (Color32::WHITE, Color32::BLACK, -107.88),
+        ] {
+            let lc = apca_contrast(text, background);
+            assert!(
+                (lc - expected).abs() < 0.05,
+                "{:?} on {:?}: {}",
+                text,
+                background,
+                lc
+            );
+        }
+    }
+
+    #[test]
+    fn apca_clips_low_contrast_to_zero() {
+        // Below ±0.1 the contrast is 0, past it Lc starts at (0.1 - 0.027) * 100
+        let min_lc = 7.3 - 1e-3;
+        for v in 0..=255 {
+            let dark_on_light = apca_contrast(gray(v), Color32::WHITE);
+            assert!(
+                dark_on_light == 0.0 || dark_on_light >= min_lc,
+                "{}",
+                dark_on_light
+            );
+            let light_on_dark = apca_contrast(gray(v), Color32::BLACK);
+            assert!(
+                light_on_dark == 0.0 || light_on_dark <= -min_lc,
+                "{}",
+                light_on_dark
+            );
+        }
+        assert_eq!(apca_contrast(gray(0xf8), Color32::WHITE), 0.0);
+        assert_eq!(apca_contrast(gray(0x20), Color32::BLACK), 0.0);
+    }
+}
@@ -0,0 +1,128 @@
+use ecolor::{Color32, Rgba};
+use serde::{Deserialize, Serialize};
+
+/// Color vision deficiency to simulate when previewing a gradient.
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum ColorVisionDeficiency {
+    #[default]
+    None,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+    Achromatopsia,
+}
+
+impl ColorVisionDeficiency {
+    pub const ALL: [ColorVisionDeficiency; 5] = [
+        ColorVisionDeficiency::None,
+        ColorVisionDeficiency::Protanopia,
+        ColorVisionDeficiency::Deuteranopia,
+        ColorVisionDeficiency::Tritanopia,
+        ColorVisionDeficiency::Achromatopsia,
+    ];
+
+    pub fn is_none(&self) -> bool {
+        *self == ColorVisionDeficiency::None
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ColorVisionDeficiency::None => "Normal vision",
+            ColorVisionDeficiency::Protanopia => "Protanopia",
+            ColorVisionDeficiency::Deuteranopia => "Deuteranopia",
+            ColorVisionDeficiency::Tritanopia => "Tritanopia",
+            ColorVisionDeficiency::Achromatopsia => "Achromatopsia",
+        }
+    }
+
+    /// Linear RGB simulation matrix, Machado et al. 2009 at severity 1.0.
+    fn matrix(&self) -> Option<[[f32; 3]; 3]> {
+        match self {
+            ColorVisionDeficiency::None => None,
+            ColorVisionDeficiency::Protanopia => Some([
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ]),
+            ColorVisionDeficiency::Deuteranopia => Some([
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ]),
+            ColorVisionDeficiency::Tritanopia => Some([
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ]),
+            // Rod monochromacy, only relative luminance is left
+            ColorVisionDeficiency::Achromatopsia => Some([
+                [0.2126, 0.7152, 0.0722],
+                [0.2126, 0.7152, 0.0722],
+                [0.2126, 0.7152, 0.0722],
+            ]),
+        }
+    }
+
+    pub fn simulate(&self, color: Color32) -> Color32 {
+        let Some(m) = self.matrix() else {
+            return color;
+        };
+
+        let linear = Rgba::from(color);
+        let rgb = [linear.r(), linear.g(), linear.b()];
+        let mut res = [0.0; 3];
+        for (i, row) in m.iter().enumerate() {
+            res[i] = (row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]).clamp(0.0, 1.0);
+        }
+
+        Color32::from(Rgba::from_rgba_unmultiplied(
+            res[0],
+            res[1],
+            res[2],
+            linear.a(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normal_vision_is_the_identity() {
+        for color in [
+            Color32::from_rgb(255, 0, 0),
+            Color32::from_rgb(12, 200, 99),
+            Color32::from_rgba_unmultiplied(40, 60, 250, 128),
+        ] {
+            assert_eq!(ColorVisionDeficiency::None.simulate(color), color);
+        }
+    }
+
+    #[test]
+    fn grays_look_the_same_to_everyone() {
+        for cvd in ColorVisionDeficiency::ALL {
+            for v in [0, 64, 128, 255] {
+                let simulated = cvd.simulate(Color32::from_gray(v));
+                for c in simulated.to_array().into_iter().take(3) {
+                    assert!(c.abs_diff(v) <= 1, "{:?} {:?}", cvd, simulated);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn deuteranopia_matches_machado() {
+        // The red and green columns of the published matrix, sRGB encoded
+        let deuteranopia = ColorVisionDeficiency::Deuteranopia;
+        for (color, expected) in [
+            (Color32::from_rgb(255, 0, 0), [163, 144, 0]),
+            (Color32::from_rgb(0, 255, 0), [239, 214, 58]),
+        ] {
+            let simulated = deuteranopia.simulate(color).to_array();
+            for c in 0..3 {
+                assert!(simulated[c].abs_diff(expected[c]) <= 1, "{:?}", simulated);
+            }
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use ecolor::Color32;
+use palette::{color_difference::Ciede2000, FromColor, Lab, Srgb};
+
+pub fn color32_to_lab(color: Color32) -> Lab {
+    let srgb = Srgb::new(color.r(), color.g(), color.b()).into_format::<f32>();
+    Lab::from_color(srgb)
+}
+
+/// CIEDE2000 difference between two colors.
+pub fn delta_e(a: Color32, b: Color32) -> f32 {
+    color32_to_lab(a).difference(color32_to_lab(b))
+}
+
+/// Smallest CIEDE2000 difference between any two of the colors, `None` with less than two colors.
+pub fn min_pairwise_delta_e(colors: &[Color32]) -> Option<f32> {
+    let labs: Vec<Lab> = colors.iter().map(|c| color32_to_lab(*c)).collect();
+    let mut min: Option<f32> = None;
+    for i in 0..labs.len() {
+        for j in (i + 1)..labs.len() {
+            let dist = labs[i].difference(labs[j]);
+            min = Some(min.map_or(dist, |m| m.min(dist)));
+        }
+    }
+    min
+}
@@ -0,0 +1,90 @@
+use ecolor::Color32;
+use palette::{color_difference::Ciede2000, Lab};
+
+use crate::color::delta_e::color32_to_lab;
+
+const MAX_SWAP_ITERATIONS: usize = 16;
+
+fn min_distance(distances: &[Vec<f32>], selected: &[usize]) -> f32 {
+    let mut min = f32::MAX;
+    for (i, a) in selected.iter().enumerate() {
+        for b in &selected[i + 1..] {
+            min = min.min(distances[*a][*b]);
+        }
+    }
+    min
+}
+
+/// Picks `count` of the candidate colors so that the smallest CIEDE2000 difference between any
+/// two picked colors is as large as possible.
+///
+/// Farthest point traversal from the most distant pair, then swaps picked colors for unpicked
+/// ones for as long as that improves the minimum distance. Returns the picked indices in
+/// ascending order.
+pub fn select_distinct_samples(candidates: &[Color32], count: usize) -> Vec<usize> {
+    let count = count.min(candidates.len());
+    if count < 2 {
+        return (0..count).collect();
+    }
+
+    let labs: Vec<Lab> = candidates.iter().map(|c| color32_to_lab(*c)).collect();
+    let n = labs.len();
+    let mut distances = vec![vec![0.0; n]; n];
+    let mut farthest_pair = (0, n - 1);
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let dist = labs[i].difference(labs[j]);
+            distances[i][j] = dist;
+            distances[j][i] = dist;
+            if dist > distances[farthest_pair.0][farthest_pair.1] {
+                farthest_pair = (i, j);
+            }
+        }
+    }
+
+    let mut selected = vec![farthest_pair.0, farthest_pair.1];
+    while selected.len() < count {
+        let next = (0..n)
+            .filter(|i| !selected.contains(i))
+            .max_by(|a, b| {
+                let dist_a = selected
+                    .iter()
+                    .map(|s| distances[*a][*s])
+                    .fold(f32::MAX, f32::min);
+                let dist_b = selected
+                    .iter()
+                    .map(|s| distances[*b][*s])
+                    .fold(f32::MAX, f32::min);
+                dist_a.total_cmp(&dist_b)
+            })
+            .unwrap();
+        selected.push(next);
+    }
+
+    let mut current_min = min_distance(&distances, &selected);
+    for _ in 0..MAX_SWAP_ITERATIONS {
+        let mut improved = false;
+        for slot in 0..selected.len() {
+            for candidate in 0..n {
+                if selected.contains(&candidate) {
+                    continue;
+                }
+                let previous = selected[slot];
+                selected[slot] = candidate;
+                let new_min = min_distance(&distances, &selected);
+                if new_min > current_min {
+                    current_min = new_min;
+                    improved = true;
+                } else {
+                    selected[slot] = previous;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+
+    selected.sort_unstable();
+    selected
+}
@@ -0,0 +1,289 @@
+#![allow(clippy::excessive_precision)]
+
+use ecolor::{gamma_from_linear, linear_from_gamma, Color32};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::oklab::{
+        delta_e_ok, linear_srgb_to_oklab, oklab_to_linear_srgb, oklab_to_oklch, oklch_to_oklab,
+    },
+    datatypes::hsv_key_value::HsvKeyValue,
+};
+
+const GAMUT_EPSILON: f32 = 0.0001;
+
+type Matrix3 = [[f32; 3]; 3];
+
+const LINEAR_SRGB_TO_DISPLAY_P3: Matrix3 = [
+    [0.8224621, 0.1775380, 0.0000000],
+    [0.0331941, 0.9668058, 0.0000000],
+    [0.0170827, 0.0723974, 0.9105199],
+];
+const DISPLAY_P3_TO_LINEAR_SRGB: Matrix3 = [
+    [1.2249401, -0.2249404, 0.0000000],
+    [-0.0420569, 1.0420571, 0.0000000],
+    [-0.0196376, -0.0786361, 1.0982735],
+];
+const LINEAR_SRGB_TO_REC2020: Matrix3 = [
+    [0.6274040, 0.3292820, 0.0433136],
+    [0.0690970, 0.9195400, 0.0113612],
+    [0.0163916, 0.0880132, 0.8955950],
+];
+const REC2020_TO_LINEAR_SRGB: Matrix3 = [
+    [1.6604910, -0.5876411, -0.0728499],
+    [-0.1245505, 1.1328999, -0.0083494],
+    [-0.0181508, -0.1005789, 1.1187297],
+];
+
+fn mul_matrix3(m: &Matrix3, v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+/// RGB color spaces with their own primaries, all with a D65 white point.
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum Gamut {
+    #[default]
+    Srgb,
+    DisplayP3,
+    Rec2020,
+}
+
+impl Gamut {
+    pub const ALL: [Gamut; 3] = [Gamut::Srgb, Gamut::DisplayP3, Gamut::Rec2020];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Gamut::Srgb => "sRGB",
+            Gamut::DisplayP3 => "Display P3",
+            Gamut::Rec2020 => "Rec.2020",
+        }
+    }
+
+    /// Linear sRGB to this gamut's linear RGB.
+    pub fn linear_srgb_to_gamut(&self, rgb: [f32; 3]) -> [f32; 3] {
+        match self {
+            Gamut::Srgb => rgb,
+            Gamut::DisplayP3 => mul_matrix3(&LINEAR_SRGB_TO_DISPLAY_P3, rgb),
+            Gamut::Rec2020 => mul_matrix3(&LINEAR_SRGB_TO_REC2020, rgb),
+        }
+    }
+
+    /// This gamut's linear RGB to linear sRGB.
+    pub fn gamut_to_linear_srgb(&self, rgb: [f32; 3]) -> [f32; 3] {
+        match self {
+            Gamut::Srgb => rgb,
+            Gamut::DisplayP3 => mul_matrix3(&DISPLAY_P3_TO_LINEAR_SRGB, rgb),
+            Gamut::Rec2020 => mul_matrix3(&REC2020_TO_LINEAR_SRGB, rgb),
+        }
+    }
+
+    pub fn contains(&self, linear_srgb: [f32; 3]) -> bool {
+        self.linear_srgb_to_gamut(linear_srgb)
+            .iter()
+            .all(|c| *c >= -GAMUT_EPSILON && *c <= 1.0 + GAMUT_EPSILON)
+    }
+
+    fn clip(&self, linear_srgb: [f32; 3]) -> [f32; 3] {
+        let rgb = self.linear_srgb_to_gamut(linear_srgb);
+        self.gamut_to_linear_srgb([
+            rgb[0].clamp(0.0, 1.0),
+            rgb[1].clamp(0.0, 1.0),
+            rgb[2].clamp(0.0, 1.0),
+        ])
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum GamutMapping {
+    /// Clamp each channel
+    #[default]
+    Clip,
+    /// Reduce OKLCH chroma until the color fits, keeping lightness and hue
+    OklchChroma,
+    /// CSS Color 4 gamut mapping, chroma reduction that accepts clipping within a JND
+    Css4,
+}
+
+impl GamutMapping {
+    pub const ALL: [GamutMapping; 3] = [
+        GamutMapping::Clip,
+        GamutMapping::OklchChroma,
+        GamutMapping::Css4,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GamutMapping::Clip => "Clip",
+            GamutMapping::OklchChroma => "OKLCH chroma",
+            GamutMapping::Css4 => "CSS Color 4",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct GamutSettings {
+    pub mapping: GamutMapping,
+    pub check_gamut: Gamut,
+    pub highlight_out_of_gamut: bool,
+}
+
+impl Default for GamutSettings {
+    fn default() -> Self {
+        Self {
+            mapping: GamutMapping::default(),
+            check_gamut: Gamut::Srgb,
+            highlight_out_of_gamut: true,
+        }
+    }
+}
+
+impl GamutSettings {
+    pub fn is_out_of_gamut(&self, val: &HsvKeyValue) -> bool {
+        !self.check_gamut.contains(hsv_to_linear_srgb(val))
+    }
+}
+
+/// Same conversion as `HsvaGamma` into `Rgba`, without clamping saturation so spline overshoot
+/// shows up as channels outside [0, 1].
+pub fn hsv_to_linear_srgb(val: &HsvKeyValue) -> [f32; 3] {
+    let h = val.h().rem_euclid(1.0);
+    let s = val.s();
+    let v = linear_from_gamma(val.v());
+
+    let f = h * 6.0 - (h * 6.0).floor();
+    let p = v * (1.0 - s);
+    let q = v * (1.0 - f * s);
+    let t = v * (1.0 - (1.0 - f) * s);
+
+    match (h * 6.0).floor() as i32 % 6 {
+        0 => [v, t, p],
+        1 => [q, v, p],
+        2 => [p, v, t],
+        3 => [p, q, v],
+        4 => [t, p, v],
+        _ => [v, p, q],
+    }
+}
+
+pub fn linear_srgb_to_color32(rgb: [f32; 3]) -> Color32 {
+    let to_u8 = |c: f32| (gamma_from_linear(c.clamp(0.0, 1.0)) * 255.0).round() as u8;
+    Color32::from_rgb(to_u8(rgb[0]), to_u8(rgb[1]), to_u8(rgb[2]))
+}
+
+fn reduce_chroma(linear_srgb: [f32; 3], gamut: Gamut) -> [f32; 3] {
+    let mut lch = oklab_to_oklch(linear_srgb_to_oklab(linear_srgb));
+    let mut min = 0.0;
+    let mut max = lch[1];
+    while max - min > GAMUT_EPSILON {
+        lch[1] = (min + max) * 0.5;
+        if gamut.contains(oklab_to_linear_srgb(oklch_to_oklab(lch))) {
+            min = lch[1];
+        } else {
+            max = lch[1];
+        }
+    }
+    lch[1] = min;
+    gamut.clip(oklab_to_linear_srgb(oklch_to_oklab(lch)))
+}
+
+/// https://www.w3.org/TR/css-color-4/#binsearch
+fn css4_gamut_map(linear_srgb: [f32; 3], gamut: Gamut) -> [f32; 3] {
+    const JND: f32 = 0.02;
+
+    let origin = oklab_to_oklch(linear_srgb_to_oklab(linear_srgb));
+    let mut current = origin;
+    let mut clipped = gamut.clip(oklab_to_linear_srgb(oklch_to_oklab(current)));
+    if delta_e_ok(linear_srgb_to_oklab(clipped), oklch_to_oklab(current)) < JND {
+        return clipped;
+    }
+
+    let mut min = 0.0;
+    let mut max = origin[1];
+    let mut min_in_gamut = true;
+    while max - min > GAMUT_EPSILON {
+        current[1] = (min + max) * 0.5;
+        let current_rgb = oklab_to_linear_srgb(oklch_to_oklab(current));
+        if min_in_gamut && gamut.contains(current_rgb) {
+            min = current[1];
+            continue;
+        }
+
+        clipped = gamut.clip(current_rgb);
+        let e = delta_e_ok(linear_srgb_to_oklab(clipped), oklch_to_oklab(current));
+        if e < JND {
+            if JND - e < GAMUT_EPSILON {
+                return clipped;
+            }
+            min_in_gamut = false;
+            min = current[1];
+        } else {
+            max = current[1];
+        }
+    }
+
+    clipped
+}
+
+/// Brings a linear sRGB color inside `gamut`, result is still linear sRGB.
+pub fn map_to_gamut(linear_srgb: [f32; 3], gamut: Gamut, mapping: GamutMapping) -> [f32; 3] {
+    if gamut.contains(linear_srgb) {
+        return linear_srgb;
+    }
+
+    let lightness = linear_srgb_to_oklab(linear_srgb)[0];
+    if lightness >= 1.0 {
+        return [1.0; 3];
+    } else if lightness <= 0.0 {
+        return [0.0; 3];
+    }
+
+    match mapping {
+        GamutMapping::Clip => gamut.clip(linear_srgb),
+        GamutMapping::OklchChroma => reduce_chroma(linear_srgb, gamut),
+        GamutMapping::Css4 => css4_gamut_map(linear_srgb, gamut),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_in_gamut_are_left_alone() {
+        let rgb = [0.2, 0.5, 0.7];
+        for gamut in Gamut::ALL {
+            assert!(gamut.contains(rgb));
+            for mapping in GamutMapping::ALL {
+                assert_eq!(map_to_gamut(rgb, gamut, mapping), rgb);
+            }
+        }
+    }
+
+    #[test]
+    fn css4_lands_in_gamut_within_a_jnd() {
+        const JND: f32 = 0.02;
+
+        for (color, source, target) in [
+            ([0.0, 1.0, 0.0], Gamut::DisplayP3, Gamut::Srgb),
+            ([1.0, 0.0, 0.0], Gamut::Rec2020, Gamut::Srgb),
+            ([0.0, 0.0, 1.0], Gamut::Rec2020, Gamut::DisplayP3),
+        ] {
+            let rgb = source.gamut_to_linear_srgb(color);
+            assert!(!target.contains(rgb));
+
+            let mapped = map_to_gamut(rgb, target, GamutMapping::Css4);
+            assert!(target.contains(mapped), "{:?}", mapped);
+
+            // Only chroma is given up, lightness and hue stay within a JND
+            let origin = oklab_to_oklch(linear_srgb_to_oklab(rgb));
+            let mapped_lab = linear_srgb_to_oklab(mapped);
+            let chroma = oklab_to_oklch(mapped_lab)[1];
+            let e = delta_e_ok(mapped_lab, oklch_to_oklab([origin[0], chroma, origin[2]]));
+            assert!(e < JND, "{:?} {}", mapped, e);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! https://bottosson.github.io/posts/oklab/
+#![allow(clippy::excessive_precision)]
+
+/// Linear sRGB to OKLab `[L, a, b]`, not clamped.
+pub fn linear_srgb_to_oklab(rgb: [f32; 3]) -> [f32; 3] {
+    let l = 0.4122214708 * rgb[0] + 0.5363325363 * rgb[1] + 0.0514459929 * rgb[2];
+    let m = 0.2119034982 * rgb[0] + 0.6806995451 * rgb[1] + 0.1073969566 * rgb[2];
+    let s = 0.0883024619 * rgb[0] + 0.2817188376 * rgb[1] + 0.6299787005 * rgb[2];
+
+    let l_ = l.cbrt();
+    let m_ = m.cbrt();
+    let s_ = s.cbrt();
+
+    [
+        0.2104542553 * l_ + 0.7936177850 * m_ - 0.0040720468 * s_,
+        1.9779984951 * l_ - 2.4285922050 * m_ + 0.4505937099 * s_,
+        0.0259040371 * l_ + 0.7827717662 * m_ - 0.8086757660 * s_,
+    ]
+}
+
+/// OKLab `[L, a, b]` to linear sRGB, not clamped.
+pub fn oklab_to_linear_srgb(lab: [f32; 3]) -> [f32; 3] {
+    let l_ = lab[0] + 0.3963377774 * lab[1] + 0.2158037573 * lab[2];
+    let m_ = lab[0] - 0.1055613458 * lab[1] - 0.0638541728 * lab[2];
+    let s_ = lab[0] - 0.0894841775 * lab[1] - 1.2914855480 * lab[2];
+
+    let l = l_ * l_ * l_;
+    let m = m_ * m_ * m_;
+    let s = s_ * s_ * s_;
+
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+}
+
+/// OKLab `[L, a, b]` to OKLCH `[L, C, h]`, hue normalized to [0, 1).
+pub fn oklab_to_oklch(lab: [f32; 3]) -> [f32; 3] {
+    let c = (lab[1] * lab[1] + lab[2] * lab[2]).sqrt();
+    let h = lab[2].atan2(lab[1]) / std::f32::consts::TAU;
+    [lab[0], c, h.rem_euclid(1.0)]
+}
+
+/// OKLCH `[L, C, h]` to OKLab `[L, a, b]`, hue normalized to [0, 1).
+pub fn oklch_to_oklab(lch: [f32; 3]) -> [f32; 3] {
+    let angle = lch[2] * std::f32::consts::TAU;
+    [lch[0], lch[1] * angle.cos(), lch[1] * angle.sin()]
+}
+
+/// Euclidean distance in OKLab, the deltaEOK of CSS Color 4.
+pub fn delta_e_ok(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let d = [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+    (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt()
+}
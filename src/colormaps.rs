@@ -0,0 +1,308 @@
+mod tables;
+
+use ecolor::{linear_f32_from_gamma_u8, Color32};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::{
+        gamut::{linear_srgb_to_color32, map_to_gamut, Gamut, GamutMapping},
+        oklab::{linear_srgb_to_oklab, oklab_to_linear_srgb, oklab_to_oklch, oklch_to_oklab},
+    },
+    common::SplineMode,
+    datatypes::control_point::ControlPoint,
+    error::Result,
+    gradient_fit::{fit_control_points, FitSettings},
+};
+
+/// Colors sampled along a colormap or template before fitting.
+const NUM_SAMPLES: usize = 64;
+
+/// Fit used for built-in colormaps and templates.
+pub const COLORMAP_FIT_SETTINGS: FitSettings = FitSettings {
+    spline_mode: SplineMode::Bezier,
+    tolerance: 2.0,
+    max_control_points: 16,
+};
+
+/// A well-known colormap as its published lookup table.
+pub struct Colormap {
+    pub name: &'static str,
+    /// Evenly spaced sRGB colors from 0 to 1
+    pub colors: &'static [u32],
+}
+
+pub const BUILTIN_COLORMAPS: [Colormap; 7] = [
+    Colormap {
+        name: "viridis",
+        colors: &tables::VIRIDIS,
+    },
+    Colormap {
+        name: "inferno",
+        colors: &tables::INFERNO,
+    },
+    Colormap {
+        name: "magma",
+        colors: &tables::MAGMA,
+    },
+    Colormap {
+        name: "plasma",
+        colors: &tables::PLASMA,
+    },
+    Colormap {
+        name: "cividis",
+        colors: &tables::CIVIDIS,
+    },
+    Colormap {
+        name: "turbo",
+        colors: &tables::TURBO,
+    },
+    Colormap {
+        name: "batlow",
+        colors: &tables::BATLOW,
+    },
+];
+
+fn hex_to_color32(hex: u32) -> Color32 {
+    let [_, r, g, b] = hex.to_be_bytes();
+    Color32::from_rgb(r, g, b)
+}
+
+fn color32_to_oklab(color: Color32) -> [f32; 3] {
+    linear_srgb_to_oklab([color.r(), color.g(), color.b()].map(linear_f32_from_gamma_u8))
+}
+
+fn oklab_to_color32(lab: [f32; 3]) -> Color32 {
+    let linear = map_to_gamut(
+        oklab_to_linear_srgb(lab),
+        Gamut::Srgb,
+        GamutMapping::OklchChroma,
+    );
+    linear_srgb_to_color32(linear)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn lerp3(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [
+        lerp(a[0], b[0], t),
+        lerp(a[1], b[1], t),
+        lerp(a[2], b[2], t),
+    ]
+}
+
+/// `NUM_SAMPLES` colors along straight OKLab lines through `stops`.
+fn sample_oklab_stops(stops: &[[f32; 3]]) -> Vec<Color32> {
+    let segments = (stops.len() - 1) as f32;
+    (0..NUM_SAMPLES)
+        .map(|i| {
+            let x = i as f32 / (NUM_SAMPLES - 1) as f32 * segments;
+            let segment = (x as usize).min(stops.len() - 2);
+            oklab_to_color32(lerp3(
+                stops[segment],
+                stops[segment + 1],
+                x - segment as f32,
+            ))
+        })
+        .collect()
+}
+
+impl Colormap {
+    pub fn samples(&self) -> Vec<Color32> {
+        let stops: Vec<[f32; 3]> = self
+            .colors
+            .iter()
+            .map(|hex| color32_to_oklab(hex_to_color32(*hex)))
+            .collect();
+        sample_oklab_stops(&stops)
+    }
+
+    pub fn control_points(&self) -> Result<Vec<ControlPoint>> {
+        Ok(fit_control_points(&self.samples(), &COLORMAP_FIT_SETTINGS)?.control_points)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum TemplateKind {
+    /// Start to end, hue takes the short way around OKLCH
+    #[default]
+    Sequential,
+    /// Start to end through a neutral middle
+    Diverging,
+    /// Start to end and back again, hue turns all the way around so both ends meet
+    Cyclic,
+}
+
+impl TemplateKind {
+    pub const ALL: [TemplateKind; 3] = [
+        TemplateKind::Sequential,
+        TemplateKind::Diverging,
+        TemplateKind::Cyclic,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TemplateKind::Sequential => "Sequential",
+            TemplateKind::Diverging => "Diverging",
+            TemplateKind::Cyclic => "Cyclic",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct TemplateSettings {
+    pub kind: TemplateKind,
+    pub start: Color32,
+    /// Only used by diverging templates
+    pub middle: Color32,
+    pub end: Color32,
+}
+
+impl Default for TemplateSettings {
+    fn default() -> Self {
+        Self {
+            kind: TemplateKind::default(),
+            start: Color32::from_rgb(0x1b, 0x1a, 0x4a),
+            middle: Color32::from_rgb(0xf2, 0xf2, 0xf2),
+            end: Color32::from_rgb(0xf5, 0xe0, 0x5b),
+        }
+    }
+}
+
+fn sample_cyclic(start: [f32; 3], end: [f32; 3]) -> Vec<Color32> {
+    let [start, end] = [start, end].map(oklab_to_oklch);
+    // Hue increases from start to end, then on to where it started one turn later
+    let end_hue = start[2] + (end[2] - start[2]).rem_euclid(1.0);
+    (0..NUM_SAMPLES)
+        .map(|i| {
+            let x = i as f32 / (NUM_SAMPLES - 1) as f32;
+            let (t, hue) = if x <= 0.5 {
+                (x * 2.0, lerp(start[2], end_hue, x * 2.0))
+            } else {
+                (2.0 - x * 2.0, lerp(end_hue, start[2] + 1.0, x * 2.0 - 1.0))
+            };
+            let lch = [lerp(start[0], end[0], t), lerp(start[1], end[1], t), hue];
+            oklab_to_color32(oklch_to_oklab([lch[0], lch[1], lch[2].rem_euclid(1.0)]))
+        })
+        .collect()
+}
+
+fn sample_sequential(start: [f32; 3], end: [f32; 3]) -> Vec<Color32> {
+    let [start, end] = [start, end].map(oklab_to_oklch);
+    let hue_delta = (end[2] - start[2] + 0.5).rem_euclid(1.0) - 0.5;
+    (0..NUM_SAMPLES)
+        .map(|i| {
+            let t = i as f32 / (NUM_SAMPLES - 1) as f32;
+            let hue = (start[2] + hue_delta * t).rem_euclid(1.0);
+            let lch = [lerp(start[0], end[0], t), lerp(start[1], end[1], t), hue];
+            oklab_to_color32(oklch_to_oklab(lch))
+        })
+        .collect()
+}
+
+pub fn template_samples(settings: &TemplateSettings) -> Vec<Color32> {
+    let start = color32_to_oklab(settings.start);
+    let end = color32_to_oklab(settings.end);
+    match settings.kind {
+        TemplateKind::Sequential => sample_sequential(start, end),
+        TemplateKind::Diverging => {
+            sample_oklab_stops(&[start, color32_to_oklab(settings.middle), end])
+        }
+        TemplateKind::Cyclic => sample_cyclic(start, end),
+    }
+}
+
+/// Control points fitted to a sequential, diverging or cyclic template between the endpoints.
+pub fn generate_template(
+    settings: &TemplateSettings,
+    spline_mode: SplineMode,
+) -> Result<Vec<ControlPoint>> {
+    let fit_settings = FitSettings {
+        spline_mode,
+        ..COLORMAP_FIT_SETTINGS
+    };
+    Ok(fit_control_points(&template_samples(settings), &fit_settings)?.control_points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::delta_e::delta_e, ui_egui::previewer::CurveSampler};
+
+    fn max_delta_e(
+        control_points: &[ControlPoint],
+        spline_mode: SplineMode,
+        samples: &[Color32],
+    ) -> f32 {
+        let sampler = CurveSampler::new(control_points, spline_mode);
+        samples
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| {
+                let x = i as f32 / (samples.len() - 1) as f32;
+                delta_e(sampler.sample(x).color(), *sample)
+            })
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn builtin_colormaps_fit_their_samples() {
+        for colormap in &BUILTIN_COLORMAPS {
+            let samples = colormap.samples();
+            assert_eq!(samples[0], hex_to_color32(colormap.colors[0]));
+            assert_eq!(
+                samples[NUM_SAMPLES - 1],
+                hex_to_color32(*colormap.colors.last().unwrap())
+            );
+
+            let control_points = colormap.control_points().unwrap();
+            assert!(control_points.len() <= COLORMAP_FIT_SETTINGS.max_control_points);
+            let error = max_delta_e(&control_points, COLORMAP_FIT_SETTINGS.spline_mode, &samples);
+            assert!(
+                error <= COLORMAP_FIT_SETTINGS.tolerance,
+                "{}: max ΔE {}",
+                colormap.name,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn templates_meet_their_endpoints() {
+        for kind in TemplateKind::ALL {
+            let settings = TemplateSettings {
+                kind,
+                ..Default::default()
+            };
+            let samples = template_samples(&settings);
+            assert_eq!(samples.len(), NUM_SAMPLES);
+            assert!(delta_e(samples[0], settings.start) < 0.5, "{:?}", kind);
+            let last = samples[NUM_SAMPLES - 1];
+            match kind {
+                TemplateKind::Cyclic => assert!(delta_e(last, settings.start) < 0.5),
+                _ => assert!(delta_e(last, settings.end) < 0.5, "{:?}", kind),
+            }
+        }
+
+        let diverging = TemplateSettings {
+            kind: TemplateKind::Diverging,
+            ..Default::default()
+        };
+        let samples = template_samples(&diverging);
+        let middle = samples[NUM_SAMPLES / 2];
+        assert!(delta_e(middle, diverging.middle) < 3.0);
+    }
+
+    #[test]
+    fn sequential_lightness_is_monotonic() {
+        let settings = TemplateSettings::default();
+        let lightness: Vec<f32> = template_samples(&settings)
+            .iter()
+            .map(|color| color32_to_oklab(*color)[0])
+            .collect();
+        for pair in lightness.windows(2) {
+            assert!(pair[0] <= pair[1] + 0.005, "{:?}", lightness);
+        }
+    }
+}
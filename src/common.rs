@@ -23,6 +23,55 @@ pub enum SplineMode {
     #[default]
     HermiteBezier,
     Polynomial,
+    /// One shared handle per segment, stored as the out tangent of the segment's first point
+    QuadraticBezier,
+}
+
+impl SplineMode {
+    /// `SplineMode::Polynomial` is still `todo!()` in `control_points_to_spline`.
+    pub const IMPLEMENTED: [SplineMode; 4] = [
+        SplineMode::Linear,
+        SplineMode::Bezier,
+        SplineMode::HermiteBezier,
+        SplineMode::QuadraticBezier,
+    ];
+}
+
+/// How the ends of the gradient behave.
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize, Default)]
+pub enum GradientMode {
+    /// Starts at the first control point and ends at the last
+    #[default]
+    Open,
+    /// The last control point continues smoothly into the first, for phase and angle data
+    Cyclic,
+    /// A neutral control point at the middle with mirrored lightness on each side, for signed
+    /// data
+    Diverging,
+}
+
+impl GradientMode {
+    pub const ALL: [GradientMode; 3] = [
+        GradientMode::Open,
+        GradientMode::Cyclic,
+        GradientMode::Diverging,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GradientMode::Open => "Open",
+            GradientMode::Cyclic => "Cyclic",
+            GradientMode::Diverging => "Diverging",
+        }
+    }
+
+    /// Diverging gradients need an odd number of control points so one sits at the middle.
+    pub fn allows_control_point_count(&self, count: usize) -> bool {
+        match self {
+            GradientMode::Diverging => count % 2 == 1,
+            _ => true,
+        }
+    }
 }
 
 #[allow(unused_macros)]
@@ -0,0 +1,246 @@
+use ecolor::Color32;
+
+use crate::{
+    color::{delta_e::delta_e, gamut::GamutMapping},
+    common::{GradientMode, SplineMode},
+    datatypes::control_point::ControlPoint,
+    ui_egui::previewer::CurveSampler,
+};
+
+/// Positions the difference plot samples, one per previewer pixel at common pane widths.
+pub const COMPARE_SAMPLES: usize = 256;
+/// Control points whose colors are closer than this are not reported as changed
+const CHANGED_DELTA_E: f32 = 0.5;
+
+/// A gradient as the previewer shows it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComparedGradient {
+    pub control_points: Vec<ControlPoint>,
+    pub spline_mode: SplineMode,
+    pub gradient_mode: GradientMode,
+}
+
+impl ComparedGradient {
+    /// The previewer's sampling, see `ui_previewer_curve`.
+    pub fn color_at(&self, mapping: GamutMapping) -> impl Fn(f32) -> Color32 {
+        let sampler = CurveSampler::with_gradient_mode(
+            &self.control_points,
+            self.spline_mode,
+            self.gradient_mode,
+        );
+        move |x| sampler.sample(x).color_gamut_mapped(mapping)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ControlPointChange {
+    /// Only in the second gradient
+    Added { index: usize },
+    /// Only in the first gradient
+    Removed { index: usize },
+    /// Color moved by `delta_e`, or only its tangents or hue direction changed
+    Changed { index: usize, delta_e: f32 },
+}
+
+impl ControlPointChange {
+    pub fn describe(&self) -> String {
+        match self {
+            ControlPointChange::Added { index } => format!("#{} added", index),
+            ControlPointChange::Removed { index } => format!("#{} removed", index),
+            ControlPointChange::Changed { index, delta_e } if *delta_e > CHANGED_DELTA_E => {
+                format!("#{} color ΔE {:.2}", index, delta_e)
+            }
+            ControlPointChange::Changed { index, .. } => format!("#{} tangents or hue", index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GradientComparison {
+    /// CIEDE2000 between the gradients at `COMPARE_SAMPLES` even steps
+    pub delta_e: Vec<f32>,
+    pub changes: Vec<ControlPointChange>,
+}
+
+impl GradientComparison {
+    pub fn new(a: &ComparedGradient, b: &ComparedGradient, mapping: GamutMapping) -> Self {
+        Self {
+            delta_e: delta_e_profile(a.color_at(mapping), b.color_at(mapping), COMPARE_SAMPLES),
+            changes: control_point_changes(&a.control_points, &b.control_points),
+        }
+    }
+
+    /// Largest difference and the gradient position it is at.
+    pub fn max_delta_e(&self) -> Option<(f32, f32)> {
+        let last = self.delta_e.len().saturating_sub(1).max(1) as f32;
+        self.delta_e
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, delta_e)| (*delta_e, i as f32 / last))
+    }
+
+    pub fn mean_delta_e(&self) -> f32 {
+        self.delta_e.iter().sum::<f32>() / self.delta_e.len().max(1) as f32
+    }
+}
+
+/// Difference between two gradients at `num_samples` even steps from 0 to 1.
+pub fn delta_e_profile(
+    a: impl Fn(f32) -> Color32,
+    b: impl Fn(f32) -> Color32,
+    num_samples: usize,
+) -> Vec<f32> {
+    let last = num_samples.saturating_sub(1).max(1) as f32;
+    (0..num_samples)
+        .map(|i| {
+            let x = i as f32 / last;
+            delta_e(a(x), b(x))
+        })
+        .collect()
+}
+
+fn same_color(a: &ControlPoint, b: &ControlPoint) -> bool {
+    delta_e(a.val().color(), b.val().color()) <= CHANGED_DELTA_E
+}
+
+/// Index pairs of the longest common subsequence of `a` and `b` by color, in order.
+fn matching_colors(a: &[ControlPoint], b: &[ControlPoint]) -> Vec<(usize, usize)> {
+    // lengths[i][j] is the LCS length of a[i..] and b[j..]
+    let mut lengths = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if same_color(&a[i], &b[j]) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut matches = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if same_color(&a[i], &b[j]) && lengths[i][j] == lengths[i + 1][j + 1] + 1 {
+            matches.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
+
+/// Control points that differ between `a` and `b`. Points are aligned by color so an inserted
+/// or removed point doesn't shift every later one, unmatched points between two aligned ones
+/// are paired up in order as changed. Indices of changed and added points are into `b`.
+pub fn control_point_changes(a: &[ControlPoint], b: &[ControlPoint]) -> Vec<ControlPointChange> {
+    let changed = |before: &ControlPoint, after: &ControlPoint, index: usize| {
+        let delta_e = delta_e(before.val().color(), after.val().color());
+        (delta_e > CHANGED_DELTA_E
+            || before.tangents() != after.tangents()
+            || before.hue_interpolation() != after.hue_interpolation())
+        .then_some(ControlPointChange::Changed { index, delta_e })
+    };
+
+    let mut changes = Vec::new();
+    let (mut start_a, mut start_b) = (0, 0);
+    let end = (a.len(), b.len());
+    for (match_a, match_b) in matching_colors(a, b).into_iter().chain([end]) {
+        // Unmatched points up to the next color match
+        let (gap_a, gap_b) = (start_a..match_a, start_b..match_b);
+        let paired = gap_a.len().min(gap_b.len());
+        for (index_a, index) in gap_a.clone().zip(gap_b.clone()) {
+            changes.extend(changed(&a[index_a], &b[index], index));
+        }
+        changes.extend(
+            gap_a
+                .skip(paired)
+                .map(|index| ControlPointChange::Removed { index }),
+        );
+        changes.extend(
+            gap_b
+                .skip(paired)
+                .map(|index| ControlPointChange::Added { index }),
+        );
+
+        if (match_a, match_b) != end {
+            changes.extend(changed(&a[match_a], &b[match_b], match_b));
+        }
+        (start_a, start_b) = (match_a + 1, match_b + 1);
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{compared_gradient, wrapping_control_points};
+
+    #[test]
+    fn identical_gradients_do_not_differ() {
+        let a = compared_gradient(wrapping_control_points());
+        let comparison = GradientComparison::new(&a, &a.clone(), GamutMapping::Clip);
+        assert_eq!(comparison.delta_e.len(), COMPARE_SAMPLES);
+        assert!(comparison.delta_e.iter().all(|delta_e| *delta_e == 0.0));
+        assert!(comparison.changes.is_empty());
+    }
+
+    #[test]
+    fn difference_peaks_at_the_changed_control_point() {
+        let a = compared_gradient(wrapping_control_points());
+        let mut b = a.clone();
+        b.control_points[2].val_mut()[1] = 0.9;
+
+        let comparison = GradientComparison::new(&a, &b, GamutMapping::Clip);
+        let (max_delta_e, max_x) = comparison.max_delta_e().unwrap();
+        assert!((max_x - 2.0 / 3.0).abs() < 0.02, "peak at {}", max_x);
+        assert!(max_delta_e > CHANGED_DELTA_E);
+        assert!(comparison.delta_e[0] < 1e-3);
+        assert_eq!(
+            comparison.changes,
+            vec![ControlPointChange::Changed {
+                index: 2,
+                delta_e: max_delta_e,
+            }]
+        );
+    }
+
+    #[test]
+    fn inserted_and_removed_control_points_do_not_shift_later_ones() {
+        let control_points = wrapping_control_points();
+        let mut inserted = control_points.clone();
+        let mut new_point = control_points[1].clone();
+        new_point.val_mut()[1] = 0.1;
+        inserted.insert(1, new_point);
+        assert_eq!(
+            control_point_changes(&control_points, &inserted),
+            vec![ControlPointChange::Added { index: 1 }]
+        );
+
+        let mut removed = control_points.clone();
+        removed.remove(2);
+        assert_eq!(
+            control_point_changes(&control_points, &removed),
+            vec![ControlPointChange::Removed { index: 2 }]
+        );
+    }
+
+    #[test]
+    fn added_and_removed_control_points_are_reported() {
+        let control_points = wrapping_control_points();
+        let changes = control_point_changes(&control_points[..3], &control_points);
+        assert_eq!(changes, vec![ControlPointChange::Added { index: 3 }]);
+        let changes = control_point_changes(&control_points, &control_points[..2]);
+        assert_eq!(
+            changes,
+            vec![
+                ControlPointChange::Removed { index: 2 },
+                ControlPointChange::Removed { index: 3 },
+            ]
+        );
+    }
+}
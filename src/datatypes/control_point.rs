@@ -12,12 +12,110 @@ pub type ControlPointTangent = ControlPointValue;
 pub type ControlPointTangents = [Option<ControlPointTangent>; 2];
 pub type ControlPointT = f32;
 
+/// How the two tangent handles of a control point constrain each other.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum TangentMode {
+    /// Handles move independently
+    #[default]
+    Free,
+    /// Handles stay opposite each other, each keeps its own length
+    Aligned,
+    /// Handles stay opposite each other with the same length
+    Mirrored,
+    /// Handles are computed from the neighbouring control points for a smooth curve
+    AutoSmooth,
+    /// Handles point at the neighbouring control points, giving straight segments and corners
+    Vector,
+}
+
+impl TangentMode {
+    pub const ALL: [TangentMode; 5] = [
+        TangentMode::Free,
+        TangentMode::Aligned,
+        TangentMode::Mirrored,
+        TangentMode::AutoSmooth,
+        TangentMode::Vector,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TangentMode::Free => "Free",
+            TangentMode::Aligned => "Aligned",
+            TangentMode::Mirrored => "Mirrored",
+            TangentMode::AutoSmooth => "Auto smooth",
+            TangentMode::Vector => "Vector",
+        }
+    }
+}
+
+/// Which way around the hue circle a segment travels, like CSS Color 4 `hue-interpolation-method`.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum HueInterpolation {
+    /// The arc of at most half a turn
+    #[default]
+    Shortest,
+    /// The arc of at least half a turn
+    Longest,
+    /// Always increasing hue (CSS `increasing`)
+    Clockwise,
+    /// Always decreasing hue (CSS `decreasing`)
+    CounterClockwise,
+}
+
+impl HueInterpolation {
+    pub const ALL: [HueInterpolation; 4] = [
+        HueInterpolation::Shortest,
+        HueInterpolation::Longest,
+        HueInterpolation::Clockwise,
+        HueInterpolation::CounterClockwise,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HueInterpolation::Shortest => "Shortest",
+            HueInterpolation::Longest => "Longest",
+            HueInterpolation::Clockwise => "Clockwise",
+            HueInterpolation::CounterClockwise => "Counter-clockwise",
+        }
+    }
+
+    /// `to` moved by whole turns so that going from `from` to it in a straight line travels
+    /// the arc this method picks. Equal hues never make a full turn.
+    pub fn unwrap(&self, from: f32, to: f32) -> f32 {
+        let delta = (to - from).rem_euclid(1.0);
+        let delta = match self {
+            _ if delta == 0.0 => 0.0,
+            HueInterpolation::Shortest if delta > 0.5 => delta - 1.0,
+            HueInterpolation::Longest if delta < 0.5 => delta - 1.0,
+            HueInterpolation::CounterClockwise => delta - 1.0,
+            _ => delta,
+        };
+        from + delta
+    }
+
+    /// Method whose `unwrap` travels the unwrapped hue difference `delta`, for |delta| < 1.
+    pub fn for_delta(delta: f32) -> Self {
+        if delta.abs() <= 0.5 {
+            HueInterpolation::Shortest
+        } else if delta > 0.0 {
+            HueInterpolation::Clockwise
+        } else {
+            HueInterpolation::CounterClockwise
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct ControlPointStorage {
     pub val: ControlPointValue,
     pub t: ControlPointT,
     pub tangents: ControlPointTangents,
+    #[serde(default)]
+    pub tangent_mode: TangentMode,
+    /// Hue direction of the segment starting at this control point
+    #[serde(default)]
+    pub hue_interpolation: HueInterpolation,
 }
 
 impl ControlPointStorage {
@@ -26,6 +124,8 @@ impl ControlPointStorage {
             val: ControlPointValue::default(),
             t: 0.0,
             tangents: [None; 2],
+            tangent_mode: TangentMode::Free,
+            hue_interpolation: HueInterpolation::Shortest,
         }
     }
 }
@@ -94,7 +194,106 @@ impl ControlPoint {
         &mut self.storage_mut().tangents
     }
 
+    pub fn tangent_mode(&self) -> TangentMode {
+        self.storage().tangent_mode
+    }
+    pub fn tangent_mode_mut(&mut self) -> &mut TangentMode {
+        &mut self.storage_mut().tangent_mode
+    }
+
+    pub fn hue_interpolation(&self) -> HueInterpolation {
+        self.storage().hue_interpolation
+    }
+    pub fn hue_interpolation_mut(&mut self) -> &mut HueInterpolation {
+        &mut self.storage_mut().hue_interpolation
+    }
+
     pub fn flip_tangents(&mut self) {
         self.tangents_mut().swap(0, 1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::arb_hue_interpolation;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn hue_unwrap_keeps_the_hue(
+            method in arb_hue_interpolation(),
+            from in -2.0f32..2.0,
+            to in 0.0f32..1.0,
+        ) {
+            let unwrapped = method.unwrap(from, to);
+            let diff = (unwrapped - to).rem_euclid(1.0);
+            prop_assert!(diff.min(1.0 - diff) < 1e-4);
+        }
+
+        #[test]
+        fn hue_unwrap_travels_the_picked_arc(
+            method in arb_hue_interpolation(),
+            from in 0.0f32..1.0,
+            to in 0.0f32..1.0,
+        ) {
+            let delta = method.unwrap(from, to) - from;
+            prop_assert!(delta.abs() < 1.0);
+            let on_arc = match method {
+                HueInterpolation::Shortest => delta.abs() <= 0.5,
+                HueInterpolation::Longest => delta == 0.0 || delta.abs() >= 0.5,
+                HueInterpolation::Clockwise => delta >= 0.0,
+                HueInterpolation::CounterClockwise => delta <= 0.0,
+            };
+            prop_assert!(on_arc, "{:?} {} -> {} travelled {}", method, from, to, delta);
+        }
+    }
+
+    #[test]
+    fn hue_unwrap_examples() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-6;
+        assert!(close(HueInterpolation::Shortest.unwrap(0.9, 0.1), 1.1));
+        assert!(close(HueInterpolation::Longest.unwrap(0.9, 0.1), 0.1));
+        assert!(close(HueInterpolation::Clockwise.unwrap(0.3, 0.1), 1.1));
+        assert!(close(
+            HueInterpolation::CounterClockwise.unwrap(0.1, 0.3),
+            -0.7
+        ));
+        for method in HueInterpolation::ALL {
+            assert_eq!(method.unwrap(0.4, 0.4), 0.4);
+        }
+    }
+
+    #[test]
+    fn hue_unwrap_continues_from_unwrapped_hues() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+        // `from` is already a turn or more away, `to` is placed next to it
+        assert!(close(HueInterpolation::Shortest.unwrap(1.9, 0.1), 2.1));
+        assert!(close(HueInterpolation::Shortest.unwrap(-0.9, 0.9), -1.1));
+        assert!(close(HueInterpolation::Longest.unwrap(1.9, 0.1), 1.1));
+        assert!(close(HueInterpolation::Clockwise.unwrap(-0.2, 0.7), 0.7));
+        assert!(close(
+            HueInterpolation::CounterClockwise.unwrap(2.25, 0.5),
+            1.5
+        ));
+    }
+
+    #[test]
+    fn hue_unwrap_half_turn_goes_clockwise() {
+        for method in [HueInterpolation::Shortest, HueInterpolation::Longest] {
+            assert_eq!(method.unwrap(0.25, 0.75), 0.75, "{:?}", method);
+        }
+        assert_eq!(HueInterpolation::CounterClockwise.unwrap(0.25, 0.75), -0.25);
+    }
+
+    #[test]
+    fn old_control_points_deserialize_with_defaults() {
+        let json = r#"{"ControlPointSimple":{"val":{"val":[0.5,0.5,0.5]},"t":0.0,"tangents":[null,null]}}"#;
+        let control_point: ControlPoint = serde_json::from_str(json).unwrap();
+        assert_eq!(control_point.tangent_mode(), TangentMode::Free);
+        assert_eq!(
+            control_point.hue_interpolation(),
+            HueInterpolation::Shortest
+        );
+    }
+}
@@ -4,7 +4,9 @@ use ecolor::{Color32, HsvaGamma};
 use eframe::egui::{Pos2, Vec2};
 use serde::{Deserialize, Serialize};
 
-use crate::math::{hue_abs_distance, hue_lerp};
+use crate::color::gamut::{
+    hsv_to_linear_srgb, linear_srgb_to_color32, map_to_gamut, Gamut, GamutMapping,
+};
 
 type HsvKeyValueInnerType = [f32; 3];
 #[repr(C)]
@@ -49,6 +51,16 @@ impl HsvKeyValue {
         self.hsv().into()
     }
 
+    /// Like `color`, but out of sRGB values are brought into gamut with `mapping`
+    /// instead of being clamped per channel.
+    pub fn color_gamut_mapped(&self, mapping: GamutMapping) -> Color32 {
+        let linear_srgb = hsv_to_linear_srgb(self);
+        if Gamut::Srgb.contains(linear_srgb) {
+            return self.color();
+        }
+        linear_srgb_to_color32(map_to_gamut(linear_srgb, Gamut::Srgb, mapping))
+    }
+
     pub fn hsv(&self) -> HsvaGamma {
         HsvaGamma {
             h: self[2].rem_euclid(1.0),
@@ -224,6 +236,8 @@ impl std::ops::Div<HsvKeyValue> for HsvKeyValue {
     }
 }
 
+/// Hue is interpolated as a plain number, `flatten_control_points` unwraps it beforehand so the
+/// segments travel the intended arc.
 impl splines::interpolate::Interpolate<f32> for HsvKeyValue {
     fn step(t: f32, threshold: f32, a: Self, b: Self) -> Self {
         if t < threshold {
@@ -243,7 +257,7 @@ impl splines::interpolate::Interpolate<f32> for HsvKeyValue {
             val: [
                 a.val[0] * (1. - t) + b.val[0] * t,
                 a.val[1] * (1. - t) + b.val[1] * t,
-                hue_lerp(a.val[2], b.val[2], t),
+                a.val[2] * (1. - t) + b.val[2] * t,
             ],
         }
     }
@@ -284,29 +298,12 @@ impl splines::interpolate::Interpolate<f32> for HsvKeyValue {
     }
 
     fn cubic_bezier(t: f32, a: Self, u: Self, v: Self, b: Self) -> Self {
-        // Choose direction
-        let res = if hue_abs_distance(a[2], b[2]) < 0.5 {
-            let one_t = 1. - t;
-            let one_t2 = one_t * one_t;
-            let one_t3 = one_t2 * one_t;
-            let t2 = t * t;
-
-            let res = a * one_t3 + (u * one_t2 * t + v * one_t * t2) * 3. + b * t2 * t;
-            res
-        } else {
-            // Other way
-            let one_t = 1. - t;
-            let one_t2 = one_t * one_t;
-            let one_t3 = one_t2 * one_t;
-            let t2 = t * t;
-
-            let dir_res = if a[2] < b[2] { 1.0 } else { -1.0 };
-
-            let mut res = a * one_t3 + (u * one_t2 * t + v * one_t * t2) * 3. + b * t2 * t;
-            res[2] = res[2] - dir_res;
-            HsvKeyValue::new(one_t, one_t, one_t)
-        };
-        res
+        let one_t = 1. - t;
+        let one_t2 = one_t * one_t;
+        let one_t3 = one_t2 * one_t;
+        let t2 = t * t;
+
+        a * one_t3 + (u * one_t2 * t + v * one_t * t2) * 3. + b * t2 * t
     }
 
     fn cubic_bezier_mirrored(t: f32, a: Self, u: Self, v: Self, b: Self) -> Self {
@@ -43,4 +43,22 @@ impl From<arboard::Error> for ZError {
     }
 }
 
+impl From<image::ImageError> for ZError {
+    fn from(err: image::ImageError) -> ZError {
+        ZError::Message(err.to_string())
+    }
+}
+
+impl From<png::EncodingError> for ZError {
+    fn from(err: png::EncodingError) -> ZError {
+        ZError::Message(err.to_string())
+    }
+}
+
+impl From<exr::error::Error> for ZError {
+    fn from(err: exr::error::Error) -> ZError {
+        ZError::Message(err.to_string())
+    }
+}
+
 pub type Result<T> = std::result::Result<T, ZError>;
@@ -0,0 +1,385 @@
+#![allow(clippy::excessive_precision)]
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use exr::{
+    meta::attribute::Chromaticities,
+    prelude::{Image, SpecificChannels, Vec2, WritableImage},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::gamut::{hsv_to_linear_srgb, map_to_gamut, Gamut, GamutMapping},
+    datatypes::hsv_key_value::HsvKeyValue,
+    error::Result,
+};
+
+/// SDR reference white in nits (ITU-R BT.2408), linear 1.0 is encoded at this level for PQ.
+const PQ_REFERENCE_WHITE_NITS: f32 = 203.0;
+const PQ_MAX_NITS: f32 = 10000.0;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum TransferFunction {
+    #[default]
+    Linear,
+    /// sRGB piecewise curve, also used by Display P3
+    Srgb,
+    /// SMPTE ST 2084
+    Pq,
+    /// ARIB STD-B67 / BT.2100
+    Hlg,
+}
+
+impl TransferFunction {
+    pub const ALL: [TransferFunction; 4] = [
+        TransferFunction::Linear,
+        TransferFunction::Srgb,
+        TransferFunction::Pq,
+        TransferFunction::Hlg,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransferFunction::Linear => "Linear",
+            TransferFunction::Srgb => "sRGB",
+            TransferFunction::Pq => "PQ (ST 2084)",
+            TransferFunction::Hlg => "HLG",
+        }
+    }
+
+    /// Transfer characteristics code point of ITU-T H.273, as used by the PNG cICP chunk.
+    fn cicp_code_point(&self) -> u8 {
+        match self {
+            TransferFunction::Linear => 8,
+            TransferFunction::Srgb => 13,
+            TransferFunction::Pq => 16,
+            TransferFunction::Hlg => 18,
+        }
+    }
+
+    pub fn encode(&self, linear: f32) -> f32 {
+        match self {
+            TransferFunction::Linear => linear,
+            TransferFunction::Srgb => {
+                let linear = linear.max(0.0);
+                if linear <= 0.0031308 {
+                    linear * 12.92
+                } else {
+                    1.055 * linear.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Pq => {
+                const M1: f32 = 2610.0 / 16384.0;
+                const M2: f32 = 2523.0 / 4096.0 * 128.0;
+                const C1: f32 = 3424.0 / 4096.0;
+                const C2: f32 = 2413.0 / 4096.0 * 32.0;
+                const C3: f32 = 2392.0 / 4096.0 * 32.0;
+                let y = (linear.max(0.0) * PQ_REFERENCE_WHITE_NITS / PQ_MAX_NITS).min(1.0);
+                let y_m1 = y.powf(M1);
+                ((C1 + C2 * y_m1) / (1.0 + C3 * y_m1)).powf(M2)
+            }
+            TransferFunction::Hlg => {
+                const A: f32 = 0.17883277;
+                const B: f32 = 0.28466892;
+                const C: f32 = 0.55991073;
+                let linear = linear.max(0.0);
+                if linear <= 1.0 / 12.0 {
+                    (3.0 * linear).sqrt()
+                } else {
+                    A * (12.0 * linear - B).ln() + C
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum ExportFormat {
+    /// One `r, g, b` float row per sample
+    #[default]
+    Csv,
+    /// Interleaved little-endian f32 RGB
+    RawF32,
+    /// Interleaved little-endian f16 RGB
+    RawF16,
+    /// 16-bit per channel PNG ramp
+    Png16,
+    /// 32-bit float OpenEXR ramp
+    Exr,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 5] = [
+        ExportFormat::Csv,
+        ExportFormat::RawF32,
+        ExportFormat::RawF16,
+        ExportFormat::Png16,
+        ExportFormat::Exr,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV (f32)",
+            ExportFormat::RawF32 => "Raw f32",
+            ExportFormat::RawF16 => "Raw f16",
+            ExportFormat::Png16 => "PNG 16-bit",
+            ExportFormat::Exr => "OpenEXR",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::RawF32 | ExportFormat::RawF16 => "bin",
+            ExportFormat::Png16 => "png",
+            ExportFormat::Exr => "exr",
+        }
+    }
+
+    pub fn is_image(&self) -> bool {
+        matches!(self, ExportFormat::Png16 | ExportFormat::Exr)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ExportSettings {
+    pub num_samples: usize,
+    pub primaries: Gamut,
+    pub transfer_function: TransferFunction,
+    pub mapping: GamutMapping,
+    pub format: ExportFormat,
+    pub image_height: u32,
+    /// Output path without extension, the format decides it
+    pub file_stem: String,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            num_samples: 256,
+            primaries: Gamut::Srgb,
+            transfer_function: TransferFunction::Linear,
+            mapping: GamutMapping::Css4,
+            format: ExportFormat::default(),
+            image_height: 16,
+            file_stem: "gradient_export".to_string(),
+        }
+    }
+}
+
+/// Colour primaries code point of ITU-T H.273, as used by the PNG cICP chunk.
+fn cicp_primaries(gamut: Gamut) -> u8 {
+    match gamut {
+        Gamut::Srgb => 1,
+        Gamut::Rec2020 => 9,
+        Gamut::DisplayP3 => 12,
+    }
+}
+
+/// CIE xy of the red, green and blue primaries and the D65 white point.
+fn chromaticities(gamut: Gamut) -> Chromaticities {
+    let [red, green, blue] = match gamut {
+        Gamut::Srgb => [(0.64, 0.33), (0.30, 0.60), (0.15, 0.06)],
+        Gamut::DisplayP3 => [(0.680, 0.320), (0.265, 0.690), (0.150, 0.060)],
+        Gamut::Rec2020 => [(0.708, 0.292), (0.170, 0.797), (0.131, 0.046)],
+    };
+    Chromaticities {
+        red: Vec2(red.0, red.1),
+        green: Vec2(green.0, green.1),
+        blue: Vec2(blue.0, blue.1),
+        white: Vec2(0.3127, 0.3290),
+    }
+}
+
+impl ExportSettings {
+    pub fn file_path(&self) -> String {
+        format!("{}.{}", self.file_stem, self.format.extension())
+    }
+
+    /// Gamut maps the sample into the target primaries and applies the transfer function.
+    pub fn encode(&self, sample: &HsvKeyValue) -> [f32; 3] {
+        let mapped = map_to_gamut(hsv_to_linear_srgb(sample), self.primaries, self.mapping);
+        self.primaries
+            .linear_srgb_to_gamut(mapped)
+            .map(|c| self.transfer_function.encode(c))
+    }
+}
+
+pub fn export_gradient(samples: &[HsvKeyValue], settings: &ExportSettings) -> Result<String> {
+    if samples.is_empty() {
+        return Err("Nothing to export".to_string().into());
+    }
+
+    let encoded: Vec<[f32; 3]> = samples.iter().map(|s| settings.encode(s)).collect();
+    let file_path = settings.file_path();
+
+    match settings.format {
+        ExportFormat::Csv => {
+            let mut writer = BufWriter::new(File::create(&file_path)?);
+            writeln!(
+                writer,
+                "# primaries: {}, transfer: {}",
+                settings.primaries.label(),
+                settings.transfer_function.label()
+            )?;
+            writeln!(writer, "r,g,b")?;
+            for [r, g, b] in &encoded {
+                writeln!(writer, "{},{},{}", r, g, b)?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::RawF32 => {
+            let bytes: Vec<u8> = encoded
+                .iter()
+                .flatten()
+                .flat_map(|c| c.to_le_bytes())
+                .collect();
+            File::create(&file_path)?.write_all(&bytes)?;
+        }
+        ExportFormat::RawF16 => {
+            let bytes: Vec<u8> = encoded
+                .iter()
+                .flatten()
+                .flat_map(|c| half::f16::from_f32(*c).to_le_bytes())
+                .collect();
+            File::create(&file_path)?.write_all(&bytes)?;
+        }
+        ExportFormat::Png16 => {
+            let width = encoded.len() as u32;
+            let height = settings.image_height.max(1);
+            let row: Vec<u8> = encoded
+                .iter()
+                .flatten()
+                .flat_map(|c| ((c.clamp(0.0, 1.0) * 65535.0).round() as u16).to_be_bytes())
+                .collect();
+
+            let mut encoder =
+                png::Encoder::new(BufWriter::new(File::create(&file_path)?), width, height);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Sixteen);
+            let mut writer = encoder.write_header()?;
+            // Primaries and transfer function, full range RGB
+            writer.write_chunk(
+                png::chunk::cICP,
+                &[
+                    cicp_primaries(settings.primaries),
+                    settings.transfer_function.cicp_code_point(),
+                    0,
+                    1,
+                ],
+            )?;
+            writer.write_image_data(&row.repeat(height as usize))?;
+            writer.finish()?;
+        }
+        ExportFormat::Exr => {
+            // OpenEXR has no transfer function attribute, only the primaries are recorded
+            let mut image = Image::from_channels(
+                (encoded.len(), settings.image_height.max(1) as usize),
+                SpecificChannels::rgb(|Vec2(x, _)| {
+                    let [r, g, b] = encoded[x];
+                    (r, g, b)
+                }),
+            );
+            image.attributes.chromaticities = Some(chromaticities(settings.primaries));
+            image.write().to_file(&file_path)?;
+        }
+    }
+
+    log::info!(
+        "Exported {} samples ({}, {}) to {}",
+        samples.len(),
+        settings.primaries.label(),
+        settings.transfer_function.label(),
+        file_path
+    );
+
+    Ok(file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(found: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (found - expected).abs() <= tolerance,
+            "{} vs {}",
+            found,
+            expected
+        );
+    }
+
+    fn temp_settings(format: ExportFormat) -> ExportSettings {
+        ExportSettings {
+            num_samples: 4,
+            primaries: Gamut::Rec2020,
+            transfer_function: TransferFunction::Pq,
+            format,
+            image_height: 2,
+            file_stem: std::env::temp_dir()
+                .join(format!("z_color_picker_export_{}", std::process::id()))
+                .to_string_lossy()
+                .to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn samples() -> Vec<HsvKeyValue> {
+        vec![
+            HsvKeyValue::new(0.8, 0.9, 0.1),
+            HsvKeyValue::new(0.5, 0.5, 0.6),
+        ]
+    }
+
+    #[test]
+    fn pq_matches_st_2084() {
+        let pq = TransferFunction::Pq;
+        assert_close(pq.encode(PQ_MAX_NITS / PQ_REFERENCE_WHITE_NITS), 1.0, 1e-5);
+        assert_close(pq.encode(100.0 / PQ_REFERENCE_WHITE_NITS), 0.5081, 1e-3);
+        assert_close(pq.encode(0.0), 0.0, 1e-5);
+    }
+
+    #[test]
+    fn hlg_matches_bt_2100() {
+        let hlg = TransferFunction::Hlg;
+        assert_close(hlg.encode(0.0), 0.0, 1e-6);
+        assert_close(hlg.encode(1.0 / 12.0), 0.5, 1e-5);
+        assert_close(hlg.encode(1.0), 1.0, 1e-5);
+    }
+
+    #[test]
+    fn png_records_primaries_and_transfer() {
+        let settings = temp_settings(ExportFormat::Png16);
+        let exported = export_gradient(&samples(), &settings);
+        let decoded = File::open(settings.file_path()).map(|file| {
+            let decoder = png::Decoder::new(std::io::BufReader::new(file));
+            decoder
+                .read_info()
+                .map(|reader| reader.info().coding_independent_code_points)
+        });
+        let _ = std::fs::remove_file(settings.file_path());
+
+        exported.unwrap();
+        let cicp = decoded.unwrap().unwrap().unwrap();
+        assert_eq!(cicp.color_primaries, 9);
+        assert_eq!(cicp.transfer_function, 16);
+        assert_eq!(cicp.matrix_coefficients, 0);
+        assert!(cicp.is_video_full_range_image);
+    }
+
+    #[test]
+    fn exr_records_primaries() {
+        let settings = temp_settings(ExportFormat::Exr);
+        let exported = export_gradient(&samples(), &settings);
+        let meta = exr::meta::MetaData::read_from_file(settings.file_path(), false);
+        let _ = std::fs::remove_file(settings.file_path());
+
+        exported.unwrap();
+        let chromaticities = meta.unwrap().headers[0]
+            .shared_attributes
+            .chromaticities
+            .unwrap();
+        assert_eq!(chromaticities, super::chromaticities(Gamut::Rec2020));
+    }
+}
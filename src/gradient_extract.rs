@@ -0,0 +1,470 @@
+use ecolor::{linear_f32_from_gamma_u8, Color32, HsvaGamma, Rgba};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::{
+        gamut::linear_srgb_to_color32,
+        oklab::{linear_srgb_to_oklab, oklab_to_linear_srgb},
+    },
+    common::SplineMode,
+    datatypes::{
+        control_point::{create_tangent_for_control_point, ControlPoint, TangentMode},
+        hsv_key_value::HsvKeyValue,
+    },
+    error::Result,
+    gradient_fit::{fit_control_points, FitResult, FitSettings},
+    ui_egui::tangents::apply_tangent_modes,
+};
+
+/// Pixels clustered at most, larger images are sampled with a stride.
+const MAX_CLUSTERED_PIXELS: usize = 20_000;
+const K_MEANS_ITERATIONS: usize = 24;
+/// Pixels more transparent than this are left out.
+const MIN_ALPHA: u8 = 128;
+/// Samples fitted between two neighbouring ordered colors.
+const SAMPLES_PER_SEGMENT: usize = 16;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum ColorOrdering {
+    /// Darkest to lightest
+    #[default]
+    Lightness,
+    /// Shortest path through all colors in OKLab, from its darker end
+    ShortestPath,
+}
+
+impl ColorOrdering {
+    pub const ALL: [ColorOrdering; 2] = [ColorOrdering::Lightness, ColorOrdering::ShortestPath];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ColorOrdering::Lightness => "Lightness",
+            ColorOrdering::ShortestPath => "Shortest path",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct ExtractSettings {
+    pub num_colors: usize,
+    pub ordering: ColorOrdering,
+    /// Largest CIEDE2000 difference from the ordered colors the fit may leave
+    pub tolerance: f32,
+}
+
+impl Default for ExtractSettings {
+    fn default() -> Self {
+        Self {
+            num_colors: 5,
+            ordering: ColorOrdering::default(),
+            tolerance: 2.0,
+        }
+    }
+}
+
+/// A gradient fitted to the ordered dominant colors of an image.
+#[derive(Debug, Clone)]
+pub struct ExtractedGradient {
+    pub colors: Vec<DominantColor>,
+    pub control_points: Vec<ControlPoint>,
+    pub spline_mode: SplineMode,
+    pub max_delta_e: f32,
+}
+
+/// A cluster center in OKLab and how many pixels it stands for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DominantColor {
+    pub lab: [f32; 3],
+    pub weight: usize,
+}
+
+fn distance_squared(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (0..3).map(|c| (a[c] - b[c]) * (a[c] - b[c])).sum()
+}
+
+fn nearest(centers: &[[f32; 3]], lab: &[f32; 3]) -> usize {
+    (0..centers.len())
+        .min_by(|a, b| {
+            distance_squared(&centers[*a], lab).total_cmp(&distance_squared(&centers[*b], lab))
+        })
+        .unwrap()
+}
+
+fn image_to_oklab(image: &RgbaImage) -> Vec<[f32; 3]> {
+    let num_pixels = (image.width() * image.height()) as usize;
+    let stride = num_pixels.div_ceil(MAX_CLUSTERED_PIXELS).max(1);
+    image
+        .pixels()
+        .step_by(stride)
+        .filter(|pixel| pixel.0[3] >= MIN_ALPHA)
+        .map(|pixel| {
+            let [r, g, b, _] = pixel.0;
+            linear_srgb_to_oklab([r, g, b].map(linear_f32_from_gamma_u8))
+        })
+        .collect()
+}
+
+/// k-means in OKLab with deterministic k-means++ style seeding (always the farthest pixel), so
+/// the same image always gives the same colors. Sorted by weight, heaviest first.
+pub fn dominant_colors(image: &RgbaImage, num_colors: usize) -> Vec<DominantColor> {
+    let pixels = image_to_oklab(image);
+    if pixels.is_empty() || num_colors == 0 {
+        return Vec::new();
+    }
+
+    let mean = pixels.iter().fold([0.0; 3], |acc, p| {
+        [acc[0] + p[0], acc[1] + p[1], acc[2] + p[2]]
+    });
+    let mean = mean.map(|c| c / pixels.len() as f32);
+    let mut centers = vec![pixels[nearest(&pixels, &mean)]];
+    while centers.len() < num_colors {
+        let farthest = pixels
+            .iter()
+            .max_by(|a, b| {
+                let dist_a = distance_squared(&centers[nearest(&centers, a)], a);
+                let dist_b = distance_squared(&centers[nearest(&centers, b)], b);
+                dist_a.total_cmp(&dist_b)
+            })
+            .unwrap();
+        if centers.contains(farthest) {
+            // Fewer distinct colors than asked for
+            break;
+        }
+        centers.push(*farthest);
+    }
+
+    let mut assignments = vec![0; pixels.len()];
+    for _ in 0..K_MEANS_ITERATIONS {
+        let mut changed = false;
+        for (pixel, assignment) in pixels.iter().zip(assignments.iter_mut()) {
+            let cluster = nearest(&centers, pixel);
+            changed |= cluster != *assignment;
+            *assignment = cluster;
+        }
+
+        let mut sums = vec![[0.0f32; 3]; centers.len()];
+        let mut counts = vec![0usize; centers.len()];
+        for (pixel, cluster) in pixels.iter().zip(&assignments) {
+            for c in 0..3 {
+                sums[*cluster][c] += pixel[c];
+            }
+            counts[*cluster] += 1;
+        }
+        for ((center, sum), count) in centers.iter_mut().zip(&sums).zip(&counts) {
+            if *count > 0 {
+                *center = sum.map(|c| c / *count as f32);
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    let mut weights = vec![0usize; centers.len()];
+    for cluster in &assignments {
+        weights[*cluster] += 1;
+    }
+    let mut colors: Vec<DominantColor> = centers
+        .into_iter()
+        .zip(weights)
+        .filter(|(_, weight)| *weight > 0)
+        .map(|(lab, weight)| DominantColor { lab, weight })
+        .collect();
+    colors.sort_by_key(|color| std::cmp::Reverse(color.weight));
+    colors
+}
+
+fn path_length(colors: &[DominantColor]) -> f32 {
+    colors
+        .windows(2)
+        .map(|pair| distance_squared(&pair[0].lab, &pair[1].lab).sqrt())
+        .sum()
+}
+
+/// Greedy nearest neighbour from the darkest color, then 2-opt until no reversal shortens it.
+/// The path is flipped if needed so it still starts at the darker end.
+fn order_shortest_path(colors: &mut [DominantColor]) {
+    for i in 1..colors.len() {
+        let current = colors[i - 1].lab;
+        let next = (i..colors.len())
+            .min_by(|a, b| {
+                distance_squared(&colors[*a].lab, &current)
+                    .total_cmp(&distance_squared(&colors[*b].lab, &current))
+            })
+            .unwrap();
+        colors.swap(i, next);
+    }
+
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 0..colors.len() {
+            for j in (i + 2)..=colors.len() {
+                let before = path_length(colors);
+                colors[i..j].reverse();
+                if path_length(colors) + 1e-6 < before {
+                    improved = true;
+                } else {
+                    colors[i..j].reverse();
+                }
+            }
+        }
+    }
+
+    if colors.first().map(|c| c.lab[0]) > colors.last().map(|c| c.lab[0]) {
+        colors.reverse();
+    }
+}
+
+pub fn order_colors(colors: &mut [DominantColor], ordering: ColorOrdering) {
+    colors.sort_by(|a, b| a.lab[0].total_cmp(&b.lab[0]));
+    if ordering == ColorOrdering::ShortestPath {
+        order_shortest_path(colors);
+    }
+}
+
+pub fn oklab_to_hsv(lab: [f32; 3]) -> HsvKeyValue {
+    let [r, g, b] = oklab_to_linear_srgb(lab).map(|c| c.clamp(0.0, 1.0));
+    let hsv = HsvaGamma::from(Rgba::from_rgb(r, g, b));
+    HsvKeyValue::new(hsv.s, hsv.v, hsv.h)
+}
+
+/// The ordered colors joined by straight lines in OKLab, sampled densely for fitting.
+fn dense_samples(colors: &[DominantColor]) -> Vec<Color32> {
+    let to_color = |lab: [f32; 3]| linear_srgb_to_color32(oklab_to_linear_srgb(lab));
+    let (Some(first), Some(last)) = (colors.first(), colors.last()) else {
+        return Vec::new();
+    };
+    let mut samples: Vec<Color32> = colors
+        .windows(2)
+        .flat_map(|pair| {
+            (0..SAMPLES_PER_SEGMENT).map(move |i| {
+                let t = i as f32 / SAMPLES_PER_SEGMENT as f32;
+                to_color([0, 1, 2].map(|c| pair[0].lab[c] + (pair[1].lab[c] - pair[0].lab[c]) * t))
+            })
+        })
+        .collect();
+    if colors.len() == 1 {
+        samples.push(to_color(first.lab));
+    }
+    samples.push(to_color(last.lab));
+    samples
+}
+
+/// Fits every implemented spline mode to the ordered colors and keeps the one that stays
+/// within the tolerance with the fewest control points, the smaller error breaking ties.
+pub fn fit_colors(colors: &[DominantColor], tolerance: f32) -> Result<(FitResult, SplineMode)> {
+    let samples = dense_samples(colors);
+    let mut best: Option<(FitResult, SplineMode)> = None;
+    for spline_mode in SplineMode::IMPLEMENTED {
+        let settings = FitSettings {
+            spline_mode,
+            tolerance,
+            max_control_points: colors.len().max(2) + 2,
+        };
+        let fitted = fit_control_points(&samples, &settings)?;
+        let rank = |fitted: &FitResult| {
+            (
+                fitted.max_delta_e > tolerance,
+                fitted.control_points.len(),
+                fitted.max_delta_e,
+            )
+        };
+        if best
+            .as_ref()
+            .is_none_or(|(best, _)| rank(&fitted) < rank(best))
+        {
+            best = Some((fitted, spline_mode));
+        }
+    }
+    Ok(best.unwrap())
+}
+
+/// Control points through `values` in order, with handles set up for `spline_mode`.
+pub fn values_to_control_points(
+    values: impl IntoIterator<Item = HsvKeyValue>,
+    spline_mode: SplineMode,
+) -> Vec<ControlPoint> {
+    let mut control_points: Vec<ControlPoint> = values
+        .into_iter()
+        .enumerate()
+        .map(|(i, val)| ControlPoint::new_simple(val, i as f32))
+        .collect();
+
+    match spline_mode {
+        SplineMode::Bezier => {
+            for control_point in control_points.iter_mut() {
+                *control_point.tangents_mut() = [Some(create_tangent_for_control_point()); 2];
+                *control_point.tangent_mode_mut() = TangentMode::AutoSmooth;
+            }
+            apply_tangent_modes(&mut control_points, None);
+        }
+        SplineMode::QuadraticBezier => {
+            for i in 0..control_points.len().saturating_sub(1) {
+                let half_segment = (*control_points[i + 1].val() - *control_points[i].val()) * 0.5;
+                control_points[i].tangents_mut()[1] = Some(half_segment);
+            }
+        }
+        _ => {}
+    }
+
+    control_points
+}
+
+/// Dominant colors of `image` as a gradient: clustered, ordered and fitted with control points.
+pub fn extract_gradient(
+    image: &RgbaImage,
+    settings: &ExtractSettings,
+) -> Result<ExtractedGradient> {
+    let mut colors = dominant_colors(image, settings.num_colors);
+    if colors.is_empty() {
+        return Err("The image has no opaque pixels".to_string().into());
+    }
+    order_colors(&mut colors, settings.ordering);
+    let (fitted, spline_mode) = fit_colors(&colors, settings.tolerance)?;
+    Ok(ExtractedGradient {
+        colors,
+        control_points: fitted.control_points,
+        spline_mode,
+        max_delta_e: fitted.max_delta_e,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::delta_e::delta_e, ui_egui::previewer::CurveSampler};
+    use image::Rgba;
+
+    /// Vertical stripes of the given colors, wider stripes for later colors.
+    fn striped_image(colors: &[[u8; 3]]) -> RgbaImage {
+        let widths: Vec<u32> = (1..=colors.len() as u32).map(|i| i * 4).collect();
+        let total: u32 = widths.iter().sum();
+        RgbaImage::from_fn(total, 8, |x, _| {
+            let mut end = 0;
+            for (color, width) in colors.iter().zip(&widths) {
+                end += width;
+                if x < end {
+                    return Rgba([color[0], color[1], color[2], 255]);
+                }
+            }
+            unreachable!()
+        })
+    }
+
+    #[test]
+    fn finds_the_colors_of_flat_regions() {
+        let colors = [[200, 30, 30], [20, 20, 120], [240, 230, 90]];
+        let image = striped_image(&colors);
+
+        let dominant = dominant_colors(&image, 3);
+        assert_eq!(dominant.len(), 3);
+        // Heaviest first, the last stripe is the widest
+        assert_eq!(dominant[0].weight, 12 * 8);
+
+        let ordered: Vec<Color32> = extract_gradient(&image, &ExtractSettings::default())
+            .unwrap()
+            .colors
+            .iter()
+            .map(|color| linear_srgb_to_color32(oklab_to_linear_srgb(color.lab)))
+            .collect();
+        let expected = [colors[1], colors[0], colors[2]];
+        for (found, expected) in ordered.iter().zip(expected) {
+            let expected = Color32::from_rgb(expected[0], expected[1], expected[2]);
+            assert!(delta_e(*found, expected) < 0.5, "{:?}", ordered);
+        }
+    }
+
+    #[test]
+    fn fitted_gradient_passes_through_the_ordered_colors() {
+        let image = striped_image(&[[20, 20, 120], [200, 30, 30], [240, 230, 90], [30, 160, 60]]);
+        let settings = ExtractSettings {
+            num_colors: 4,
+            ordering: ColorOrdering::ShortestPath,
+            tolerance: 2.0,
+        };
+        let extracted = extract_gradient(&image, &settings).unwrap();
+        assert!(extracted.max_delta_e <= settings.tolerance);
+
+        let sampler = CurveSampler::new(&extracted.control_points, extracted.spline_mode);
+        let last = (extracted.colors.len() - 1) as f32;
+        for (i, color) in extracted.colors.iter().enumerate() {
+            let expected = linear_srgb_to_color32(oklab_to_linear_srgb(color.lab));
+            let fitted = sampler.sample(i as f32 / last).color();
+            assert!(delta_e(fitted, expected) <= settings.tolerance);
+        }
+    }
+
+    #[test]
+    fn picks_the_spline_mode_needing_the_fewest_control_points() {
+        let image = striped_image(&[[10, 10, 10], [120, 60, 20], [250, 240, 200]]);
+        let mut colors = dominant_colors(&image, 3);
+        order_colors(&mut colors, ColorOrdering::Lightness);
+        let (picked, _) = fit_colors(&colors, 2.0).unwrap();
+        assert!(picked.max_delta_e <= 2.0);
+        for spline_mode in SplineMode::IMPLEMENTED {
+            let settings = FitSettings {
+                spline_mode,
+                tolerance: 2.0,
+                max_control_points: 5,
+            };
+            let fitted = fit_control_points(&dense_samples(&colors), &settings).unwrap();
+            assert!(
+                fitted.max_delta_e > 2.0
+                    || picked.control_points.len() <= fitted.control_points.len(),
+                "{:?} fits with fewer control points",
+                spline_mode
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_images_without_opaque_pixels() {
+        assert!(extract_gradient(&RgbaImage::new(4, 4), &ExtractSettings::default()).is_err());
+    }
+
+    #[test]
+    fn never_returns_more_colors_than_the_image_has() {
+        let image = striped_image(&[[10, 10, 10], [250, 250, 250]]);
+        assert_eq!(dominant_colors(&image, 8).len(), 2);
+        assert!(dominant_colors(&RgbaImage::new(4, 4), 4).is_empty());
+    }
+
+    #[test]
+    fn shortest_path_is_no_longer_than_lightness_order() {
+        let colors = [
+            [250, 20, 20],
+            [20, 250, 20],
+            [20, 20, 250],
+            [250, 250, 20],
+            [20, 250, 250],
+            [250, 20, 250],
+        ];
+        let mut by_lightness = dominant_colors(&striped_image(&colors), colors.len());
+        let mut by_path = by_lightness.clone();
+        order_colors(&mut by_lightness, ColorOrdering::Lightness);
+        order_colors(&mut by_path, ColorOrdering::ShortestPath);
+        assert!(path_length(&by_path) <= path_length(&by_lightness));
+        assert!(by_path[0].lab[0] <= by_path.last().unwrap().lab[0]);
+    }
+
+    #[test]
+    fn bezier_handles_are_smooth() {
+        let control_points = values_to_control_points(
+            [
+                HsvKeyValue::new(0.0, 0.05, 0.0),
+                HsvKeyValue::new(0.7, 0.5, 0.07),
+                HsvKeyValue::new(0.2, 0.98, 0.14),
+            ],
+            SplineMode::Bezier,
+        );
+        let [Some(tangent_in), Some(tangent_out)] = *control_points[1].tangents() else {
+            panic!("Bezier control points need both handles");
+        };
+        for c in 0..3 {
+            assert!((tangent_in[c] + tangent_out[c]).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use ecolor::{Color32, HsvaGamma};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::delta_e::delta_e,
+    common::SplineMode,
+    datatypes::{
+        control_point::{ControlPoint, HueInterpolation},
+        hsv_key_value::HsvKeyValue,
+    },
+    error::{Result, ZError},
+    ui_egui::previewer::CurveSampler,
+};
+
+/// Longer sample lists are resampled down to this before fitting.
+const MAX_FIT_SAMPLES: usize = 256;
+/// Keeps the normal equations solvable when a slot barely touches any sample.
+const RIDGE: f64 = 1e-6;
+/// Hue residuals are weighted by chroma, plus this so greys still pull a little.
+const MIN_HUE_WEIGHT: f32 = 0.02;
+
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct FitSettings {
+    pub spline_mode: SplineMode,
+    /// Largest CIEDE2000 difference allowed at any sample
+    pub tolerance: f32,
+    pub max_control_points: usize,
+}
+
+impl Default for FitSettings {
+    fn default() -> Self {
+        Self {
+            spline_mode: SplineMode::Bezier,
+            tolerance: 2.0,
+            max_control_points: 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FitResult {
+    pub control_points: Vec<ControlPoint>,
+    pub max_delta_e: f32,
+}
+
+/// Parses hex colors (`#rrggbb`, `rrggbb` or `#rgb`) separated by whitespace, commas or semicolons.
+pub fn parse_color_list(text: &str) -> Result<Vec<Color32>> {
+    text.split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            let hex = token.trim_start_matches('#');
+            let hex = match hex.len() {
+                3 => hex.chars().flat_map(|c| [c, c]).collect(),
+                _ => hex.to_string(),
+            };
+            match (hex.len(), u32::from_str_radix(&hex, 16)) {
+                (6, Ok(rgb)) => {
+                    let [_, r, g, b] = rgb.to_be_bytes();
+                    Ok(Color32::from_rgb(r, g, b))
+                }
+                _ => Err(ZError::Message(format!("Not a hex color: {:?}", token))),
+            }
+        })
+        .collect()
+}
+
+fn resample(samples: &[Color32]) -> Vec<Color32> {
+    if samples.len() <= MAX_FIT_SAMPLES {
+        return samples.to_vec();
+    }
+    (0..MAX_FIT_SAMPLES)
+        .map(|i| {
+            let index = i as f32 / (MAX_FIT_SAMPLES - 1) as f32 * (samples.len() - 1) as f32;
+            samples[index.round() as usize]
+        })
+        .collect()
+}
+
+/// Targets as HSV with the hue unwrapped along the samples, so it can be fitted as a plain number.
+fn unwrapped_targets(samples: &[Color32]) -> Vec<HsvKeyValue> {
+    let mut targets: Vec<HsvKeyValue> = Vec::with_capacity(samples.len());
+    for sample in samples {
+        let hsv = HsvaGamma::from(*sample);
+        let hue = match targets.last() {
+            Some(prev) => HueInterpolation::Shortest.unwrap(prev.h(), hsv.h),
+            None => hsv.h,
+        };
+        targets.push(HsvKeyValue::new(hsv.s, hsv.v, hue));
+    }
+    targets
+}
+
+/// Value slots each control point has for `spline_mode`: the value, then its tangents.
+fn slots_per_control_point(spline_mode: SplineMode) -> usize {
+    match spline_mode {
+        SplineMode::Bezier => 3,
+        SplineMode::QuadraticBezier => 2,
+        _ => 1,
+    }
+}
+
+fn control_points_from_slots(
+    slots: &[HsvKeyValue],
+    num_control_points: usize,
+    spline_mode: SplineMode,
+) -> Vec<ControlPoint> {
+    let per_point = slots_per_control_point(spline_mode);
+    (0..num_control_points)
+        .map(|i| {
+            let slot = &slots[i * per_point..(i + 1) * per_point];
+            match spline_mode {
+                SplineMode::Bezier => {
+                    ControlPoint::new(slot[0], i as f32, [Some(slot[1]), Some(slot[2])])
+                }
+                SplineMode::QuadraticBezier => {
+                    ControlPoint::new(slot[0], i as f32, [None, Some(slot[1])])
+                }
+                _ => ControlPoint::new_simple(slot[0], i as f32),
+            }
+        })
+        .collect()
+}
+
+/// How much each slot moves each sample. Every spline mode is linear in its values and tangents
+/// once the hue is unwrapped, so probing one slot at a time gives the exact basis.
+fn slot_basis(
+    num_control_points: usize,
+    spline_mode: SplineMode,
+    num_samples: usize,
+) -> Vec<Vec<f32>> {
+    let num_slots = num_control_points * slots_per_control_point(spline_mode);
+    (0..num_slots)
+        .map(|slot| {
+            let mut slots = vec![HsvKeyValue::default(); num_slots];
+            // Saturation is never unwrapped, so it shows the pure spline response
+            slots[slot] = HsvKeyValue::new(1.0, 0.0, 0.0);
+            let control_points = control_points_from_slots(&slots, num_control_points, spline_mode);
+            CurveSampler::new(&control_points, spline_mode)
+                .sample_n(num_samples)
+                .iter()
+                .map(|sample| sample.s())
+                .collect()
+        })
+        .collect()
+}
+
+/// Weighted least squares through the normal equations, Gaussian elimination with pivoting.
+fn solve_least_squares(basis: &[Vec<f32>], weights: &[f32], target: &[f32]) -> Vec<f32> {
+    let n = basis.len();
+    let mut matrix = vec![vec![0.0f64; n + 1]; n];
+    for (i, row) in matrix.iter_mut().enumerate() {
+        for j in 0..n {
+            row[j] = basis[i]
+                .iter()
+                .zip(&basis[j])
+                .zip(weights)
+                .map(|((a, b), w)| (a * b * w) as f64)
+                .sum();
+        }
+        row[i] += RIDGE;
+        row[n] = basis[i]
+            .iter()
+            .zip(target)
+            .zip(weights)
+            .map(|((a, t), w)| (a * t * w) as f64)
+            .sum();
+    }
+
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|a, b| matrix[*a][col].abs().total_cmp(&matrix[*b][col].abs()))
+            .unwrap();
+        matrix.swap(col, pivot);
+        let (above, below) = matrix.split_at_mut(col + 1);
+        let pivot_row = &above[col];
+        for row in below.iter_mut() {
+            let factor = row[col] / pivot_row[col];
+            for (value, pivot_value) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+
+    let mut solution = vec![0.0f64; n];
+    for row in (0..n).rev() {
+        let known: f64 = ((row + 1)..n).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (matrix[row][n] - known) / matrix[row][row];
+    }
+    solution.into_iter().map(|x| x as f32).collect()
+}
+
+/// Hue directions that make `flatten_control_points` reproduce the fitted unwrapped hues.
+fn set_hue_interpolation(control_points: &mut [ControlPoint]) {
+    for i in 0..control_points.len().saturating_sub(1) {
+        let delta = control_points[i + 1].val().h() - control_points[i].val().h();
+        *control_points[i].hue_interpolation_mut() = HueInterpolation::for_delta(delta);
+    }
+}
+
+fn fit_with(
+    targets: &[HsvKeyValue],
+    weights: &[[f32; 3]],
+    num_control_points: usize,
+    spline_mode: SplineMode,
+) -> Vec<ControlPoint> {
+    let basis = slot_basis(num_control_points, spline_mode, targets.len());
+    let channels: Vec<Vec<f32>> = (0..3)
+        .map(|channel| {
+            let target: Vec<f32> = targets.iter().map(|t| t[channel]).collect();
+            let weight: Vec<f32> = weights.iter().map(|w| w[channel]).collect();
+            solve_least_squares(&basis, &weight, &target)
+        })
+        .collect();
+    let slots: Vec<HsvKeyValue> = (0..basis.len())
+        .map(|slot| HsvKeyValue::new(channels[0][slot], channels[1][slot], channels[2][slot]))
+        .collect();
+
+    let mut control_points = control_points_from_slots(&slots, num_control_points, spline_mode);
+    for control_point in control_points.iter_mut() {
+        let val = control_point.val_mut();
+        val[0] = val[0].clamp(0.0, 1.0);
+        val[1] = val[1].clamp(0.0, 1.0);
+    }
+    set_hue_interpolation(&mut control_points);
+    for control_point in control_points.iter_mut() {
+        let val = control_point.val_mut();
+        val[2] = val[2].rem_euclid(1.0);
+    }
+    control_points
+}
+
+fn max_delta_e(
+    control_points: &[ControlPoint],
+    spline_mode: SplineMode,
+    samples: &[Color32],
+) -> f32 {
+    CurveSampler::new(control_points, spline_mode)
+        .sample_n(samples.len())
+        .iter()
+        .zip(samples)
+        .map(|(fitted, sample)| delta_e(fitted.color(), *sample))
+        .fold(0.0, f32::max)
+}
+
+/// Fewest evenly spaced control points for `settings.spline_mode` that reproduce `samples`
+/// within the tolerance. Falls back to the best fit with `max_control_points` if none does.
+pub fn fit_control_points(samples: &[Color32], settings: &FitSettings) -> Result<FitResult> {
+    if samples.len() < 2 {
+        return Err("Need at least two colors to fit".to_string().into());
+    }
+
+    let samples = resample(samples);
+    let targets = unwrapped_targets(&samples);
+    let weights: Vec<[f32; 3]> = targets
+        .iter()
+        .map(|t| [1.0, 1.0, t.s() * t.v() + MIN_HUE_WEIGHT])
+        .collect();
+
+    let max_control_points = settings.max_control_points.clamp(2, samples.len());
+    let mut best: Option<FitResult> = None;
+    for num_control_points in 2..=max_control_points {
+        let control_points = fit_with(&targets, &weights, num_control_points, settings.spline_mode);
+        let error = max_delta_e(&control_points, settings.spline_mode, &samples);
+        if best.as_ref().is_none_or(|best| error < best.max_delta_e) {
+            best = Some(FitResult {
+                control_points,
+                max_delta_e: error,
+            });
+        }
+        if error <= settings.tolerance {
+            break;
+        }
+    }
+
+    Ok(best.unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{wrapping_control_points, IMPLEMENTED_SPLINE_MODES};
+
+    fn sampled(control_points: &[ControlPoint], spline_mode: SplineMode, n: usize) -> Vec<Color32> {
+        CurveSampler::new(control_points, spline_mode)
+            .sample_n(n)
+            .iter()
+            .map(|sample| sample.color())
+            .collect()
+    }
+
+    #[test]
+    fn parses_hex_color_lists() {
+        let colors = parse_color_list("#ff0000, 00ff00;\n#00f  #123456").unwrap();
+        assert_eq!(
+            colors,
+            vec![
+                Color32::from_rgb(255, 0, 0),
+                Color32::from_rgb(0, 255, 0),
+                Color32::from_rgb(0, 0, 255),
+                Color32::from_rgb(0x12, 0x34, 0x56),
+            ]
+        );
+        assert!(parse_color_list("#ff0000 nothex").is_err());
+        assert!(parse_color_list("#ff00").is_err());
+    }
+
+    #[test]
+    fn refits_gradients_of_every_mode_within_tolerance() {
+        let settings = FitSettings::default();
+        for spline_mode in IMPLEMENTED_SPLINE_MODES {
+            let samples = sampled(&wrapping_control_points(), SplineMode::Linear, 64);
+            let result = fit_control_points(
+                &samples,
+                &FitSettings {
+                    spline_mode,
+                    ..settings
+                },
+            )
+            .unwrap();
+            assert!(
+                result.max_delta_e <= settings.tolerance,
+                "{:?} max ΔE {}",
+                spline_mode,
+                result.max_delta_e
+            );
+            assert!(result.control_points.len() < samples.len() / 2);
+            // Re-measure, the reported error must match what gets loaded
+            let refit = sampled(&result.control_points, spline_mode, samples.len());
+            let max_delta_e = refit
+                .iter()
+                .zip(&samples)
+                .map(|(a, b)| delta_e(*a, *b))
+                .fold(0.0, f32::max);
+            assert!((max_delta_e - result.max_delta_e).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn linear_ramp_needs_two_points() {
+        let samples: Vec<Color32> = (0..=20u8)
+            .map(|i| Color32::from_rgb(i * 12, i * 12, i * 12))
+            .collect();
+        let result = fit_control_points(
+            &samples,
+            &FitSettings {
+                spline_mode: SplineMode::Linear,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(result.control_points.len(), 2);
+    }
+
+    #[test]
+    fn fits_a_line_drawn_across_an_image() {
+        use crate::image_processing::{sample_line, FramePixelRead};
+        use eframe::egui::Pos2;
+
+        let control_points = wrapping_control_points();
+        let gradient = sampled(&control_points, SplineMode::HermiteBezier, 128);
+        let image = image::RgbaImage::from_fn(128, 32, |x, _| {
+            let color = gradient[x as usize];
+            image::Rgba([color.r(), color.g(), color.b(), 255])
+        });
+        let samples = sample_line(
+            &FramePixelRead::from_rgba_image(&image),
+            Pos2::new(0.5, 2.0),
+            Pos2::new(127.5, 30.0),
+            128,
+            2.0,
+        );
+
+        let result = fit_control_points(&samples, &FitSettings::default()).unwrap();
+        assert!(result.max_delta_e <= FitSettings::default().tolerance);
+    }
+
+    #[test]
+    fn needs_two_colors() {
+        assert!(fit_control_points(&[Color32::RED], &FitSettings::default()).is_err());
+    }
+}
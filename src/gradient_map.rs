@@ -0,0 +1,277 @@
+use ecolor::linear_f32_from_gamma_u8;
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::oklab::linear_srgb_to_oklab,
+    common::{GradientMode, SplineMode},
+    datatypes::control_point::ControlPoint,
+    error::{Result, ZError},
+    preset::{get_presets_path, load_presets},
+    ui_egui::{app::ZColorPickerAppContext, previewer::CurveSampler},
+};
+
+/// Gradient samples per mapping, more than enough for 8-bit input.
+const LUT_SIZE: usize = 1024;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum MapChannel {
+    /// Rec. 709 luma of the encoded values, what image editors use for gradient maps
+    #[default]
+    Luminance,
+    /// OKLab L, perceptually even steps
+    Lightness,
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+impl MapChannel {
+    pub const ALL: [MapChannel; 6] = [
+        MapChannel::Luminance,
+        MapChannel::Lightness,
+        MapChannel::Red,
+        MapChannel::Green,
+        MapChannel::Blue,
+        MapChannel::Alpha,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MapChannel::Luminance => "Luminance",
+            MapChannel::Lightness => "Lightness (OKLab)",
+            MapChannel::Red => "Red",
+            MapChannel::Green => "Green",
+            MapChannel::Blue => "Blue",
+            MapChannel::Alpha => "Alpha",
+        }
+    }
+
+    /// Name accepted on the command line
+    pub fn cli_name(&self) -> &'static str {
+        match self {
+            MapChannel::Luminance => "luminance",
+            MapChannel::Lightness => "lightness",
+            MapChannel::Red => "red",
+            MapChannel::Green => "green",
+            MapChannel::Blue => "blue",
+            MapChannel::Alpha => "alpha",
+        }
+    }
+
+    /// Position along the gradient in [0, 1].
+    pub fn value(&self, pixel: &Rgba<u8>) -> f32 {
+        let [r, g, b, a] = pixel.0;
+        match self {
+            MapChannel::Luminance => {
+                (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) / 255.0
+            }
+            MapChannel::Lightness => {
+                let linear = [r, g, b].map(linear_f32_from_gamma_u8);
+                linear_srgb_to_oklab(linear)[0].clamp(0.0, 1.0)
+            }
+            MapChannel::Red => r as f32 / 255.0,
+            MapChannel::Green => g as f32 / 255.0,
+            MapChannel::Blue => b as f32 / 255.0,
+            MapChannel::Alpha => a as f32 / 255.0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct GradientMapSettings {
+    pub channel: MapChannel,
+    /// Map the brightest pixels to the start of the gradient instead
+    pub invert: bool,
+}
+
+/// Remaps every pixel of `image` through the gradient, keeping the source alpha.
+pub fn gradient_map_image(
+    image: &RgbaImage,
+    control_points: &[ControlPoint],
+    spline_mode: SplineMode,
+    gradient_mode: GradientMode,
+    settings: &GradientMapSettings,
+) -> RgbaImage {
+    let lut: Vec<[u8; 3]> =
+        CurveSampler::with_gradient_mode(control_points, spline_mode, gradient_mode)
+            .sample_n(LUT_SIZE)
+            .iter()
+            .map(|sample| {
+                let color = sample.color();
+                [color.r(), color.g(), color.b()]
+            })
+            .collect();
+
+    let mut mapped = image.clone();
+    for pixel in mapped.pixels_mut() {
+        let mut t = settings.channel.value(pixel);
+        if settings.invert {
+            t = 1.0 - t;
+        }
+        let [r, g, b] = lut[(t * (LUT_SIZE - 1) as f32).round() as usize];
+        *pixel = Rgba([r, g, b, pixel.0[3]]);
+    }
+
+    mapped
+}
+
+pub fn load_image(path: &str) -> Result<RgbaImage> {
+    Ok(image::open(path)?.to_rgba8())
+}
+
+/// Saves in the format of the path's extension, alpha is dropped for formats without it.
+pub fn save_image(image: RgbaImage, path: &str) -> Result<()> {
+    let image = match ImageFormat::from_path(path) {
+        Ok(ImageFormat::Jpeg) => DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(image).to_rgb8()),
+        _ => DynamicImage::ImageRgba8(image),
+    };
+    Ok(image.save(path)?)
+}
+
+pub const CLI_USAGE: &str = "usage: z_color_picker --gradient-map <input> <output> [--preset <name>] [--channel <luminance|lightness|red|green|blue|alpha>] [--invert]";
+
+/// `--gradient-map` command line entry, `args` are the arguments after the flag. Uses the named
+/// preset or the startup gradient.
+pub fn run_gradient_map_cli(args: &[String]) -> Result<()> {
+    let mut positional = Vec::new();
+    let mut preset_name = None;
+    let mut settings = GradientMapSettings::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--preset" => {
+                preset_name = Some(
+                    args.next()
+                        .ok_or_else(|| ZError::Message("--preset needs a name".to_string()))?,
+                );
+            }
+            "--channel" => {
+                let name = args.next().map(String::as_str).unwrap_or_default();
+                settings.channel = MapChannel::ALL
+                    .into_iter()
+                    .find(|c| c.cli_name() == name)
+                    .ok_or_else(|| ZError::Message(format!("Unknown channel {:?}", name)))?;
+            }
+            "--invert" => settings.invert = true,
+            _ => positional.push(arg),
+        }
+    }
+    let [input, output] = positional[..] else {
+        return Err(ZError::Message(CLI_USAGE.to_string()));
+    };
+
+    let (control_points, spline_mode, gradient_mode) = match preset_name {
+        Some(name) => load_presets(&get_presets_path())?
+            .into_iter()
+            .find(|preset| &preset.name == name)
+            .ok_or_else(|| ZError::Message(format!("No preset named {:?}", name)))?
+            .into(),
+        None => (
+            ZColorPickerAppContext::DEFAULT_STARTUP_CONTROL_POINTS.to_vec(),
+            SplineMode::default(),
+            GradientMode::default(),
+        ),
+    };
+
+    let image = load_image(input)?;
+    let mapped = gradient_map_image(
+        &image,
+        &control_points,
+        spline_mode,
+        gradient_mode,
+        &settings,
+    );
+    save_image(mapped, output)?;
+    println!("Gradient mapped {} to {}", input, output);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{wrapping_control_points, IMPLEMENTED_SPLINE_MODES};
+
+    fn rgb(color: ecolor::Color32) -> [u8; 3] {
+        [color.r(), color.g(), color.b()]
+    }
+
+    #[test]
+    fn black_and_white_map_to_the_gradient_ends() {
+        let control_points = wrapping_control_points();
+        let first = rgb(control_points[0].val().color());
+        let last = rgb(control_points.last().unwrap().val().color());
+        let image = RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                Rgba([0, 0, 0, 40])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+
+        for spline_mode in IMPLEMENTED_SPLINE_MODES {
+            for channel in [
+                MapChannel::Luminance,
+                MapChannel::Lightness,
+                MapChannel::Red,
+            ] {
+                let mut settings = GradientMapSettings {
+                    channel,
+                    invert: false,
+                };
+                let mapped = gradient_map_image(
+                    &image,
+                    &control_points,
+                    spline_mode,
+                    GradientMode::Open,
+                    &settings,
+                );
+                assert_eq!(mapped.get_pixel(0, 0).0, [first[0], first[1], first[2], 40]);
+                assert_eq!(mapped.get_pixel(1, 0).0, [last[0], last[1], last[2], 255]);
+
+                settings.invert = true;
+                let mapped = gradient_map_image(
+                    &image,
+                    &control_points,
+                    spline_mode,
+                    GradientMode::Open,
+                    &settings,
+                );
+                assert_eq!(mapped.get_pixel(0, 0).0, [last[0], last[1], last[2], 40]);
+            }
+        }
+    }
+
+    #[test]
+    fn saves_jpeg_without_alpha() {
+        let path = std::env::temp_dir()
+            .join(format!("z_color_picker_map_{}.jpg", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let image = RgbaImage::from_pixel(8, 8, Rgba([200, 100, 50, 128]));
+        let saved = save_image(image, &path);
+        let loaded = load_image(&path);
+        let _ = std::fs::remove_file(&path);
+
+        saved.unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.dimensions(), (8, 8));
+        let [r, g, b, a] = loaded.get_pixel(4, 4).0;
+        assert_eq!(a, 255);
+        for (found, expected) in [r, g, b].into_iter().zip([200, 100, 50]) {
+            assert!(found.abs_diff(expected) <= 4, "{:?}", [r, g, b]);
+        }
+    }
+
+    #[test]
+    fn cli_rejects_bad_arguments() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert!(run_gradient_map_cli(&args(&["only_input.png"])).is_err());
+        assert!(run_gradient_map_cli(&args(&["a.png", "b.png", "--channel", "hue"])).is_err());
+        let missing_preset = run_gradient_map_cli(&args(&["a.png", "b.png", "--preset"]));
+        assert!(missing_preset.unwrap_err().to_string().contains("--preset"));
+    }
+}
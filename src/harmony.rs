@@ -0,0 +1,198 @@
+use ecolor::{linear_f32_from_gamma_u8, Color32, HsvaGamma, Rgba};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::{
+        gamut::{map_to_gamut, Gamut, GamutMapping},
+        oklab::{linear_srgb_to_oklab, oklab_to_linear_srgb, oklab_to_oklch, oklch_to_oklab},
+    },
+    common::SplineMode,
+    datatypes::{control_point::ControlPoint, hsv_key_value::HsvKeyValue},
+    gradient_extract::values_to_control_points,
+};
+
+const MONOCHROMATIC_COLORS: usize = 5;
+
+/// Color-theory rules, hues are rotated around the OKLCH wheel so the steps look even.
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum HarmonyRule {
+    #[default]
+    Complementary,
+    SplitComplementary,
+    Triadic,
+    /// Square, four hues a quarter turn apart
+    Tetradic,
+    Analogous,
+    Monochromatic,
+}
+
+impl HarmonyRule {
+    pub const ALL: [HarmonyRule; 6] = [
+        HarmonyRule::Complementary,
+        HarmonyRule::SplitComplementary,
+        HarmonyRule::Triadic,
+        HarmonyRule::Tetradic,
+        HarmonyRule::Analogous,
+        HarmonyRule::Monochromatic,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HarmonyRule::Complementary => "Complementary",
+            HarmonyRule::SplitComplementary => "Split complementary",
+            HarmonyRule::Triadic => "Triadic",
+            HarmonyRule::Tetradic => "Tetradic",
+            HarmonyRule::Analogous => "Analogous",
+            HarmonyRule::Monochromatic => "Monochromatic",
+        }
+    }
+
+    /// Whether `angle` changes the hues of this rule.
+    pub fn uses_angle(&self) -> bool {
+        matches!(
+            self,
+            HarmonyRule::SplitComplementary | HarmonyRule::Analogous
+        )
+    }
+
+    /// Hue of each color relative to the base in turns, in gradient order. `angle` is the
+    /// distance to the neighbours for analogous, and to the complement for split complementary.
+    pub fn hue_offsets(&self, angle: f32) -> Vec<f32> {
+        match self {
+            HarmonyRule::Complementary => vec![0.0, 0.5],
+            // Sweeps from one split through the base to the other
+            HarmonyRule::SplitComplementary => vec![angle - 0.5, 0.0, 0.5 - angle],
+            HarmonyRule::Triadic => vec![0.0, 1.0 / 3.0, 2.0 / 3.0],
+            HarmonyRule::Tetradic => vec![0.0, 0.25, 0.5, 0.75],
+            HarmonyRule::Analogous => vec![-angle, 0.0, angle],
+            HarmonyRule::Monochromatic => vec![0.0; MONOCHROMATIC_COLORS],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct HarmonySettings {
+    pub rule: HarmonyRule,
+    pub base: Color32,
+    /// Degrees
+    pub angle: f32,
+    /// OKLCH lightness difference from the first color to the last, centered on the base
+    pub lightness_spread: f32,
+    /// OKLCH chroma difference from the first color to the last, centered on the base
+    pub chroma_spread: f32,
+    pub spline_mode: SplineMode,
+}
+
+impl Default for HarmonySettings {
+    fn default() -> Self {
+        Self {
+            rule: HarmonyRule::default(),
+            base: Color32::from_rgb(51, 122, 183),
+            angle: 30.0,
+            lightness_spread: 0.4,
+            chroma_spread: 0.0,
+            spline_mode: SplineMode::HermiteBezier,
+        }
+    }
+}
+
+fn color32_to_oklch(color: Color32) -> [f32; 3] {
+    let linear = [color.r(), color.g(), color.b()].map(linear_f32_from_gamma_u8);
+    oklab_to_oklch(linear_srgb_to_oklab(linear))
+}
+
+/// OKLCH to HSV, chroma is reduced until the color fits in sRGB.
+fn oklch_to_hsv(lch: [f32; 3]) -> HsvKeyValue {
+    let linear = oklab_to_linear_srgb(oklch_to_oklab(lch));
+    let [r, g, b] =
+        map_to_gamut(linear, Gamut::Srgb, GamutMapping::OklchChroma).map(|c| c.clamp(0.0, 1.0));
+    let hsv = HsvaGamma::from(Rgba::from_rgb(r, g, b));
+    HsvKeyValue::new(hsv.s, hsv.v, hsv.h)
+}
+
+/// The base color and its harmonies as control points, lightness and chroma ramp linearly
+/// across them.
+pub fn generate_harmony(settings: &HarmonySettings) -> Vec<ControlPoint> {
+    let [base_lightness, base_chroma, base_hue] = color32_to_oklch(settings.base);
+    let offsets = settings.rule.hue_offsets(settings.angle / 360.0);
+    let last = (offsets.len() - 1) as f32;
+
+    let values = offsets.iter().enumerate().map(|(i, offset)| {
+        let t = i as f32 / last - 0.5;
+        oklch_to_hsv([
+            (base_lightness + settings.lightness_spread * t).clamp(0.0, 1.0),
+            (base_chroma + settings.chroma_spread * t).max(0.0),
+            (base_hue + offset).rem_euclid(1.0),
+        ])
+    });
+    values_to_control_points(values, settings.spline_mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::gamut::hsv_to_linear_srgb;
+
+    fn oklch_of(control_point: &ControlPoint) -> [f32; 3] {
+        oklab_to_oklch(linear_srgb_to_oklab(hsv_to_linear_srgb(
+            control_point.val(),
+        )))
+    }
+
+    fn hue_distance(a: f32, b: f32) -> f32 {
+        let d = (a - b).rem_euclid(1.0);
+        d.min(1.0 - d)
+    }
+
+    #[test]
+    fn rules_rotate_the_base_hue() {
+        // Muted enough that every rotation stays inside sRGB
+        let base = Color32::from_rgb(170, 120, 110);
+        let base_hue = color32_to_oklch(base)[2];
+        for rule in HarmonyRule::ALL {
+            let settings = HarmonySettings {
+                rule,
+                base,
+                lightness_spread: 0.0,
+                ..Default::default()
+            };
+            let control_points = generate_harmony(&settings);
+            let offsets = rule.hue_offsets(settings.angle / 360.0);
+            assert_eq!(control_points.len(), offsets.len(), "{:?}", rule);
+            for (control_point, offset) in control_points.iter().zip(offsets) {
+                let hue = oklch_of(control_point)[2];
+                assert!(
+                    hue_distance(hue, base_hue + offset) < 0.005,
+                    "{:?}: hue {} expected {}",
+                    rule,
+                    hue,
+                    (base_hue + offset).rem_euclid(1.0)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn monochromatic_steps_through_lightness() {
+        let settings = HarmonySettings {
+            rule: HarmonyRule::Monochromatic,
+            base: Color32::from_rgb(60, 110, 160),
+            lightness_spread: 0.5,
+            ..Default::default()
+        };
+        let [base_lightness, _, base_hue] = color32_to_oklch(settings.base);
+        let lch: Vec<[f32; 3]> = generate_harmony(&settings).iter().map(oklch_of).collect();
+
+        assert_eq!(lch.len(), MONOCHROMATIC_COLORS);
+        assert!((lch[0][0] - (base_lightness - 0.25)).abs() < 0.005);
+        assert!((lch[2][0] - base_lightness).abs() < 0.005);
+        assert!((lch[4][0] - (base_lightness + 0.25)).abs() < 0.005);
+        for pair in lch.windows(2) {
+            assert!(pair[0][0] < pair[1][0], "{:?}", lch);
+        }
+        // Chroma is reduced where a step leaves sRGB, the hue stays
+        for color in &lch {
+            assert!(hue_distance(color[2], base_hue) < 0.01, "{:?}", lch);
+        }
+    }
+}
@@ -1,9 +1,11 @@
 use std::ops::Rem;
 
+use ecolor::{gamma_u8_from_linear_f32, linear_f32_from_gamma_u8, Color32};
 use eframe::{
-    egui::{self, Rect},
+    egui::{self, ColorImage, Pos2, Rect},
     glow::{self, HasContext},
 };
+use image::RgbaImage;
 
 #[repr(C)]
 #[derive(Clone, Debug)]
@@ -11,13 +13,92 @@ pub struct Rgb {
     pub val: (u8, u8, u8),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct FramePixelRead {
     pub width: usize,
     pub height: usize,
     pub data: Vec<Rgb>,
 }
 
+impl FramePixelRead {
+    pub fn from_rgba_image(image: &RgbaImage) -> Self {
+        Self {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            data: image
+                .pixels()
+                .map(|p| Rgb {
+                    val: (p.0[0], p.0[1], p.0[2]),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn from_color_image(image: &ColorImage) -> Self {
+        Self {
+            width: image.width(),
+            height: image.height(),
+            data: image
+                .pixels
+                .iter()
+                .map(|c| Rgb {
+                    val: (c.r(), c.g(), c.b()),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_color_image(&self) -> ColorImage {
+        ColorImage::from_rgb([self.width, self.height], &u8u8u8_to_u8(&self.data))
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> &Rgb {
+        &self.data[y * self.width + x]
+    }
+}
+
+/// `num_samples` colors evenly spaced from `from` to `to`, in pixel coordinates. Each is the
+/// average, in linear light, of the pixels within `radius` of its point on the line.
+pub fn sample_line(
+    pixels: &FramePixelRead,
+    from: Pos2,
+    to: Pos2,
+    num_samples: usize,
+    radius: f32,
+) -> Vec<Color32> {
+    let max = Pos2::new(pixels.width as f32 - 0.5, pixels.height as f32 - 0.5);
+    let reach = radius.max(0.0).ceil() as i32;
+
+    (0..num_samples)
+        .map(|i| {
+            let t = i as f32 / (num_samples - 1).max(1) as f32;
+            let center = from.lerp(to, t).clamp(Pos2::ZERO, max);
+            let (cx, cy) = (center.x.floor() as i32, center.y.floor() as i32);
+
+            let mut sum = [0.0f32; 3];
+            let mut count = 0;
+            for y in (cy - reach)..=(cy + reach) {
+                for x in (cx - reach)..=(cx + reach) {
+                    let inside_image = (0..pixels.width as i32).contains(&x)
+                        && (0..pixels.height as i32).contains(&y);
+                    let pixel_center = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                    if !inside_image || pixel_center.distance(center) > radius.max(0.5) {
+                        continue;
+                    }
+                    let (r, g, b) = pixels.pixel(x as usize, y as usize).val;
+                    for (sum, channel) in sum.iter_mut().zip([r, g, b]) {
+                        *sum += linear_f32_from_gamma_u8(channel);
+                    }
+                    count += 1;
+                }
+            }
+
+            let [r, g, b] = sum.map(|sum| gamma_u8_from_linear_f32(sum / count.max(1) as f32));
+            Color32::from_rgb(r, g, b)
+        })
+        .collect()
+}
+
 pub fn u8_to_u8u8u8(buf: &[u8]) -> Vec<Rgb> {
     assert!(buf.len().rem(3) == 0);
     let mut ret: Vec<Rgb> = Vec::with_capacity(buf.len() / 3);
@@ -167,3 +248,55 @@ pub fn gl_read_rect_pixels(
         height: height as usize,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn horizontal_ramp() -> FramePixelRead {
+        FramePixelRead::from_rgba_image(&RgbaImage::from_fn(64, 8, |x, _| {
+            image::Rgba([(x * 4) as u8, 255 - (x * 4) as u8, 100, 255])
+        }))
+    }
+
+    #[test]
+    fn line_samples_follow_the_pixels() {
+        let pixels = horizontal_ramp();
+        let samples = sample_line(&pixels, Pos2::new(0.5, 4.5), Pos2::new(63.5, 4.5), 64, 0.0);
+        for (x, sample) in samples.iter().enumerate() {
+            let (r, g, b) = pixels.pixel(x, 4).val;
+            assert_eq!(*sample, Color32::from_rgb(r, g, b));
+        }
+    }
+
+    #[test]
+    fn line_samples_average_around_the_line() {
+        let checker = FramePixelRead::from_rgba_image(&RgbaImage::from_fn(16, 16, |x, y| {
+            let v = if (x + y) % 2 == 0 { 255 } else { 0 };
+            image::Rgba([v, v, v, 255])
+        }));
+        let samples = sample_line(&checker, Pos2::new(4.0, 8.0), Pos2::new(12.0, 8.0), 5, 3.0);
+        for sample in samples {
+            // Half the pixels lit is half the light, not half the encoded value
+            let expected = gamma_u8_from_linear_f32(0.5) as i32;
+            assert!((sample.r() as i32 - expected).abs() <= 12, "{:?}", sample);
+        }
+    }
+
+    #[test]
+    fn line_samples_clamp_to_the_image() {
+        let pixels = horizontal_ramp();
+        let samples = sample_line(
+            &pixels,
+            Pos2::new(-50.0, -50.0),
+            Pos2::new(500.0, 4.0),
+            2,
+            1.0,
+        );
+        let (r, g, b) = pixels.pixel(0, 0).val;
+        assert_eq!(samples[0].r(), r);
+        assert_eq!(samples[0].g(), g);
+        assert_eq!(samples[0].b(), b);
+        assert_eq!(samples[1].r(), pixels.pixel(63, 4).val.0);
+    }
+}
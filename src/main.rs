@@ -6,16 +6,28 @@ use std::env;
 
 use eframe::egui::{self};
 
-use crate::{logger::LogCollector, ui_egui::app::ZApp};
+use crate::{gradient_map::run_gradient_map_cli, logger::LogCollector, ui_egui::app::ZApp};
 
+mod blend;
+mod color;
+mod colormaps;
 mod common;
+mod compare;
 mod datatypes;
 mod error;
+mod export;
 mod fs;
+mod gradient_extract;
+mod gradient_fit;
+mod gradient_map;
+mod harmony;
 mod image_processing;
 mod logger;
 mod math;
 mod preset;
+mod screen_capture;
+#[cfg(test)]
+mod test_utils;
 mod ui_egui;
 
 fn main() -> eframe::Result {
@@ -23,6 +35,15 @@ fn main() -> eframe::Result {
 
     let log_buffer = LogCollector::init().expect("Failed to init logger");
 
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--gradient-map") {
+        if let Err(e) = run_gradient_map_cli(&args[2..]) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([2560.0, 1440.0]),
         ..Default::default()
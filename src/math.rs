@@ -1,9 +1,10 @@
 use std::f32::consts::TAU;
 
+use crate::color::gamut::{linear_srgb_to_color32, map_to_gamut, Gamut, GamutMapping};
 #[allow(unused_imports)]
 use crate::error::Result;
 use bspline::Interpolate;
-use ecolor::Color32;
+use ecolor::{linear_f32_from_gamma_u8, Color32};
 use eframe::egui::{lerp, Vec2};
 use palette::{FromColor, LabHue, Lch, LinSrgb};
 
@@ -90,18 +91,6 @@ pub fn hue_lerp(hue0: f32, hue1: f32, t: f32) -> f32 {
     hue.rem_euclid(1.0)
 }
 
-pub fn unwrap_hue(a: f32, b: f32) -> (f32, f32) {
-    let mut b = b;
-    if hue_abs_distance(a, b) > 0.5 {
-        if b > a {
-            b -= 1.0;
-        } else {
-            b += 1.0;
-        }
-    }
-    (a, b)
-}
-
 pub fn color_lerp(color_src: Color32, color_trg: Color32, t: f32) -> Color32 {
     const C: f32 = 0.7;
     const ALPHA: f32 = 0.1;
@@ -120,18 +109,16 @@ pub fn color_lerp_ex(
         t = t.clamp(0.0, 1.0);
     }
 
-    let color_src_linsrgb = LinSrgb::new(
-        color_src.r() as f32 / 255.0,
-        color_src.g() as f32 / 255.0,
-        color_src.b() as f32 / 255.0,
-    );
-    let color_trg_linsrgb = LinSrgb::new(
-        color_trg.r() as f32 / 255.0,
-        color_trg.g() as f32 / 255.0,
-        color_trg.b() as f32 / 255.0,
-    );
-    let lch_src = Lch::from_color(color_src_linsrgb);
-    let lch_trg = Lch::from_color(color_trg_linsrgb);
+    // Color32 is gamma encoded, Lch expects linear light
+    let to_linsrgb = |color: Color32| {
+        LinSrgb::new(
+            linear_f32_from_gamma_u8(color.r()),
+            linear_f32_from_gamma_u8(color.g()),
+            linear_f32_from_gamma_u8(color.b()),
+        )
+    };
+    let lch_src = Lch::from_color(to_linsrgb(color_src));
+    let lch_trg = Lch::from_color(to_linsrgb(color_trg));
 
     // Lerp hue
     let lerped_hue_normalized = hue_lerp(
@@ -160,12 +147,74 @@ pub fn color_lerp_ex(
     let new_lch: Lch = Lch::new(
         lch_src.l,
         new_chroma_normalized * Lch::<f32>::max_chroma(),
-        lerped_hue_normalized,
+        new_hue,
     );
     let new_color = LinSrgb::from_color(new_lch);
-    Color32::from_rgb(
-        (new_color.red * 255.0) as u8,
-        (new_color.green * 255.0) as u8,
-        (new_color.blue * 255.0) as u8,
-    )
+    linear_srgb_to_color32(map_to_gamut(
+        [new_color.red, new_color.green, new_color.blue],
+        Gamut::Srgb,
+        GamutMapping::Css4,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn hue_lerp_stays_in_unit_range(a in 0.0f32..1.0, b in 0.0f32..1.0, t in 0.0f32..=1.0) {
+            let hue = hue_lerp(a, b, t);
+            prop_assert!((0.0..1.0).contains(&hue), "{}", hue);
+        }
+
+        #[test]
+        fn hue_lerp_hits_endpoints(a in 0.0f32..1.0, b in 0.0f32..1.0) {
+            prop_assert!(hue_abs_distance(hue_lerp(a, b, 0.0), a) < 1e-4);
+            prop_assert!(hue_abs_distance(hue_lerp(a, b, 1.0), b) < 1e-4);
+        }
+
+        #[test]
+        fn hue_lerp_takes_shortest_arc(a in 0.0f32..1.0, b in 0.0f32..1.0, t in 0.0f32..=1.0) {
+            let travelled = hue_abs_distance(a, hue_lerp(a, b, t));
+            prop_assert!((travelled - t * hue_abs_distance(a, b)).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn hue_lerp_wraps_through_red() {
+        assert!((hue_lerp(0.9, 0.1, 0.5) - 0.0).abs() < 1e-6);
+        assert!((hue_lerp(0.9, 0.1, 0.25) - 0.95).abs() < 1e-6);
+    }
+
+    /// Relative luminance, what CIE lightness is computed from.
+    fn luminance(color: Color32) -> f32 {
+        0.2126 * linear_f32_from_gamma_u8(color.r())
+            + 0.7152 * linear_f32_from_gamma_u8(color.g())
+            + 0.0722 * linear_f32_from_gamma_u8(color.b())
+    }
+
+    #[test]
+    fn color_lerp_ex_reproduces_the_source_color() {
+        let src = Color32::from_rgb(200, 60, 30);
+        let lerped = color_lerp_ex(src, Color32::from_rgb(30, 90, 200), 0.0, 0.7, 0.1);
+        for (a, b) in lerped.to_array().iter().zip(src.to_array()) {
+            assert!(a.abs_diff(b) <= 1, "{:?} != {:?}", lerped, src);
+        }
+    }
+
+    #[test]
+    fn color_lerp_ex_desaturates_in_linear_light() {
+        // Fully desaturated halfway, a gray as light as the source
+        let src = Color32::from_rgb(200, 60, 30);
+        let gray = color_lerp_ex(src, Color32::from_rgb(30, 90, 200), 0.5, 1.0, 0.1);
+        assert!(gray.r().abs_diff(gray.g()) <= 1 && gray.g().abs_diff(gray.b()) <= 1);
+        assert!(
+            (luminance(gray) - luminance(src)).abs() < 0.01,
+            "{:?} is not as light as {:?}",
+            gray,
+            src
+        );
+    }
 }
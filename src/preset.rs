@@ -1,10 +1,12 @@
 use std::{
+    cell::OnceCell,
     fs::{self, remove_file, DirEntry},
     path::{Path, PathBuf},
 };
 
 use crate::{
-    common::SplineMode,
+    colormaps::{BUILTIN_COLORMAPS, COLORMAP_FIT_SETTINGS},
+    common::{GradientMode, SplineMode},
     datatypes::control_point::ControlPoint,
     error::{Result, ZError},
     ui_egui::app::AppDataCtx,
@@ -29,37 +31,63 @@ impl PresetEntity {
         }
     }
 
-    pub fn apply(&self, control_points: &mut Vec<ControlPoint>, spline_mode: &mut SplineMode) {
-        self.data.clone().apply(control_points, spline_mode);
+    pub fn apply(
+        &self,
+        control_points: &mut Vec<ControlPoint>,
+        spline_mode: &mut SplineMode,
+        gradient_mode: &mut GradientMode,
+    ) {
+        self.data
+            .clone()
+            .apply(control_points, spline_mode, gradient_mode);
     }
 
-    pub fn into(self) -> (Vec<ControlPoint>, SplineMode) {
-        (self.data.control_points, self.data.spline_mode)
+    pub fn into(self) -> (Vec<ControlPoint>, SplineMode, GradientMode) {
+        (
+            self.data.control_points,
+            self.data.spline_mode,
+            self.data.gradient_mode,
+        )
     }
 
     pub fn make_preset_data(
         control_points: &Vec<ControlPoint>,
         spline_mode: &SplineMode,
+        gradient_mode: &GradientMode,
     ) -> AppDataCtx {
         AppDataCtx {
             spline_mode: *spline_mode,
             control_points: control_points.clone(),
+            gradient_mode: *gradient_mode,
         }
     }
 }
 
 impl AppDataCtx {
-    pub fn apply(self, control_points: &mut Vec<ControlPoint>, spline_mode: &mut SplineMode) {
+    pub fn apply(
+        self,
+        control_points: &mut Vec<ControlPoint>,
+        spline_mode: &mut SplineMode,
+        gradient_mode: &mut GradientMode,
+    ) {
         *control_points = self.control_points;
         *spline_mode = self.spline_mode;
+        *gradient_mode = self.gradient_mode;
     }
 }
 
 impl From<(Vec<ControlPoint>, SplineMode)> for AppDataCtx {
     fn from(value: (Vec<ControlPoint>, SplineMode)) -> Self {
+        (value.0, value.1, GradientMode::Open).into()
+    }
+}
+
+impl From<(Vec<ControlPoint>, SplineMode, GradientMode)> for AppDataCtx {
+    fn from(value: (Vec<ControlPoint>, SplineMode, GradientMode)) -> Self {
         Self {
             control_points: value.0,
             spline_mode: value.1,
+            gradient_mode: value.2,
         }
     }
 }
@@ -180,6 +208,9 @@ pub struct PresetHandler {
     pub presets: Vec<PresetEntity>,
     pub preset_selected_index: Option<usize>,
     pub auto_save_presets: bool,
+    /// Read-only, fitted on first use and never written to disk
+    #[serde(skip)]
+    builtin_presets: OnceCell<Vec<PresetEntity>>,
 }
 
 impl Default for PresetHandler {
@@ -193,6 +224,7 @@ impl Default for PresetHandler {
             presets: presets,
             preset_selected_index: None,
             auto_save_presets: false,
+            builtin_presets: OnceCell::new(),
         }
     }
 }
@@ -205,15 +237,33 @@ impl PresetHandler {
         &mut self.presets
     }
 
+    /// Well-known colormaps, see `BUILTIN_COLORMAPS`.
+    pub fn builtin_presets(&self) -> &Vec<PresetEntity> {
+        self.builtin_presets.get_or_init(|| {
+            BUILTIN_COLORMAPS
+                .iter()
+                .filter_map(|colormap| match colormap.control_points() {
+                    Ok(control_points) => Some(PresetEntity::new(
+                        colormap.name,
+                        (control_points, COLORMAP_FIT_SETTINGS.spline_mode).into(),
+                    )),
+                    Err(e) => {
+                        log::error!("Failed to fit colormap {}: {}", colormap.name, e);
+                        None
+                    }
+                })
+                .collect()
+        })
+    }
+
     pub fn apply_selected_preset(
         &mut self,
         control_points: &mut Vec<ControlPoint>,
         spline_mode: &mut SplineMode,
+        gradient_mode: &mut GradientMode,
     ) {
         if let Some(preset) = self.presets.get(self.preset_selected_index.unwrap()) {
-            let (preset_control_points, preset_spline_mode) = preset.clone().into();
-            *control_points = preset_control_points;
-            *spline_mode = preset_spline_mode;
+            preset.apply(control_points, spline_mode, gradient_mode);
             log::info!("Preset Applied!");
         } else {
             log::info!("No preset selected");
@@ -244,6 +294,7 @@ impl PresetHandler {
         name: &String,
         control_points: &Vec<ControlPoint>,
         spline_mode: &SplineMode,
+        gradient_mode: &GradientMode,
     ) -> Result<()> {
         for i in self.presets.iter() {
             if &i.name == name {
@@ -253,7 +304,10 @@ impl PresetHandler {
             }
         }
 
-        let preset = PresetEntity::new(name, (control_points.clone(), *spline_mode).into());
+        let preset = PresetEntity::new(
+            name,
+            PresetEntity::make_preset_data(control_points, spline_mode, gradient_mode),
+        );
         let index = self.presets.len();
         self.presets.push(preset);
 
@@ -277,3 +331,66 @@ impl PresetHandler {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{arb_control_points, arb_spline_mode, wrapping_control_points};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn preset_json_round_trip(
+            control_points in arb_control_points(8),
+            spline_mode in arb_spline_mode(),
+            gradient_mode in prop::sample::select(GradientMode::ALL.to_vec()),
+        ) {
+            let data = PresetEntity::make_preset_data(&control_points, &spline_mode, &gradient_mode);
+            let json = serde_json::to_string_pretty(&data).unwrap();
+            let loaded: AppDataCtx = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(loaded.spline_mode, spline_mode);
+            prop_assert_eq!(loaded.control_points, control_points);
+            prop_assert_eq!(loaded.gradient_mode, gradient_mode);
+        }
+    }
+
+    #[test]
+    fn presets_without_gradient_mode_load_as_open() {
+        let json = r#"{ "spline_mode": "Linear", "control_points": [] }"#;
+        let loaded: AppDataCtx = serde_json::from_str(json).unwrap();
+        assert_eq!(loaded.gradient_mode, GradientMode::Open);
+    }
+
+    #[test]
+    fn load_presets_from_folder() {
+        let folder =
+            std::env::temp_dir().join(format!("z_color_picker_presets_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+
+        let data: AppDataCtx = (wrapping_control_points(), SplineMode::Bezier).into();
+        let json = serde_json::to_string_pretty(&data).unwrap();
+        fs::write(folder.join("wrapping.json"), json).unwrap();
+        fs::write(folder.join("broken.json"), "{ not json").unwrap();
+
+        let presets = load_presets(&folder);
+        fs::remove_dir_all(&folder).unwrap();
+
+        // The broken preset is logged and skipped
+        let presets = presets.unwrap();
+        assert_eq!(presets.len(), 1);
+        assert_eq!(presets[0].name, "wrapping");
+        let (control_points, spline_mode, _) = presets[0].clone().into();
+        assert_eq!(control_points, wrapping_control_points());
+        assert_eq!(spline_mode, SplineMode::Bezier);
+    }
+
+    #[test]
+    fn load_presets_from_empty_folder_fails() {
+        let folder =
+            std::env::temp_dir().join(format!("z_color_picker_no_presets_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let presets = load_presets(&folder);
+        fs::remove_dir_all(&folder).unwrap();
+        assert!(presets.is_err());
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use image::RgbaImage;
+
+use crate::error::{Result, ZError};
+
+/// The portal may wait for the user to allow the screenshot.
+const DESKTOP_CAPTURE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// `capture_desktop` running on a worker thread, so a portal that never answers can't freeze
+/// the UI. Poll it every frame.
+#[derive(Clone)]
+pub struct DesktopCapture {
+    result: Arc<Mutex<Option<Result<RgbaImage>>>>,
+    started: Instant,
+}
+
+impl DesktopCapture {
+    pub fn start() -> Self {
+        let result = Arc::new(Mutex::new(None));
+        let worker_result = result.clone();
+        std::thread::spawn(move || {
+            let captured = capture_desktop();
+            // Nobody is waiting any more once timed out, the capture is dropped
+            if let Ok(mut slot) = worker_result.lock() {
+                *slot = Some(captured);
+            }
+        });
+        Self {
+            result,
+            started: Instant::now(),
+        }
+    }
+
+    /// `None` while the capture is still running. A capture that outlives the timeout is
+    /// reported as failed, its thread is left to finish on its own.
+    pub fn poll(&self) -> Option<Result<RgbaImage>> {
+        let finished = match self.result.lock() {
+            Ok(mut slot) => slot.take(),
+            Err(_) => Some(Err(ZError::Message(
+                "Desktop capture thread panicked".to_string(),
+            ))),
+        };
+        finished.or_else(|| {
+            (self.started.elapsed() > DESKTOP_CAPTURE_TIMEOUT).then(|| {
+                Err(ZError::Message(format!(
+                    "Desktop capture timed out after {}s",
+                    DESKTOP_CAPTURE_TIMEOUT.as_secs()
+                )))
+            })
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+enum Backend {
+    X11,
+    Portal,
+}
+
+/// Screenshot of the whole desktop. Wayland sessions go through the xdg-desktop-portal, X11
+/// reads the root window, each is tried when the other fails.
+#[cfg(target_os = "linux")]
+pub fn capture_desktop() -> Result<RgbaImage> {
+    let backends = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        [("portal", Backend::Portal), ("X11", Backend::X11)]
+    } else {
+        [("X11", Backend::X11), ("portal", Backend::Portal)]
+    };
+
+    let mut errors = Vec::new();
+    for (name, capture) in backends {
+        let captured = match capture {
+            Backend::X11 => x11::capture(),
+            Backend::Portal => portal::capture(),
+        };
+        match captured {
+            Ok(image) => return Ok(image),
+            Err(e) => errors.push(format!("{}: {}", name, e)),
+        }
+    }
+    Err(ZError::Message(errors.join(", ")))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn capture_desktop() -> Result<RgbaImage> {
+    Err(ZError::Message(
+        "Desktop capture is not supported on this platform".to_string(),
+    ))
+}
+
+/// Path of a `file://` URI, with percent escapes decoded.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn file_uri_to_path(uri: &str) -> Option<std::path::PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let escaped = (encoded[i] == b'%')
+            .then(|| encoded.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok().map(Into::into)
+}
+
+/// 32 bits per pixel BGRX in least significant byte first order, what X servers use for
+/// 24 and 32 bit visuals.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn bgrx_to_rgba(width: u32, height: u32, data: &[u8]) -> Option<RgbaImage> {
+    let rgba = data
+        .chunks_exact(4)
+        .flat_map(|bgrx| [bgrx[2], bgrx[1], bgrx[0], 255])
+        .collect();
+    RgbaImage::from_raw(width, height, rgba)
+}
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use image::RgbaImage;
+    use x11rb::{
+        connection::Connection,
+        protocol::xproto::{ConnectionExt, ImageFormat, ImageOrder},
+    };
+
+    use super::bgrx_to_rgba;
+    use crate::error::{Result, ZError};
+
+    fn message(err: impl std::fmt::Display) -> ZError {
+        ZError::Message(err.to_string())
+    }
+
+    pub fn capture() -> Result<RgbaImage> {
+        let (connection, screen_num) = x11rb::connect(None).map_err(message)?;
+        let setup = connection.setup();
+        let screen = &setup.roots[screen_num];
+        let (width, height) = (screen.width_in_pixels, screen.height_in_pixels);
+
+        let reply = connection
+            .get_image(ImageFormat::Z_PIXMAP, screen.root, 0, 0, width, height, !0)
+            .map_err(message)?
+            .reply()
+            .map_err(message)?;
+
+        let bits_per_pixel = setup
+            .pixmap_formats
+            .iter()
+            .find(|format| format.depth == reply.depth)
+            .map(|format| format.bits_per_pixel);
+        if bits_per_pixel != Some(32) || setup.image_byte_order != ImageOrder::LSB_FIRST {
+            return Err(ZError::Message(format!(
+                "Unsupported X11 pixel format, depth {} with {:?} bits per pixel",
+                reply.depth, bits_per_pixel
+            )));
+        }
+
+        bgrx_to_rgba(width as u32, height as u32, &reply.data)
+            .ok_or_else(|| ZError::Message("X11 screenshot has the wrong size".to_string()))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod portal {
+    use std::{
+        collections::HashMap,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use image::RgbaImage;
+    use zbus::{
+        blocking::{Connection, Proxy},
+        zvariant::{OwnedObjectPath, OwnedValue, Value},
+    };
+
+    use super::file_uri_to_path;
+    use crate::{
+        error::{Result, ZError},
+        gradient_map::load_image,
+    };
+
+    const DESTINATION: &str = "org.freedesktop.portal.Desktop";
+    /// Keeps request paths unique while a timed out capture is still waiting for its response
+    static NEXT_REQUEST: AtomicUsize = AtomicUsize::new(0);
+
+    fn message(err: impl std::fmt::Display) -> ZError {
+        ZError::Message(err.to_string())
+    }
+
+    /// org.freedesktop.portal.Screenshot, non-interactive. The portal saves the screenshot to
+    /// a file which is removed again once loaded.
+    pub fn capture() -> Result<RgbaImage> {
+        let connection = Connection::session().map_err(message)?;
+
+        // The request object is only known after the call returns, subscribing to its
+        // Response before calling needs the path predicted from our token
+        let token = format!(
+            "z_color_picker_{}_{}",
+            std::process::id(),
+            NEXT_REQUEST.fetch_add(1, Ordering::Relaxed)
+        );
+        let sender = connection
+            .unique_name()
+            .ok_or_else(|| ZError::Message("No D-Bus unique name".to_string()))?
+            .trim_start_matches(':')
+            .replace('.', "_");
+        let request_path = format!(
+            "/org/freedesktop/portal/desktop/request/{}/{}",
+            sender, token
+        );
+        let request = Proxy::new(
+            &connection,
+            DESTINATION,
+            request_path.as_str(),
+            "org.freedesktop.portal.Request",
+        )
+        .map_err(message)?;
+        let mut responses = request.receive_signal("Response").map_err(message)?;
+
+        let screenshot = Proxy::new(
+            &connection,
+            DESTINATION,
+            "/org/freedesktop/portal/desktop",
+            "org.freedesktop.portal.Screenshot",
+        )
+        .map_err(message)?;
+        let options: HashMap<&str, Value> = HashMap::from([
+            ("handle_token", Value::from(token.as_str())),
+            ("interactive", Value::from(false)),
+        ]);
+        let _: OwnedObjectPath = screenshot
+            .call("Screenshot", &("", options))
+            .map_err(message)?;
+
+        let response = responses
+            .next()
+            .ok_or_else(|| ZError::Message("Portal closed without a response".to_string()))?;
+        let body = response.body();
+        let (code, results): (u32, HashMap<String, OwnedValue>) =
+            body.deserialize().map_err(message)?;
+        if code != 0 {
+            return Err(ZError::Message(
+                "Screenshot was cancelled or denied".to_string(),
+            ));
+        }
+
+        let uri = results
+            .get("uri")
+            .and_then(|uri| <&str>::try_from(uri).ok().map(str::to_string))
+            .ok_or_else(|| ZError::Message("Portal response has no uri".to_string()))?;
+        let path = file_uri_to_path(&uri)
+            .ok_or_else(|| ZError::Message(format!("Not a file uri: {}", uri)))?;
+        let image = load_image(&path.to_string_lossy())?;
+        if let Err(e) = std::fs::remove_file(&path) {
+            log::warn!("Failed to remove portal screenshot {:?}: {}", path, e);
+        }
+
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_file_uris() {
+        assert_eq!(
+            file_uri_to_path("file:///home/me/Pictures/Screenshot%20from%202024.png"),
+            Some("/home/me/Pictures/Screenshot from 2024.png".into())
+        );
+        assert_eq!(
+            file_uri_to_path("file:///tmp/100%25%zz.png"),
+            Some("/tmp/100%%zz.png".into())
+        );
+        assert_eq!(file_uri_to_path("https://example.com/a.png"), None);
+    }
+
+    #[test]
+    fn converts_bgrx_pixels() {
+        let image = bgrx_to_rgba(2, 1, &[1, 2, 3, 0, 10, 20, 30, 0]).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [3, 2, 1, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [30, 20, 10, 255]);
+        assert!(bgrx_to_rgba(2, 2, &[0; 8]).is_none());
+    }
+}
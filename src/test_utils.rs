@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+
+use image::RgbImage;
+use proptest::prelude::*;
+
+use crate::{
+    common::{GradientMode, SplineMode},
+    compare::ComparedGradient,
+    datatypes::{
+        control_point::{ControlPoint, HueInterpolation},
+        hsv_key_value::HsvKeyValue,
+    },
+};
+
+pub const IMPLEMENTED_SPLINE_MODES: [SplineMode; 4] = SplineMode::IMPLEMENTED;
+
+pub const EPSILON: f32 = 1e-4;
+/// Per channel, leaves room for float differences between platforms
+const GOLDEN_TOLERANCE: u8 = 2;
+
+/// Hues compared around the circle, so 0.9999 and 0.0 are close.
+pub fn assert_hsv_close(a: &HsvKeyValue, b: &HsvKeyValue) {
+    let hue_diff = (a.h() - b.h()).rem_euclid(1.0);
+    assert!(
+        (a.s() - b.s()).abs() < EPSILON
+            && (a.v() - b.v()).abs() < EPSILON
+            && hue_diff.min(1.0 - hue_diff) < EPSILON,
+        "{:?} != {:?}",
+        a,
+        b
+    );
+}
+
+pub fn arb_hue_interpolation() -> impl Strategy<Value = HueInterpolation> {
+    prop::sample::select(HueInterpolation::ALL.to_vec())
+}
+
+pub fn arb_spline_mode() -> impl Strategy<Value = SplineMode> {
+    prop::sample::select(IMPLEMENTED_SPLINE_MODES.to_vec())
+}
+
+fn arb_tangent() -> impl Strategy<Value = HsvKeyValue> {
+    (-0.2f32..0.2, -0.2f32..0.2, -0.2f32..0.2).prop_map(|(s, v, h)| HsvKeyValue::new(s, v, h))
+}
+
+pub fn arb_control_point() -> impl Strategy<Value = ControlPoint> {
+    (
+        0.0f32..=1.0,
+        0.0f32..=1.0,
+        0.0f32..1.0,
+        arb_tangent(),
+        arb_tangent(),
+        arb_hue_interpolation(),
+    )
+        .prop_map(|(s, v, h, tangent_in, tangent_out, hue_interpolation)| {
+            let mut control_point = ControlPoint::new(
+                HsvKeyValue::new(s, v, h),
+                0.0,
+                [Some(tangent_in), Some(tangent_out)],
+            );
+            *control_point.hue_interpolation_mut() = hue_interpolation;
+            control_point
+        })
+}
+
+pub fn arb_control_points(max_len: usize) -> impl Strategy<Value = Vec<ControlPoint>> {
+    prop::collection::vec(arb_control_point(), 2..=max_len)
+}
+
+/// An open Hermite gradient, what the compare and blend tests start from.
+pub fn compared_gradient(control_points: Vec<ControlPoint>) -> ComparedGradient {
+    ComparedGradient {
+        control_points,
+        spline_mode: SplineMode::HermiteBezier,
+        gradient_mode: GradientMode::Open,
+    }
+}
+
+/// A red to blue gradient through the wraparound, used by golden and snapshot tests.
+pub fn wrapping_control_points() -> Vec<ControlPoint> {
+    [
+        HsvKeyValue::new(0.9, 0.9, 0.85),
+        HsvKeyValue::new(0.6, 1.0, 0.05),
+        HsvKeyValue::new(0.8, 0.5, 0.3),
+        HsvKeyValue::new(0.3, 0.9, 0.6),
+    ]
+    .into_iter()
+    .map(|val| {
+        ControlPoint::new(
+            val,
+            0.0,
+            [
+                Some(HsvKeyValue::new(-0.1, 0.05, -0.05)),
+                Some(HsvKeyValue::new(0.1, -0.05, 0.05)),
+            ],
+        )
+    })
+    .collect()
+}
+
+/// Decreasing, wrapping-decreasing and wrapping-increasing shortest arcs, so every
+/// `HueInterpolation` method takes a different route through at least one segment.
+pub fn hue_interpolation_control_points() -> Vec<ControlPoint> {
+    [0.6, 0.3, 0.9, 0.1]
+        .into_iter()
+        .map(|h| ControlPoint::new(HsvKeyValue::new(0.8, 0.9, h), 0.0, [None, None]))
+        .collect()
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.png", name))
+}
+
+/// Compares `image` to the committed golden PNG. Set `UPDATE_GOLDEN=1` to write it after an
+/// intended change or for a new golden, a missing golden fails the test otherwise.
+pub fn assert_golden(name: &str, image: &RgbImage) {
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        image.save(&path).unwrap();
+        return;
+    }
+    assert!(
+        path.exists(),
+        "missing golden {}, run with UPDATE_GOLDEN=1 to create it",
+        path.display()
+    );
+
+    let golden = image::open(&path).unwrap().to_rgb8();
+    assert_eq!(golden.dimensions(), image.dimensions(), "{}", name);
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let expected = golden.get_pixel(x, y);
+        let matches = pixel
+            .0
+            .iter()
+            .zip(expected.0)
+            .all(|(a, b)| a.abs_diff(b) <= GOLDEN_TOLERANCE);
+        assert!(
+            matches,
+            "{} differs at ({}, {}): {:?} != golden {:?}",
+            name, x, y, pixel, expected
+        );
+    }
+}
@@ -1,12 +1,26 @@
 pub mod app;
+pub mod blend;
+pub mod channel_curves;
 pub mod clipboard;
 pub mod color_picker;
+pub mod color_space_3d;
+pub mod colormaps;
+pub mod compare;
 pub mod content_windows;
+pub mod contrast_matrix;
 pub mod control_points;
 pub mod curves;
 pub mod debug_windows;
+pub mod exporter;
+pub mod eyedropper;
 pub mod gradient;
+pub mod gradient_extract;
+pub mod gradient_fit;
+pub mod gradient_map;
+pub mod harmony;
 pub mod panes;
 pub mod previewer;
+#[cfg(test)]
+pub mod snapshot;
 pub mod tangents;
 pub mod ui_common;
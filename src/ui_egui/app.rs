@@ -1,5 +1,5 @@
 use arboard::ImageData;
-use ecolor::Color32;
+use ecolor::{Color32, HsvaGamma};
 use eframe::egui::{self, Layout, PointerButton, Rect, Ui};
 use serde::{Deserialize, Serialize};
 use std::{
@@ -15,9 +15,11 @@ use std::{
 use winapi::shared::winerror::ERROR_INCOMPATIBLE_SERVICE_SID_TYPE;
 
 use crate::{
-    common::{ColorStringCopy, SplineMode},
+    color::gamut::GamutSettings,
+    common::{ColorStringCopy, GradientMode, SplineMode},
     datatypes::control_point::{
         ControlPoint, ControlPointStorage, ControlPointTangent, ControlPointValue,
+        HueInterpolation, TangentMode,
     },
     image_processing::{u8u8u8_to_u8u8u8u8, u8u8u8u8_to_u8, FramePixelRead, Rgb},
     logger::LogCollector,
@@ -30,9 +32,12 @@ use crate::{
         content_windows::WindowZColorPickerOptions,
         control_points,
         debug_windows::{DebugWindowControlPoints, DebugWindowTestWindow},
+        eyedropper::Eyedropper,
         panes::{
-            ColorPickerOptionsPane, ColorPickerPane, LogPane, Pane, PreviewerPane, TreeBehavior,
-            ZAppPane,
+            BlendPane, ChannelCurvesPane, ColorPickerOptionsPane, ColorPickerPane,
+            ColorSpace3dPane, ColormapsPane, ComparePane, ContrastMatrixPane, ExporterPane,
+            GradientExtractPane, GradientFitPane, GradientMapPane, HarmonyPane, LogPane, Pane,
+            PreviewerPane, TreeBehavior, ZAppPane,
         },
         previewer::{PreviewerUiResponses, ZPreviewer},
         ui_common::ContentWindow,
@@ -42,7 +47,7 @@ use eframe::{
     epaint::{Pos2, Vec2},
     CreationContext,
 };
-use egui_tiles::Tile;
+use egui_tiles::{Container, Tile, TileId};
 
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 enum AppState {
@@ -61,6 +66,8 @@ struct MouseClickEvent {
 pub struct AppDataCtx {
     pub spline_mode: SplineMode,
     pub control_points: Vec<ControlPoint>,
+    #[serde(default)]
+    pub gradient_mode: GradientMode,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,28 +76,88 @@ pub struct ZColorPickerOptions {
     pub is_hue_middle_interpolated: bool,
     pub is_insert_right: bool,
     pub is_window_lock: bool,
+    #[serde(default)]
+    pub gamut_settings: GamutSettings,
+    #[serde(default)]
+    pub show_curvature_comb: bool,
+    #[serde(default)]
+    pub show_continuity: bool,
+    #[serde(default)]
+    pub enforce_g1: bool,
 }
 
 impl Default for ZColorPickerOptions {
     fn default() -> Self {
         Self {
             is_curve_locked: false,
-            is_hue_middle_interpolated: true,
+            is_hue_middle_interpolated: false,
             is_insert_right: true,
             is_window_lock: true,
+            gamut_settings: GamutSettings::default(),
+            show_curvature_comb: false,
+            show_continuity: false,
+            enforce_g1: false,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
-pub struct ZColorPickerAppContext {
+pub type GradientId = usize;
+
+/// One open gradient, with the state of its own color picker and previewer panes.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GradientDocument {
+    pub id: GradientId,
+    pub name: String,
     pub control_points: Vec<ControlPoint>,
     pub spline_mode: SplineMode,
+    #[serde(default)]
+    pub gradient_mode: GradientMode,
+    /// Name of the preset this gradient was loaded from or saved as
+    #[serde(default)]
+    pub source_preset: Option<String>,
+    pub z_color_picker: Rc<RefCell<ZColorPickerWrapper>>,
+    pub previewer: ZPreviewer,
+}
+
+impl GradientDocument {
+    pub fn new(id: GradientId, control_points: Vec<ControlPoint>, spline_mode: SplineMode) -> Self {
+        Self {
+            id,
+            name: format!("Gradient {}", id + 1),
+            control_points,
+            spline_mode,
+            gradient_mode: GradientMode::default(),
+            source_preset: None,
+            z_color_picker: Rc::new(RefCell::new(ZColorPickerWrapper::default())),
+            previewer: ZPreviewer::default(),
+        }
+    }
+}
+
+fn default_gradients() -> Vec<GradientDocument> {
+    vec![GradientDocument::new(
+        0,
+        ZColorPickerAppContext::DEFAULT_STARTUP_CONTROL_POINTS.to_vec(),
+        SplineMode::HermiteBezier,
+    )]
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ZColorPickerAppContext {
+    /// Never empty, the last gradient can not be closed
+    #[serde(default = "default_gradients")]
+    pub gradients: Vec<GradientDocument>,
+    /// Gradient the shared tool panes work on
+    #[serde(default)]
+    pub active_gradient_id: GradientId,
+    #[serde(default)]
+    next_gradient_id: GradientId,
+    /// Control points copied from one gradient to paste into another
+    #[serde(skip)]
+    pub copied_control_points: Vec<ControlPoint>,
 
     pub preset_handler: PresetHandler,
 
-    pub z_color_picker: Rc<RefCell<ZColorPickerWrapper>>,
-    pub previewer: ZPreviewer,
     pub color_copy_format: ColorStringCopy,
     #[serde(skip)]
     debug_window_control_points: DebugWindowControlPoints,
@@ -110,11 +177,13 @@ pub struct ZColorPickerAppContext {
 
     #[serde(skip)]
     pub options_window: WindowZColorPickerOptions,
+    #[serde(skip)]
+    pub eyedropper: Eyedropper,
 }
 
 const LAZY_TANGENT_DELTA: f32 = 0.01;
 impl ZColorPickerAppContext {
-    const DEFAULT_STARTUP_CONTROL_POINTS: [ControlPoint; 4] = [
+    pub const DEFAULT_STARTUP_CONTROL_POINTS: [ControlPoint; 4] = [
         ControlPoint::ControlPointSimple(ControlPointStorage {
             val: ControlPointValue {
                 val: [0.25, 0.33, 0.0],
@@ -128,6 +197,8 @@ impl ZColorPickerAppContext {
                     val: [LAZY_TANGENT_DELTA, 0.0, 0.0],
                 }),
             ],
+            tangent_mode: TangentMode::Free,
+            hue_interpolation: HueInterpolation::Shortest,
         }),
         ControlPoint::ControlPointSimple(ControlPointStorage {
             val: ControlPointValue {
@@ -142,6 +213,8 @@ impl ZColorPickerAppContext {
                     val: [LAZY_TANGENT_DELTA, 0.0, 0.0],
                 }),
             ],
+            tangent_mode: TangentMode::Free,
+            hue_interpolation: HueInterpolation::Shortest,
         }),
         ControlPoint::ControlPointSimple(ControlPointStorage {
             val: ControlPointValue {
@@ -156,6 +229,8 @@ impl ZColorPickerAppContext {
                     val: [LAZY_TANGENT_DELTA, 0.0, 0.0],
                 }),
             ],
+            tangent_mode: TangentMode::Free,
+            hue_interpolation: HueInterpolation::Shortest,
         }),
         ControlPoint::ControlPointSimple(ControlPointStorage {
             val: ControlPointValue {
@@ -170,21 +245,23 @@ impl ZColorPickerAppContext {
                     val: [LAZY_TANGENT_DELTA, 0.0, 0.0],
                 }),
             ],
+            tangent_mode: TangentMode::Free,
+            hue_interpolation: HueInterpolation::Shortest,
         }),
     ];
 
     pub fn default() -> Self {
-        let z_color_picker_wrapper = ZColorPickerWrapper::default();
         let mut preset_handler = PresetHandler::default();
         preset_handler.init_presets();
+        let gradients = default_gradients();
         Self {
-            control_points: Self::DEFAULT_STARTUP_CONTROL_POINTS.to_vec(),
-            spline_mode: SplineMode::HermiteBezier,
+            active_gradient_id: gradients[0].id,
+            next_gradient_id: gradients.len(),
+            gradients,
+            copied_control_points: Vec::new(),
 
             preset_handler,
 
-            z_color_picker: Rc::new(RefCell::new(z_color_picker_wrapper)),
-            previewer: ZPreviewer::default(),
             color_copy_format: ColorStringCopy::default(),
             debug_window_control_points: DebugWindowControlPoints::new(Pos2 { x: 200.0, y: 200.0 }),
             debug_window_test: DebugWindowTestWindow::new(Pos2 { x: 200.0, y: 200.0 }),
@@ -200,7 +277,68 @@ impl ZColorPickerAppContext {
             stored_ui_responses: PreviewerUiResponses::default(),
             open_tabs: HashSet::default(),
             options_window: WindowZColorPickerOptions::new(Pos2::new(200.0, 200.0)),
+            eyedropper: Eyedropper::default(),
+        }
+    }
+
+    pub fn gradient(&self, id: GradientId) -> Option<&GradientDocument> {
+        self.gradients.iter().find(|gradient| gradient.id == id)
+    }
+
+    pub fn gradient_mut(&mut self, id: GradientId) -> Option<&mut GradientDocument> {
+        self.gradients.iter_mut().find(|gradient| gradient.id == id)
+    }
+
+    /// The gradient shared tool panes work on, the first one if the active one was closed.
+    pub fn active_gradient(&self) -> &GradientDocument {
+        self.gradient(self.active_gradient_id)
+            .unwrap_or(&self.gradients[0])
+    }
+
+    pub fn active_gradient_mut(&mut self) -> &mut GradientDocument {
+        let index = self
+            .gradients
+            .iter()
+            .position(|gradient| gradient.id == self.active_gradient_id)
+            .unwrap_or(0);
+        &mut self.gradients[index]
+    }
+
+    /// Opens a copy of gradient `id` as a variant to compare against and makes it active.
+    pub fn duplicate_gradient(&mut self, id: GradientId) -> Option<GradientId> {
+        let source = self.gradient(id)?;
+        let new_id = self.next_gradient_id.max(
+            self.gradients
+                .iter()
+                .map(|gradient| gradient.id + 1)
+                .max()
+                .unwrap_or(0),
+        );
+        let mut duplicate =
+            GradientDocument::new(new_id, source.control_points.clone(), source.spline_mode);
+        duplicate.gradient_mode = source.gradient_mode;
+        duplicate.source_preset = source.source_preset.clone();
+        duplicate.z_color_picker.borrow_mut().options =
+            source.z_color_picker.borrow().options.clone();
+
+        self.gradients.push(duplicate);
+        self.next_gradient_id = new_id + 1;
+        self.active_gradient_id = new_id;
+        log::info!("Opened gradient {} as a copy of {}", new_id, id);
+        Some(new_id)
+    }
+
+    /// Closes gradient `id`, the last open gradient stays open.
+    pub fn close_gradient(&mut self, id: GradientId) -> bool {
+        if self.gradients.len() <= 1 {
+            return false;
+        }
+        let len = self.gradients.len();
+        self.gradients.retain(|gradient| gradient.id != id);
+        if self.active_gradient_id == id {
+            self.active_gradient_id = self.gradients[0].id;
         }
+        self.gradients.len() != len
     }
 }
 
@@ -296,38 +434,200 @@ impl ZApp {
     ) -> egui_tiles::Tree<Pane> {
         let mut tiles = egui_tiles::Tiles::default();
 
-        let mut tabs = vec![];
+        let gradient_tiles: Vec<_> = ctx
+            .borrow()
+            .gradients
+            .iter()
+            .map(|gradient| Self::insert_gradient_tiles(&mut tiles, ctx.clone(), gradient.id))
+            .collect();
+        let tile_gradients = tiles.insert_tab_tile(gradient_tiles);
+
+        let pane_log = LogPane {
+            title: Some("Log".to_string()),
+            log_buffer: log_buffer.clone(),
+            scroll_to_bottom: true,
+        };
+        let pane_contrast_matrix = ContrastMatrixPane {
+            title: Some("Contrast".to_string()),
+            ctx: ctx.clone(),
+            data: Default::default(),
+        };
+        let pane_exporter = ExporterPane {
+            title: Some("Export".to_string()),
+            ctx: ctx.clone(),
+            settings: Default::default(),
+        };
+        let pane_gradient_map = GradientMapPane {
+            title: Some("Gradient map".to_string()),
+            ctx: ctx.clone(),
+            data: Default::default(),
+        };
+        let pane_gradient_extract = GradientExtractPane {
+            title: Some("Extract".to_string()),
+            ctx: ctx.clone(),
+            data: Default::default(),
+        };
+        let pane_gradient_fit = GradientFitPane {
+            title: Some("Fit".to_string()),
+            ctx: ctx.clone(),
+            data: Default::default(),
+        };
+        let pane_harmony = HarmonyPane {
+            title: Some("Harmony".to_string()),
+            ctx: ctx.clone(),
+            data: Default::default(),
+        };
+        let pane_colormaps = ColormapsPane {
+            title: Some("Colormaps".to_string()),
+            ctx: ctx.clone(),
+            data: Default::default(),
+        };
+        let pane_compare = ComparePane {
+            title: Some("Compare".to_string()),
+            ctx: ctx.clone(),
+            data: Default::default(),
+        };
+        let pane_blend = BlendPane {
+            title: Some("Blend".to_string()),
+            ctx: ctx.clone(),
+            data: Default::default(),
+        };
+
+        let tile_console = tiles.insert_pane(Pane::Log(pane_log));
+        let tile_contrast_matrix = tiles.insert_pane(Pane::ContrastMatrix(pane_contrast_matrix));
+        let tile_exporter = tiles.insert_pane(Pane::Exporter(pane_exporter));
+        let tile_gradient_map = tiles.insert_pane(Pane::GradientMap(pane_gradient_map));
+        let tile_gradient_extract = tiles.insert_pane(Pane::GradientExtract(pane_gradient_extract));
+        let tile_gradient_fit = tiles.insert_pane(Pane::GradientFit(pane_gradient_fit));
+        let tile_harmony = tiles.insert_pane(Pane::Harmony(pane_harmony));
+        let tile_colormaps = tiles.insert_pane(Pane::Colormaps(pane_colormaps));
+        let tile_compare = tiles.insert_pane(Pane::Compare(pane_compare));
+        let tile_blend = tiles.insert_pane(Pane::Blend(pane_blend));
+        let tile_bottom = tiles.insert_tab_tile(vec![
+            tile_console,
+            tile_contrast_matrix,
+            tile_exporter,
+            tile_gradient_map,
+            tile_gradient_extract,
+            tile_gradient_fit,
+            tile_harmony,
+            tile_colormaps,
+            tile_compare,
+            tile_blend,
+        ]);
+
+        let root = tiles.insert_vertical_tile(vec![tile_gradients, tile_bottom]);
+
+        egui_tiles::Tree::new("my_tree", root, tiles)
+    }
 
+    /// Color picker, options and previewer panes of one gradient, returns the tile holding them.
+    fn insert_gradient_tiles(
+        tiles: &mut egui_tiles::Tiles<Pane>,
+        ctx: Rc<RefCell<ZColorPickerAppContext>>,
+        gradient: GradientId,
+    ) -> TileId {
         let pane_color_picker = ColorPickerPane {
             title: None,
             ctx: ctx.clone(),
+            gradient,
         };
         let pane_options = ColorPickerOptionsPane {
             title: None,
             ctx: ctx.clone(),
+            gradient,
         };
         let pane_previewer = PreviewerPane {
             title: None,
             ctx: ctx.clone(),
+            gradient,
         };
-        let pane_log = LogPane {
-            title: Some("Log".to_string()),
-            log_buffer: log_buffer.clone(),
-            scroll_to_bottom: true,
+        let pane_channel_curves = ChannelCurvesPane {
+            title: Some("Channels".to_string()),
+            ctx: ctx.clone(),
+            gradient,
+        };
+        let pane_color_space_3d = ColorSpace3dPane {
+            title: Some("3D".to_string()),
+            ctx: ctx.clone(),
+            gradient,
+            data: Default::default(),
         };
 
         let tile_color_picker = tiles.insert_pane(Pane::ColorPicker(pane_color_picker));
         let tile_options = tiles.insert_pane(Pane::ColorPickerOptionsPane(pane_options));
         let tile_previewer = tiles.insert_pane(Pane::Previewer(pane_previewer));
-        let tile_console = tiles.insert_pane(Pane::Log(pane_log));
+        let tile_color_space_3d = tiles.insert_pane(Pane::ColorSpace3d(pane_color_space_3d));
+        let tile_channel_curves = tiles.insert_pane(Pane::ChannelCurves(pane_channel_curves));
+        let tile_right = tiles.insert_tab_tile(vec![
+            tile_previewer,
+            tile_channel_curves,
+            tile_color_space_3d,
+        ]);
 
         let vertical_tile = tiles.insert_vertical_tile(vec![tile_color_picker, tile_options]);
-        let master_tile = tiles.insert_horizontal_tile(vec![vertical_tile, tile_previewer]);
-        tabs.push(tiles.insert_vertical_tile(vec![master_tile, tile_console]));
+        tiles.insert_horizontal_tile(vec![vertical_tile, tile_right])
+    }
 
-        let root = tiles.insert_tab_tile(tabs);
+    /// Adds tiles for newly opened gradients as tabs next to the other gradients, and removes
+    /// the panes of closed ones.
+    fn sync_gradient_tiles(
+        tree: &mut egui_tiles::Tree<Pane>,
+        ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    ) {
+        let mut shown = HashSet::new();
+        let mut closed = Vec::new();
+        let mut sibling = None;
+        {
+            let app_ctx = ctx.borrow();
+            for (tile_id, tile) in tree.tiles.iter() {
+                let Tile::Pane(pane) = tile else {
+                    continue;
+                };
+                let Some(gradient) = pane.gradient() else {
+                    continue;
+                };
+                if app_ctx.gradient(gradient).is_none() {
+                    closed.push(*tile_id);
+                } else if let Pane::ColorPicker(_) = pane {
+                    shown.insert(gradient);
+                    sibling = Some(*tile_id);
+                }
+            }
+        }
+        for tile_id in closed {
+            tree.remove_recursively(tile_id);
+        }
 
-        egui_tiles::Tree::new("my_tree", root, tiles)
+        let opened: Vec<GradientId> = ctx
+            .borrow()
+            .gradients
+            .iter()
+            .map(|gradient| gradient.id)
+            .filter(|id| !shown.contains(id))
+            .collect();
+        for gradient in opened {
+            let tile = Self::insert_gradient_tiles(&mut tree.tiles, ctx.clone(), gradient);
+            // The closest tab container around another gradient's color picker
+            let mut container = sibling.and_then(|id| tree.tiles.parent_of(id));
+            while let Some(id) = container {
+                if let Some(Tile::Container(Container::Tabs(_))) = tree.tiles.get(id) {
+                    break;
+                }
+                container = tree.tiles.parent_of(id);
+            }
+            match container
+                .or(tree.root)
+                .and_then(|id| tree.tiles.get_mut(id))
+            {
+                Some(Tile::Container(Container::Tabs(tabs))) => {
+                    tabs.add_child(tile);
+                    tabs.set_active(tile);
+                }
+                Some(Tile::Container(container)) => container.add_child(tile),
+                _ => log::error!("No container to open gradient {} in", gradient),
+            }
+        }
     }
 
     fn draw_ui_tree(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
@@ -335,6 +635,8 @@ impl ZApp {
             ui.with_layout(Layout::left_to_right(egui::Align::Min), |mut ui| {
                 let mut behavior = TreeBehavior {};
                 self.tree.ui(&mut behavior, ui);
+                Self::sync_gradient_tiles(&mut self.tree, self.app_ctx.clone());
+                self.update_active_gradient();
 
                 // Copy to clipboard
                 let middle_mouse_clicked = ctx.input(|i| i.pointer.middle_down());
@@ -422,6 +724,47 @@ impl ZApp {
         }
     }
 
+    /// Keeps the active gradient on screen, tool panes would otherwise edit a hidden tab.
+    fn update_active_gradient(&mut self) {
+        let visible: Vec<GradientId> = self
+            .tree
+            .active_tiles()
+            .into_iter()
+            .filter_map(|tile_id| match self.tree.tiles.get(tile_id) {
+                Some(Tile::Pane(pane @ Pane::ColorPicker(_))) => pane.gradient(),
+                _ => None,
+            })
+            .collect();
+        let mut app_ctx = self.app_ctx.borrow_mut();
+        if let Some(first) = visible.first() {
+            if !visible.contains(&app_ctx.active_gradient_id) {
+                app_ctx.active_gradient_id = *first;
+            }
+        }
+    }
+
+    /// Assigns the color picked by the eyedropper to the selected control point.
+    fn update_eyedropper(&mut self, ctx: &egui::Context) {
+        let app_ctx = &mut *self.app_ctx.borrow_mut();
+        let Some(color) = app_ctx.eyedropper.update(ctx) else {
+            return;
+        };
+
+        let gradient = app_ctx.active_gradient_mut();
+        let selected_index = gradient.z_color_picker.borrow().last_modifying_point_index;
+        match selected_index.and_then(|index| gradient.control_points.get_mut(index)) {
+            Some(control_point) => {
+                let hsv = HsvaGamma::from(color);
+                *control_point.val_mut() = ControlPointValue::new(hsv.s, hsv.v, hsv.h);
+                log::info!("Eyedropper picked {:?}", color);
+            }
+            None => log::warn!(
+                "Eyedropper picked {:?} but no control point is selected",
+                color
+            ),
+        }
+    }
+
     fn handle_clipboardcopy_event(&mut self) -> bool {
         let app_ctx = &mut self.app_ctx.borrow_mut();
         if let Some(event) = app_ctx.clipboard_event.take() {
@@ -485,7 +828,7 @@ impl ZApp {
     fn update_and_draw_debug_windows(&mut self, ui: &mut Ui) {
         // TODO: Should clean up this logic for borrowing
         let mut app_ctx = self.app_ctx.borrow_mut();
-        let control_points = app_ctx.control_points.clone();
+        let control_points = app_ctx.active_gradient().control_points.clone();
 
         app_ctx.debug_window_control_points.update(&control_points);
         app_ctx.debug_window_control_points.draw_ui(ui);
@@ -505,8 +848,8 @@ impl ZApp {
         {
             let app_ctx = &mut self.app_ctx.borrow_mut();
             let _input_ctx = ctx.input(|r| {
-                // Esc
-                if r.key_down(egui::Key::Escape) {
+                // Esc, unless it is cancelling the eyedropper
+                if r.key_down(egui::Key::Escape) && !app_ctx.eyedropper.is_active() {
                     user_quit = true;
                 }
 
@@ -579,6 +922,7 @@ impl eframe::App for ZApp {
             AppState::Idle => {
                 self.handle_clipboardcopy_event();
                 self.draw_ui_tree(ctx, frame);
+                self.update_eyedropper(ctx);
                 self.process_ctx_inputs(ctx, frame);
             }
             AppState::Exit => {
@@ -610,3 +954,75 @@ impl eframe::App for ZApp {
         // }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color_picker_tiles(tree: &egui_tiles::Tree<Pane>, gradient: GradientId) -> Vec<TileId> {
+        tree.tiles
+            .iter()
+            .filter_map(|(tile_id, tile)| match tile {
+                Tile::Pane(pane @ Pane::ColorPicker(_)) if pane.gradient() == Some(gradient) => {
+                    Some(*tile_id)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn duplicated_gradient_opens_next_to_the_original() {
+        let ctx = Rc::new(RefCell::new(ZColorPickerAppContext::default()));
+        let mut tree = ZApp::create_tree(ctx.clone(), Arc::new(Mutex::new(Vec::new())));
+        let first = ctx.borrow().active_gradient_id;
+
+        let second = ctx.borrow_mut().duplicate_gradient(first).unwrap();
+        ZApp::sync_gradient_tiles(&mut tree, ctx.clone());
+        assert_eq!(ctx.borrow().active_gradient_id, second);
+        assert_eq!(
+            ctx.borrow().gradient(second).unwrap().control_points,
+            ctx.borrow().gradient(first).unwrap().control_points
+        );
+
+        let tab_container = |tile_id: TileId| {
+            let mut id = tree.tiles.parent_of(tile_id);
+            while let Some(parent) = id {
+                if let Some(Tile::Container(Container::Tabs(_))) = tree.tiles.get(parent) {
+                    break;
+                }
+                id = tree.tiles.parent_of(parent);
+            }
+            id
+        };
+        let [first_tile] = color_picker_tiles(&tree, first)[..] else {
+            panic!("expected one color picker for the first gradient");
+        };
+        let [second_tile] = color_picker_tiles(&tree, second)[..] else {
+            panic!("expected one color picker for the second gradient");
+        };
+        assert!(tab_container(first_tile).is_some());
+        assert_eq!(tab_container(first_tile), tab_container(second_tile));
+    }
+
+    #[test]
+    fn closing_a_gradient_removes_its_panes() {
+        let ctx = Rc::new(RefCell::new(ZColorPickerAppContext::default()));
+        let mut tree = ZApp::create_tree(ctx.clone(), Arc::new(Mutex::new(Vec::new())));
+        let first = ctx.borrow().active_gradient_id;
+        let second = ctx.borrow_mut().duplicate_gradient(first).unwrap();
+        ZApp::sync_gradient_tiles(&mut tree, ctx.clone());
+
+        assert!(ctx.borrow_mut().close_gradient(second));
+        ZApp::sync_gradient_tiles(&mut tree, ctx.clone());
+        assert_eq!(ctx.borrow().active_gradient_id, first);
+        assert!(!tree
+            .tiles
+            .iter()
+            .any(|(_, tile)| matches!(tile, Tile::Pane(pane) if pane.gradient() == Some(second))));
+        assert_eq!(color_picker_tiles(&tree, first).len(), 1);
+
+        // The last gradient stays open
+        assert!(!ctx.borrow_mut().close_gradient(first));
+    }
+}
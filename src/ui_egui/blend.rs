@@ -0,0 +1,189 @@
+use eframe::egui::{self, Sense, Ui, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blend::blend_gradients,
+    colormaps::BUILTIN_COLORMAPS,
+    common::SplineMode,
+    compare::ComparedGradient,
+    datatypes::control_point::ControlPoint,
+    gradient_fit::FitResult,
+    ui_egui::{
+        app::ZColorPickerAppContext,
+        compare::{ui_source_combo, CompareSource},
+        gradient::paint_color_function_gradient,
+        previewer::CurveSampler,
+        ui_common::ui_spline_mode_combo,
+    },
+};
+
+const PREVIEW_HEIGHT: f32 = 24.0;
+
+pub enum BlendAction {
+    /// Replace the active gradient with the blend
+    Apply {
+        control_points: Vec<ControlPoint>,
+        spline_mode: SplineMode,
+    },
+    CreatePreset {
+        name: String,
+        control_points: Vec<ControlPoint>,
+        spline_mode: SplineMode,
+    },
+}
+
+/// What a blend was computed from.
+#[derive(Clone, PartialEq)]
+struct BlendKey {
+    a: ComparedGradient,
+    b: ComparedGradient,
+    t: f32,
+    spline_mode: SplineMode,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlendData {
+    pub a: CompareSource,
+    pub b: CompareSource,
+    pub t: f32,
+    pub spline_mode: SplineMode,
+    pub preset_name: String,
+    /// Fitting every frame is too slow, refitted when the key changes
+    #[serde(skip)]
+    blended: Option<(BlendKey, Option<FitResult>)>,
+}
+
+impl Default for BlendData {
+    fn default() -> Self {
+        Self {
+            a: CompareSource::Builtin(BUILTIN_COLORMAPS[0].name.to_string()),
+            b: CompareSource::Builtin(BUILTIN_COLORMAPS[1].name.to_string()),
+            t: 0.5,
+            spline_mode: SplineMode::HermiteBezier,
+            preset_name: String::new(),
+            blended: None,
+        }
+    }
+}
+
+/// Interpolates between two gradients, for variants (e.g. day to night) of two authored ends.
+pub fn ui_blend(
+    ui: &mut Ui,
+    data: &mut BlendData,
+    ctx: &ZColorPickerAppContext,
+) -> Option<BlendAction> {
+    egui::Grid::new("blend_grid")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("From");
+            ui_source_combo(ui, "blend_a", &mut data.a, ctx);
+            ui.end_row();
+
+            ui.label("To");
+            ui_source_combo(ui, "blend_b", &mut data.b, ctx);
+            ui.end_row();
+
+            ui.label("Amount");
+            ui.add(egui::Slider::new(&mut data.t, 0.0..=1.0))
+                .on_hover_text("0 is From, 1 is To, blended in OKLab");
+            ui.end_row();
+
+            ui.label("Spline mode");
+            ui_spline_mode_combo(ui, "blend_spline_mode", &mut data.spline_mode);
+            ui.end_row();
+        });
+
+    let (Some(a), Some(b)) = (data.a.resolve(ctx), data.b.resolve(ctx)) else {
+        ui.label("Select two gradients to blend, no preset is selected or it was removed");
+        return None;
+    };
+
+    let key = BlendKey {
+        a,
+        b,
+        t: data.t,
+        spline_mode: data.spline_mode,
+    };
+    if data
+        .blended
+        .as_ref()
+        .is_none_or(|(blended_with, _)| *blended_with != key)
+    {
+        let fitted = blend_gradients(&key.a, &key.b, key.t, key.spline_mode)
+            .inspect_err(|e| log::error!("Failed to blend gradients: {}", e))
+            .ok();
+        data.blended = Some((key.clone(), fitted));
+    }
+    let Some((BlendKey { a, b, .. }, Some(fitted))) = &data.blended else {
+        ui.label("The gradients could not be blended");
+        return None;
+    };
+    let control_points = fitted.control_points.clone();
+    let max_delta_e = fitted.max_delta_e;
+    let (rect, _) = ui.allocate_exact_size(
+        Vec2::new(ui.available_width(), PREVIEW_HEIGHT * 3.0),
+        Sense::hover(),
+    );
+    let row_height = rect.height() / 3.0;
+    let row = |i: f32| {
+        egui::Rect::from_min_size(
+            rect.min + Vec2::new(0.0, row_height * i),
+            Vec2::new(rect.width(), row_height),
+        )
+    };
+    let sampler = CurveSampler::new(&control_points, data.spline_mode);
+    let mapping = ctx
+        .active_gradient()
+        .z_color_picker
+        .borrow()
+        .options
+        .gamut_settings
+        .mapping;
+    paint_color_function_gradient(ui.painter(), row(0.0), a.color_at(mapping));
+    paint_color_function_gradient(ui.painter(), row(1.0), |x| {
+        sampler.sample(x).color_gamut_mapped(mapping)
+    });
+    paint_color_function_gradient(ui.painter(), row(2.0), b.color_at(mapping));
+
+    let default_name = format!(
+        "{} → {} {:.2}",
+        data.a.label(ctx),
+        data.b.label(ctx),
+        data.t
+    );
+    let mut action = None;
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut data.preset_name).hint_text(default_name.as_str()));
+        if ui.button("Save as preset").clicked() {
+            let name = if data.preset_name.is_empty() {
+                default_name.clone()
+            } else {
+                data.preset_name.clone()
+            };
+            action = Some(BlendAction::CreatePreset {
+                name,
+                control_points: control_points.clone(),
+                spline_mode: data.spline_mode,
+            });
+        }
+        if ui
+            .button("Apply")
+            .on_hover_text("Replace the active gradient with the blend")
+            .clicked()
+        {
+            action = Some(BlendAction::Apply {
+                control_points: control_points.clone(),
+                spline_mode: data.spline_mode,
+            });
+        }
+        ui.label(format!(
+            "{} control points, max ΔE {:.2}",
+            control_points.len(),
+            max_delta_e
+        ));
+    });
+
+    action
+}
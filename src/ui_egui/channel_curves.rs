@@ -0,0 +1,257 @@
+use eframe::egui::{
+    self, Align2, Color32, Id, Pos2, Rect, Sense, Shape, Stroke, TextStyle, Ui, Vec2,
+};
+
+use crate::{
+    common::SplineMode,
+    datatypes::{control_point::ControlPoint, hsv_key_value::HsvKeyValue},
+    ui_egui::previewer::CurveSampler,
+};
+
+const NUM_CURVE_SAMPLES: usize = 256;
+const LABEL_GUTTER_WIDTH: f32 = 24.0;
+const CONTROL_POINT_RADIUS: f32 = 5.0;
+const TANGENT_RADIUS: f32 = 3.5;
+const SELECTED_CONTROL_POINT_COLOR: Color32 = Color32::from_rgb(255, 220, 0);
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum HsvChannel {
+    Hue,
+    Saturation,
+    Value,
+}
+
+impl HsvChannel {
+    pub const ALL: [HsvChannel; 3] = [HsvChannel::Hue, HsvChannel::Saturation, HsvChannel::Value];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HsvChannel::Hue => "H",
+            HsvChannel::Saturation => "S",
+            HsvChannel::Value => "V",
+        }
+    }
+
+    /// Index into `HsvKeyValue::val`
+    pub fn index(&self) -> usize {
+        match self {
+            HsvChannel::Hue => 2,
+            HsvChannel::Saturation => 0,
+            HsvChannel::Value => 1,
+        }
+    }
+
+    fn curve_color(&self) -> Color32 {
+        match self {
+            HsvChannel::Hue => Color32::from_rgb(230, 120, 60),
+            HsvChannel::Saturation => Color32::from_rgb(90, 180, 230),
+            HsvChannel::Value => Color32::from_gray(220),
+        }
+    }
+
+    fn get(&self, val: &HsvKeyValue) -> f32 {
+        match self {
+            HsvChannel::Hue => val[2].rem_euclid(1.0),
+            _ => val[self.index()],
+        }
+    }
+
+    fn set(&self, val: &mut HsvKeyValue, new_value: f32) {
+        val[self.index()] = match self {
+            HsvChannel::Hue => new_value.rem_euclid(1.0),
+            _ => new_value.clamp(0.0, 1.0),
+        };
+    }
+}
+
+pub struct ChannelCurvesResponse {
+    pub changed: bool,
+    pub selected_index: Option<usize>,
+}
+
+/// x position in [0, 1] of each control point, matching how `CurveSampler` spreads them.
+fn control_point_x(index: usize, num_control_points: usize) -> f32 {
+    if num_control_points <= 1 {
+        0.5
+    } else {
+        index as f32 / (num_control_points - 1) as f32
+    }
+}
+
+fn ui_channel_curve(
+    ui: &mut Ui,
+    rect: Rect,
+    channel: HsvChannel,
+    samples: &[HsvKeyValue],
+    control_points: &mut [ControlPoint],
+    spline_mode: SplineMode,
+    response: &mut ChannelCurvesResponse,
+) {
+    let painter = ui.painter_at(rect.expand(CONTROL_POINT_RADIUS));
+    let plot_rect = Rect::from_min_max(
+        Pos2::new(rect.left() + LABEL_GUTTER_WIDTH, rect.top()),
+        rect.max,
+    );
+    let to_screen = |x: f32, y: f32| {
+        Pos2::new(
+            egui::lerp(plot_rect.x_range(), x),
+            egui::lerp(plot_rect.bottom()..=plot_rect.top(), y),
+        )
+    };
+
+    painter.rect_filled(plot_rect, 2.0, ui.visuals().extreme_bg_color);
+    painter.text(
+        Pos2::new(rect.left() + LABEL_GUTTER_WIDTH * 0.5, rect.center().y),
+        Align2::CENTER_CENTER,
+        channel.label(),
+        TextStyle::Body.resolve(ui.style()),
+        channel.curve_color(),
+    );
+
+    // Break the hue curve where it wraps around instead of drawing a line across the plot
+    let mut segment: Vec<Pos2> = Vec::new();
+    let mut prev_value: Option<f32> = None;
+    for (i, sample) in samples.iter().enumerate() {
+        let x = i as f32 / (samples.len() - 1).max(1) as f32;
+        let value = channel.get(sample);
+        if prev_value.is_some_and(|prev| (value - prev).abs() > 0.5) {
+            painter.add(Shape::line(
+                std::mem::take(&mut segment),
+                Stroke::new(2.0, channel.curve_color()),
+            ));
+        }
+        segment.push(to_screen(x, value));
+        prev_value = Some(value);
+    }
+    painter.add(Shape::line(
+        segment,
+        Stroke::new(2.0, channel.curve_color()),
+    ));
+
+    let num_control_points = control_points.len();
+    let value_per_pixel = 1.0 / plot_rect.height();
+    let segment_width = 1.0 / (num_control_points.max(2) - 1) as f32;
+    let id = Id::new("channel_curves").with(channel.index());
+
+    for (i, control_point) in control_points.iter_mut().enumerate() {
+        let x = control_point_x(i, num_control_points);
+        let value = channel.get(control_point.val());
+        let cp_screen = to_screen(x, value);
+        let is_selected = response.selected_index == Some(i);
+
+        let is_quadratic = spline_mode == SplineMode::QuadraticBezier;
+        if (spline_mode == SplineMode::Bezier || is_quadratic) && is_selected {
+            for tangent_index in 0..2 {
+                if (tangent_index == 0 && (i == 0 || is_quadratic))
+                    || (tangent_index == 1 && i + 1 == num_control_points)
+                {
+                    continue;
+                }
+                let Some(tangent) = control_point.tangents()[tangent_index] else {
+                    continue;
+                };
+
+                // Cubic Bezier handles sit a third into their segment, the quadratic one halfway
+                let direction = if tangent_index == 0 { -1.0 } else { 1.0 };
+                let handle_offset = if is_quadratic { 0.5 } else { 1.0 / 3.0 };
+                let tangent_screen = to_screen(
+                    x + direction * segment_width * handle_offset,
+                    value + tangent[channel.index()],
+                );
+                let tangent_response = ui.interact(
+                    Rect::from_center_size(tangent_screen, Vec2::splat(TANGENT_RADIUS * 3.0)),
+                    id.with((i, tangent_index)),
+                    Sense::drag(),
+                );
+                if tangent_response.dragged() {
+                    let delta = -tangent_response.drag_delta().y * value_per_pixel;
+                    if let Some(tangent) = &mut control_point.tangents_mut()[tangent_index] {
+                        tangent[channel.index()] += delta;
+                    }
+                    response.changed = true;
+                }
+
+                painter.line_segment(
+                    [cp_screen, tangent_screen],
+                    Stroke::new(1.0, Color32::WHITE.linear_multiply(0.25)),
+                );
+                painter.circle_stroke(
+                    tangent_screen,
+                    TANGENT_RADIUS,
+                    Stroke::new(1.0, Color32::WHITE),
+                );
+            }
+        }
+
+        let cp_response = ui.interact(
+            Rect::from_center_size(cp_screen, Vec2::splat(CONTROL_POINT_RADIUS * 3.0)),
+            id.with(i),
+            Sense::click_and_drag(),
+        );
+        if cp_response.clicked() || cp_response.drag_started() {
+            response.selected_index = Some(i);
+        }
+        if cp_response.dragged() {
+            let new_value = value - cp_response.drag_delta().y * value_per_pixel;
+            channel.set(control_point.val_mut(), new_value);
+            response.selected_index = Some(i);
+            response.changed = true;
+        }
+
+        let outline = if is_selected {
+            SELECTED_CONTROL_POINT_COLOR
+        } else {
+            Color32::BLACK
+        };
+        painter.circle(
+            cp_screen,
+            CONTROL_POINT_RADIUS,
+            control_point.val().color(),
+            Stroke::new(1.5, outline),
+        );
+    }
+}
+
+/// Hue, saturation and value of the gradient plotted against t, each editable on its own.
+pub fn ui_channel_curves(
+    ui: &mut Ui,
+    control_points: &mut [ControlPoint],
+    spline_mode: SplineMode,
+    selected_index: Option<usize>,
+) -> ChannelCurvesResponse {
+    let mut response = ChannelCurvesResponse {
+        changed: false,
+        selected_index,
+    };
+
+    let samples = CurveSampler::new(control_points, spline_mode).sample_n(NUM_CURVE_SAMPLES);
+
+    let rect = ui.available_rect_before_wrap();
+    ui.allocate_rect(rect, Sense::hover());
+
+    let spacing = ui.spacing().item_spacing.y;
+    let num_channels = HsvChannel::ALL.len() as f32;
+    let curve_height = (rect.height() - spacing * (num_channels - 1.0)) / num_channels;
+    for (i, channel) in HsvChannel::ALL.iter().enumerate() {
+        let curve_rect = Rect::from_min_size(
+            Pos2::new(
+                rect.left(),
+                rect.top() + i as f32 * (curve_height + spacing),
+            ),
+            Vec2::new(rect.width(), curve_height),
+        )
+        .shrink(CONTROL_POINT_RADIUS);
+
+        ui_channel_curve(
+            ui,
+            curve_rect,
+            *channel,
+            &samples,
+            control_points,
+            spline_mode,
+            &mut response,
+        );
+    }
+
+    response
+}
@@ -3,6 +3,7 @@ use std::{fs::File, io::Write, ops::Rem, time::Instant};
 use arboard::{Clipboard, ImageData};
 use ecolor::Color32;
 use eframe::egui::{self, Pos2, Rect};
+use image::RgbaImage;
 
 use crate::{
     common::ColorStringCopy,
@@ -55,6 +56,17 @@ pub fn write_pixels_to_clipboard(image_data: ImageData) -> Result<()> {
     Ok(())
 }
 
+pub fn read_image_from_clipboard() -> Result<RgbaImage> {
+    let mut clipboard = Clipboard::new()?;
+    let image_data = clipboard.get_image()?;
+    RgbaImage::from_raw(
+        image_data.width as u32,
+        image_data.height as u32,
+        image_data.bytes.into_owned(),
+    )
+    .ok_or_else(|| "Clipboard image has an unexpected size".to_string().into())
+}
+
 pub fn write_pixels_to_test_ppm(image_data: &ImageData, test_vec: Vec<Rgb>) -> Result<()> {
     let copy = image_data.clone();
 
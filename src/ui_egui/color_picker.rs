@@ -1,9 +1,15 @@
 use crate::{
-    common::{ColorStringCopy, SplineMode},
-    datatypes::control_point::{create_tangent_for_control_point, ControlPoint},
+    color::gamut::GamutSettings,
+    common::{ColorStringCopy, GradientMode, SplineMode},
+    datatypes::control_point::{
+        create_tangent_for_control_point, ControlPoint, HueInterpolation, TangentMode,
+    },
     ui_egui::{
         app::ZColorPickerOptions,
-        control_points::{ui_ordered_control_points, ControlPointUiResult},
+        control_points::{
+            flatten_control_points, ui_hue_interpolation_menu, ui_ordered_control_points,
+            ControlPointUiResult,
+        },
         ui_common::contrast_color,
     },
 };
@@ -19,20 +25,27 @@ use eframe::{
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    math::hue_lerp,
-    ui_egui::curves::ui_ordered_spline_gradient,
+    ui_egui::curves::{
+        enforce_diverging, ui_continuity_labels, ui_curvature_comb, ui_ordered_spline_gradient,
+    },
+    ui_egui::tangents::{apply_tangent_modes, enforce_g1_tangents},
     ui_egui::ui_common::{color_slider_1d, color_slider_2d, color_text_ui},
 };
 
 pub struct MainColorPickerCtx<'a> {
     pub control_points: &'a mut Vec<ControlPoint>,
     pub spline_mode: SplineMode,
+    pub gradient_mode: GradientMode,
     pub color_copy_format: ColorStringCopy,
     pub last_modifying_point_index: &'a mut Option<usize>,
     pub dragging_index: &'a mut Option<usize>,
     pub control_point_right_clicked: &'a mut Option<usize>,
     pub is_hue_middle_interpolated: bool,
     pub is_curve_locked: bool,
+    pub gamut_settings: GamutSettings,
+    pub show_curvature_comb: bool,
+    pub show_continuity: bool,
+    pub enforce_g1: bool,
 }
 
 pub struct ZColorPicker<'a> {
@@ -112,6 +125,9 @@ pub struct ZColorPickerWrapper {
     pub dragging_index: Option<usize>,
     pub right_clicked_on_index: Option<usize>,
     pub options: ZColorPickerOptions,
+    /// Gradient mode of the last frame, constraints are enforced again when it changes
+    #[serde(skip)]
+    last_gradient_mode: Option<GradientMode>,
 }
 
 impl Default for ZColorPickerWrapper {
@@ -121,6 +137,7 @@ impl Default for ZColorPickerWrapper {
             dragging_index: None,
             right_clicked_on_index: None,
             options: ZColorPickerOptions::default(),
+            last_gradient_mode: None,
         };
 
         new_color_picker
@@ -130,12 +147,12 @@ impl Default for ZColorPickerWrapper {
 impl ZColorPickerWrapper {
     pub fn pre_draw_update(
         &mut self,
-        control_points: &mut Vec<ControlPoint>,
+        control_points: &mut [ControlPoint],
         spline_mode: SplineMode,
     ) {
         if spline_mode == SplineMode::Bezier {
             // Force init tangents
-            for control_point in control_points {
+            for control_point in control_points.iter_mut() {
                 for tang in control_point.tangents_mut().iter_mut() {
                     if tang.is_none() {
                         *tang = Some(create_tangent_for_control_point());
@@ -143,6 +160,18 @@ impl ZColorPickerWrapper {
                 }
             }
         }
+        if spline_mode == SplineMode::QuadraticBezier {
+            // Start the shared handle halfway along the segment
+            for i in 0..control_points.len().saturating_sub(1) {
+                if control_points[i].tangents()[1].is_none() {
+                    // Along the hue arc the segment travels, as flatten_control_points unwraps it
+                    let from = *control_points[i].val();
+                    let mut to = *control_points[i + 1].val();
+                    to[2] = control_points[i].hue_interpolation().unwrap(from[2], to[2]);
+                    control_points[i].tangents_mut()[1] = Some((to - from) * 0.5);
+                }
+            }
+        }
     }
 
     pub fn draw_ui(
@@ -150,6 +179,7 @@ impl ZColorPickerWrapper {
         ui: &mut Ui,
         control_points: &mut Vec<ControlPoint>,
         spline_mode: SplineMode,
+        gradient_mode: GradientMode,
         color_copy_format: &ColorStringCopy,
     ) -> Response {
         let inner_response = ui.vertical(|ui| {
@@ -158,18 +188,23 @@ impl ZColorPickerWrapper {
             let mut ctx = MainColorPickerCtx {
                 control_points: control_points,
                 spline_mode: spline_mode,
+                gradient_mode,
                 color_copy_format: *color_copy_format,
                 last_modifying_point_index: &mut self.last_modifying_point_index,
                 dragging_index: &mut self.dragging_index,
                 control_point_right_clicked: &mut self.right_clicked_on_index,
                 is_hue_middle_interpolated: self.options.is_hue_middle_interpolated,
                 is_curve_locked: self.options.is_curve_locked,
+                gamut_settings: self.options.gamut_settings,
+                show_curvature_comb: self.options.show_curvature_comb,
+                show_continuity: self.options.show_continuity,
+                enforce_g1: self.options.enforce_g1,
             };
 
             let color_picker_widget: ZColorPicker<'_> = ZColorPicker::new(&mut ctx);
             let main_color_picker_response = ui.add(color_picker_widget);
 
-            self.post_draw(control_points, &main_color_picker_response);
+            self.post_draw(control_points, gradient_mode, &main_color_picker_response);
 
             main_color_picker_response
         });
@@ -278,20 +313,27 @@ impl ZColorPickerWrapper {
         }
     }
 
+    /// Spaces the middle hues evenly along the way the segments' hue interpolations travel
+    /// from the first hue to the last.
     fn apply_interpolate_hue_middle(&mut self, control_points: &mut Vec<ControlPoint>) {
-        let num_points = control_points.len();
-        if num_points >= 2 {
-            let points = &mut control_points[..];
-
-            let first_index = 0;
-            let last_index = points.len() - 1;
-            let first_hue = points[first_index].val()[2];
-            let last_hue: f32 = points[last_index].val()[2];
-
-            for i in 1..last_index {
-                let t = (i as f32) / (points.len() - 1) as f32;
-                let hue = hue_lerp(first_hue, last_hue, t);
-                points[i].val_mut()[2] = hue;
+        if control_points.len() < 3 {
+            return;
+        }
+        let flattened = flatten_control_points(control_points);
+        let last_index = control_points.len() - 1;
+        let first_hue = flattened[0].val()[2];
+        let step = (flattened[last_index].val()[2] - first_hue) / last_index as f32;
+
+        for i in 1..last_index {
+            control_points[i].val_mut()[2] = (first_hue + step * i as f32).rem_euclid(1.0);
+        }
+        // E.g. a Longest segment split into short steps would go the other way on the next pass
+        for i in 0..last_index {
+            let from = control_points[i].val()[2];
+            let to = control_points[i + 1].val()[2];
+            let hue_interpolation = control_points[i].hue_interpolation_mut();
+            if (hue_interpolation.unwrap(from, to) - from - step).abs() > 1e-4 {
+                *hue_interpolation = HueInterpolation::for_delta(step);
             }
         }
     }
@@ -315,17 +357,42 @@ impl ZColorPickerWrapper {
     fn post_draw(
         &mut self,
         control_points: &mut Vec<ControlPoint>,
+        gradient_mode: GradientMode,
         z_color_picker_response: &Response,
     ) {
-        self.apply_control_point_constraints(control_points);
+        // Only after edits, gradients loaded from presets and panes keep their hues
+        if z_color_picker_response.changed() {
+            self.apply_control_point_constraints(control_points);
+        }
 
         match self.right_clicked_on_index {
+            Some(_) if !gradient_mode.allows_control_point_count(control_points.len() - 1) => {
+                log::warn!(
+                    "{} gradients need an odd number of control points, switch to Open to remove one",
+                    gradient_mode.label()
+                );
+            }
             Some(index) => {
                 self.remove_control_point(control_points, index);
             }
             _ => {}
         }
-        self.handle_doubleclick_event(control_points, z_color_picker_response);
+        if gradient_mode.allows_control_point_count(control_points.len() + 1) {
+            self.handle_doubleclick_event(control_points, z_color_picker_response);
+        } else if z_color_picker_response.double_clicked_by(PointerButton::Primary) {
+            log::warn!(
+                "{} gradients need an odd number of control points, switch to Open to add one",
+                gradient_mode.label()
+            );
+        }
+
+        let mode_switched = self.last_gradient_mode != Some(gradient_mode);
+        self.last_gradient_mode = Some(gradient_mode);
+        if gradient_mode == GradientMode::Diverging
+            && (mode_switched || z_color_picker_response.changed())
+        {
+            enforce_diverging(control_points, self.last_modifying_point_index);
+        }
     }
 
     pub fn handle_doubleclick_event(
@@ -519,11 +586,32 @@ pub fn main_color_picker(
             ui,
             ctx.control_points,
             ctx.spline_mode,
+            ctx.gradient_mode,
+            &ctx.gamut_settings,
             &slider_2d_response,
         );
+        if ctx.show_curvature_comb {
+            ui_curvature_comb(
+                ui,
+                ctx.control_points,
+                ctx.spline_mode,
+                ctx.gradient_mode,
+                &slider_2d_response,
+            );
+        }
 
         handle_control_points_ui(ui, ctx, &slider_2d_response, &mut is_modifying_index);
 
+        if ctx.show_continuity {
+            ui_continuity_labels(
+                ui,
+                ctx.control_points,
+                ctx.spline_mode,
+                ctx.gradient_mode,
+                &slider_2d_response,
+            );
+        }
+
         slider_2d_response
     })
     .inner
@@ -633,7 +721,7 @@ fn handle_control_points_ui(
         *is_modifying_index,
         ctx.is_hue_middle_interpolated,
         &slider_2d_response,
-        ctx.spline_mode == SplineMode::Bezier,
+        ctx.spline_mode,
     );
 
     let ControlPointUiResult {
@@ -642,8 +730,13 @@ fn handle_control_points_ui(
         dragged_point,
         selected_tangent,
         dragged_tangent,
+        tangent_mode_change,
     } = control_point_ui_result;
 
+    if let Some((index, mode)) = tangent_mode_change {
+        *ctx.control_points[index].tangent_mode_mut() = mode;
+    }
+
     // Right click
     *ctx.control_point_right_clicked = hovering_control_point.and_then(|(r, idx)| {
         if r.clicked_by(PointerButton::Secondary) {
@@ -689,9 +782,21 @@ fn handle_control_points_ui(
         }
     }
 
+    let mut dragged_tangent_anchor = None;
+
     // Drag tangent
     if let Some(r) = dragged_tangent {
         if r.dragged_by(PointerButton::Primary) {
+            dragged_tangent_anchor = ctx.last_modifying_point_index.zip(selected_tangent);
+            if let Some(index) = *ctx.last_modifying_point_index {
+                // Grabbing a computed handle hands it over to the user, like vector editors do
+                let tangent_mode = ctx.control_points[index].tangent_mode_mut();
+                *tangent_mode = match *tangent_mode {
+                    TangentMode::AutoSmooth => TangentMode::Aligned,
+                    TangentMode::Vector => TangentMode::Free,
+                    mode => mode,
+                };
+            }
             if let Some(index) = *ctx.last_modifying_point_index {
                 if let Some(tang_idx) = selected_tangent {
                     if let Some(tang) = &mut ctx.control_points[index].tangents_mut()[tang_idx] {
@@ -705,6 +810,13 @@ fn handle_control_points_ui(
             }
         }
     }
+
+    if ctx.spline_mode == SplineMode::Bezier {
+        apply_tangent_modes(ctx.control_points, dragged_tangent_anchor);
+        if ctx.enforce_g1 {
+            enforce_g1_tangents(ctx.control_points, dragged_tangent_anchor);
+        }
+    }
 }
 
 fn main_color_picker_color_at_function(hue: f32, alpha: f32) -> impl Fn(f32, f32) -> Color32 {
@@ -807,6 +919,15 @@ pub fn ui_hue_control_points_overlay(
             control_points[i].val_mut()[2] +=
                 response.drag_delta().x / container_response.rect.width();
         }
+        if i + 1 < control_points.len() {
+            response.context_menu(|ui| {
+                if let Some(hue_interpolation) =
+                    ui_hue_interpolation_menu(ui, control_points[i].hue_interpolation())
+                {
+                    *control_points[i].hue_interpolation_mut() = hue_interpolation;
+                }
+            });
+        }
 
         ui.painter().add(Shape::convex_polygon(
             gizmo_rect,
@@ -949,3 +1070,143 @@ pub fn ui_t_control_points_overlay(
 
     (container_response, selected_key_frame)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::control_point::ControlPointValue;
+    use proptest::prelude::*;
+
+    #[test]
+    fn format_color_as_hex() {
+        let color = Color32::from_rgba_premultiplied(0x12, 0xab, 0xef, 0xff);
+        assert_eq!(
+            format_color_as(color, ColorStringCopy::HEX, None),
+            "FF12ABEF"
+        );
+        assert_eq!(
+            format_color_as(color, ColorStringCopy::HEX, Some(false)),
+            "FF12ABEF"
+        );
+        assert_eq!(
+            format_color_as(color, ColorStringCopy::HEX, Some(true)),
+            "12ABEF"
+        );
+        assert_eq!(
+            format_color_as(color, ColorStringCopy::HEXNOA, None),
+            "12ABEF"
+        );
+    }
+
+    #[test]
+    fn format_color_as_falls_back_to_rgb() {
+        let color = Color32::from_rgb(1, 22, 255);
+        assert_eq!(
+            format_color_as(color, ColorStringCopy::RGB, None),
+            "RGB(1, 22, 255)"
+        );
+    }
+
+    #[test]
+    fn quadratic_handle_follows_the_hue_direction() {
+        let handle_hue = |hue_interpolation| {
+            let mut control_points = vec![
+                ControlPoint::new_simple(ControlPointValue::new(0.5, 0.5, 0.9), 0.0),
+                ControlPoint::new_simple(ControlPointValue::new(0.5, 0.5, 0.1), 1.0),
+            ];
+            *control_points[0].hue_interpolation_mut() = hue_interpolation;
+            ZColorPickerWrapper::default()
+                .pre_draw_update(&mut control_points, SplineMode::QuadraticBezier);
+            control_points[0].tangents()[1].unwrap()[2]
+        };
+        // Halfway along the arc from 0.9 to 0.1, across 1.0 or back through 0.5
+        assert!((handle_hue(HueInterpolation::Shortest) - 0.1).abs() < 1e-6);
+        assert!((handle_hue(HueInterpolation::Longest) + 0.4).abs() < 1e-6);
+    }
+
+    /// Runs `post_draw` once, as the picker does after a frame that did or did not edit a point.
+    fn post_draw_once(
+        color_picker: &mut ZColorPickerWrapper,
+        control_points: &mut Vec<ControlPoint>,
+        edited: bool,
+    ) {
+        let ctx = egui::Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let mut response = ui.allocate_response(vec2(10.0, 10.0), Sense::hover());
+                if edited {
+                    response.mark_changed();
+                }
+                color_picker.post_draw(control_points, GradientMode::Open, &response);
+            });
+        });
+    }
+
+    #[test]
+    fn middle_hues_follow_the_hue_interpolation() {
+        let hues = |control_points: &[ControlPoint]| -> Vec<f32> {
+            control_points.iter().map(|cp| cp.val()[2]).collect()
+        };
+        let mut color_picker = ZColorPickerWrapper::default();
+
+        // Clockwise the long way from 0.1 to 0.2, through 0.6
+        let mut control_points: Vec<ControlPoint> = [0.1, 0.6, 0.2]
+            .into_iter()
+            .enumerate()
+            .map(|(i, h)| ControlPoint::new_simple(ControlPointValue::new(0.5, 0.5, h), i as f32))
+            .collect();
+        for control_point in &mut control_points {
+            *control_point.hue_interpolation_mut() = HueInterpolation::Clockwise;
+        }
+        color_picker.apply_interpolate_hue_middle(&mut control_points);
+        assert!((hues(&control_points)[1] - 0.65).abs() < 1e-5);
+        assert_eq!(
+            control_points[0].hue_interpolation(),
+            HueInterpolation::Clockwise
+        );
+
+        // Longest then Shortest travel 0.75 backwards, even steps are too short for Longest
+        let mut control_points: Vec<ControlPoint> = [0.0, 0.2, 0.25]
+            .into_iter()
+            .enumerate()
+            .map(|(i, h)| ControlPoint::new_simple(ControlPointValue::new(0.5, 0.5, h), i as f32))
+            .collect();
+        *control_points[0].hue_interpolation_mut() = HueInterpolation::Longest;
+        color_picker.apply_interpolate_hue_middle(&mut control_points);
+        assert!((hues(&control_points)[1] - 0.625).abs() < 1e-5);
+        let end_hue = |control_points: &[ControlPoint]| {
+            flatten_control_points(control_points).last().unwrap().val()[2]
+        };
+        assert!((end_hue(&control_points) + 0.75).abs() < 1e-5);
+        let interpolated = control_points.clone();
+        color_picker.apply_interpolate_hue_middle(&mut control_points);
+        assert_eq!(control_points, interpolated);
+    }
+
+    #[test]
+    fn loaded_colormap_keeps_its_hues() {
+        let colormap = crate::colormaps::BUILTIN_COLORMAPS
+            .iter()
+            .find(|colormap| colormap.name == "viridis")
+            .unwrap();
+        let loaded = colormap.control_points().unwrap();
+        let mut color_picker = ZColorPickerWrapper::default();
+        color_picker.options.is_hue_middle_interpolated = true;
+
+        let mut control_points = loaded.clone();
+        post_draw_once(&mut color_picker, &mut control_points, false);
+        assert_eq!(control_points, loaded);
+    }
+
+    proptest! {
+        #[test]
+        fn format_color_as_hex_parses_back(r: u8, g: u8, b: u8) {
+            let color = Color32::from_rgb(r, g, b);
+            let hex = format_color_as(color, ColorStringCopy::HEXNOA, None);
+            prop_assert_eq!(hex.len(), 6);
+            prop_assert_eq!(&hex, &hex.to_uppercase());
+            let parsed = u32::from_str_radix(&hex, 16).unwrap();
+            prop_assert_eq!(parsed, u32::from_be_bytes([0, r, g, b]));
+        }
+    }
+}
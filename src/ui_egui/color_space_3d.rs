@@ -0,0 +1,238 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use ecolor::{gamma_from_linear, HsvaGamma, Rgba};
+use eframe::egui::{self, Color32, Pos2, Rect, Sense, Stroke, Ui, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::{gamut::hsv_to_linear_srgb, oklab::linear_srgb_to_oklab},
+    common::{GradientMode, SplineMode},
+    datatypes::{control_point::ControlPoint, hsv_key_value::HsvKeyValue},
+    ui_egui::curves::generate_gradient_points_with_distance,
+};
+
+const CAMERA_DISTANCE: f32 = 4.0;
+const ORBIT_SPEED: f32 = 0.01;
+const WIREFRAME_SEGMENTS_PER_EDGE: usize = 24;
+const WIREFRAME_COLOR: Color32 = Color32::from_gray(110);
+const SELECTED_CONTROL_POINT_COLOR: Color32 = Color32::from_rgb(255, 220, 0);
+/// OKLab a/b rarely leave [-0.35, 0.35] for sRGB, scale them up to roughly match the L axis.
+const OKLAB_AB_SCALE: f32 = 3.0;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum ColorSpace3d {
+    #[default]
+    HsvCylinder,
+    Oklab,
+    RgbCube,
+}
+
+impl ColorSpace3d {
+    pub const ALL: [ColorSpace3d; 3] = [
+        ColorSpace3d::HsvCylinder,
+        ColorSpace3d::Oklab,
+        ColorSpace3d::RgbCube,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ColorSpace3d::HsvCylinder => "HSV cylinder",
+            ColorSpace3d::Oklab => "OKLab",
+            ColorSpace3d::RgbCube => "RGB cube",
+        }
+    }
+
+    /// Position in model space, y is up and everything fits roughly in [-1, 1].
+    pub fn position(&self, val: &HsvKeyValue) -> [f32; 3] {
+        match self {
+            ColorSpace3d::HsvCylinder => {
+                let angle = val.h() * TAU;
+                [
+                    val.s() * angle.cos(),
+                    val.v() * 2.0 - 1.0,
+                    val.s() * angle.sin(),
+                ]
+            }
+            _ => self.position_linear_srgb(hsv_to_linear_srgb(val)),
+        }
+    }
+
+    pub fn position_linear_srgb(&self, rgb: [f32; 3]) -> [f32; 3] {
+        match self {
+            ColorSpace3d::HsvCylinder => {
+                let hsv = HsvaGamma::from(Rgba::from_rgb(rgb[0], rgb[1], rgb[2]));
+                self.position(&HsvKeyValue::new(hsv.s, hsv.v, hsv.h))
+            }
+            ColorSpace3d::Oklab => {
+                let lab = linear_srgb_to_oklab(rgb);
+                [
+                    lab[1] * OKLAB_AB_SCALE,
+                    lab[0] * 2.0 - 1.0,
+                    lab[2] * OKLAB_AB_SCALE,
+                ]
+            }
+            ColorSpace3d::RgbCube => {
+                // Keep the sign so overshoot below zero still lands outside the cube
+                let gamma = |c: f32| c.signum() * gamma_from_linear(c.abs());
+                [
+                    gamma(rgb[0]) * 2.0 - 1.0,
+                    gamma(rgb[1]) * 2.0 - 1.0,
+                    gamma(rgb[2]) * 2.0 - 1.0,
+                ]
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorSpace3dData {
+    pub color_space: ColorSpace3d,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Default for ColorSpace3dData {
+    fn default() -> Self {
+        Self {
+            color_space: ColorSpace3d::default(),
+            yaw: 0.6,
+            pitch: 0.4,
+        }
+    }
+}
+
+struct OrbitProjection {
+    rect: Rect,
+    scale: f32,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl OrbitProjection {
+    fn new(rect: Rect, data: &ColorSpace3dData) -> Self {
+        Self {
+            rect,
+            scale: rect.width().min(rect.height()) * 0.9,
+            yaw: data.yaw,
+            pitch: data.pitch,
+        }
+    }
+
+    fn project(&self, p: [f32; 3]) -> Pos2 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let x = p[0] * cos_yaw + p[2] * sin_yaw;
+        let z = -p[0] * sin_yaw + p[2] * cos_yaw;
+
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let y = p[1] * cos_pitch - z * sin_pitch;
+        let z = p[1] * sin_pitch + z * cos_pitch;
+
+        let perspective = self.scale / (CAMERA_DISTANCE - z);
+        self.rect.center() + Vec2::new(x * perspective, -y * perspective)
+    }
+}
+
+/// Edges of the sRGB cube in linear light, subdivided so they bend when projected into
+/// non-linear spaces.
+fn srgb_gamut_wireframe() -> Vec<Vec<[f32; 3]>> {
+    let corners = |i: usize| [(i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32];
+
+    let mut edges = Vec::new();
+    for a in 0..8 {
+        for axis in 0..3 {
+            if a & (1 << axis) != 0 {
+                continue;
+            }
+            let start = corners(a);
+            let end = corners(a | (1 << axis));
+            let edge = (0..=WIREFRAME_SEGMENTS_PER_EDGE)
+                .map(|i| {
+                    let t = i as f32 / WIREFRAME_SEGMENTS_PER_EDGE as f32;
+                    [
+                        start[0] + (end[0] - start[0]) * t,
+                        start[1] + (end[1] - start[1]) * t,
+                        start[2] + (end[2] - start[2]) * t,
+                    ]
+                })
+                .collect();
+            edges.push(edge);
+        }
+    }
+
+    edges
+}
+
+pub fn ui_color_space_3d(
+    ui: &mut Ui,
+    data: &mut ColorSpace3dData,
+    control_points: &[ControlPoint],
+    spline_mode: SplineMode,
+    gradient_mode: GradientMode,
+    selected_index: Option<usize>,
+) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("color_space_3d")
+            .selected_text(data.color_space.label())
+            .show_ui(ui, |ui| {
+                for color_space in ColorSpace3d::ALL {
+                    ui.selectable_value(&mut data.color_space, color_space, color_space.label());
+                }
+            });
+        if ui.button("Reset view").clicked() {
+            let color_space = data.color_space;
+            *data = ColorSpace3dData {
+                color_space,
+                ..Default::default()
+            };
+        }
+    });
+
+    let rect = ui.available_rect_before_wrap();
+    let response = ui.allocate_rect(rect, Sense::drag());
+    if response.dragged() {
+        let delta = response.drag_delta();
+        data.yaw += delta.x * ORBIT_SPEED;
+        data.pitch = (data.pitch + delta.y * ORBIT_SPEED).clamp(-FRAC_PI_2, FRAC_PI_2);
+    }
+
+    let painter = ui.painter_at(rect);
+    let projection = OrbitProjection::new(rect, data);
+    let color_space = data.color_space;
+
+    for edge in srgb_gamut_wireframe() {
+        let points: Vec<Pos2> = edge
+            .iter()
+            .map(|rgb| projection.project(color_space.position_linear_srgb(*rgb)))
+            .collect();
+        painter.add(egui::Shape::line(points, Stroke::new(1.0, WIREFRAME_COLOR)));
+    }
+
+    let spline_points =
+        generate_gradient_points_with_distance(control_points, spline_mode, gradient_mode, 0.01);
+    for pair in spline_points.windows(2) {
+        painter.line_segment(
+            [
+                projection.project(color_space.position(&pair[0])),
+                projection.project(color_space.position(&pair[1])),
+            ],
+            Stroke::new(3.0, pair[1].color()),
+        );
+    }
+
+    for (i, control_point) in control_points.iter().enumerate() {
+        let pos = projection.project(color_space.position(control_point.val()));
+        let is_selected = selected_index == Some(i);
+        let radius = if is_selected { 7.0 } else { 5.0 };
+        let outline = if is_selected {
+            SELECTED_CONTROL_POINT_COLOR
+        } else {
+            Color32::BLACK
+        };
+        painter.circle(
+            pos,
+            radius,
+            control_point.val().color(),
+            Stroke::new(1.5, outline),
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use eframe::egui::{self, Sense, Ui, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    colormaps::{generate_template, TemplateKind, TemplateSettings},
+    common::{GradientMode, SplineMode},
+    datatypes::control_point::ControlPoint,
+    preset::PresetEntity,
+    ui_egui::{
+        gradient::paint_color_function_gradient, previewer::CurveSampler,
+        ui_common::ui_spline_mode_combo,
+    },
+};
+
+const PREVIEW_HEIGHT: f32 = 16.0;
+const NAME_WIDTH: f32 = 64.0;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColormapsData {
+    pub settings: TemplateSettings,
+    pub spline_mode: SplineMode,
+    #[serde(skip)]
+    generated: Vec<ControlPoint>,
+    /// Settings and spline mode `generated` was fitted with
+    #[serde(skip)]
+    generated_with: Option<(TemplateSettings, SplineMode)>,
+}
+
+impl Default for ColormapsData {
+    fn default() -> Self {
+        Self {
+            settings: TemplateSettings::default(),
+            spline_mode: SplineMode::HermiteBezier,
+            generated: Vec::new(),
+            generated_with: None,
+        }
+    }
+}
+
+impl ColormapsData {
+    fn update_generated(&mut self) {
+        let key = (self.settings, self.spline_mode);
+        if self.generated_with == Some(key) {
+            return;
+        }
+        match generate_template(&self.settings, self.spline_mode) {
+            Ok(control_points) => self.generated = control_points,
+            Err(e) => log::error!("Failed to generate template: {}", e),
+        }
+        self.generated_with = Some(key);
+    }
+}
+
+fn paint_preview(ui: &mut Ui, control_points: &[ControlPoint], spline_mode: SplineMode) {
+    let (rect, _) = ui.allocate_exact_size(
+        Vec2::new(ui.available_width(), PREVIEW_HEIGHT),
+        Sense::hover(),
+    );
+    let sampler = CurveSampler::new(control_points, spline_mode);
+    paint_color_function_gradient(ui.painter(), rect, |x| sampler.sample(x).color());
+}
+
+/// Built-in colormaps and sequential/diverging/cyclic templates, returns the one applied.
+pub fn ui_colormaps(
+    ui: &mut Ui,
+    data: &mut ColormapsData,
+    builtin_presets: &[PresetEntity],
+) -> Option<(Vec<ControlPoint>, SplineMode, GradientMode)> {
+    let mut applied = None;
+
+    ui.strong("Built-in");
+    for preset in builtin_presets {
+        ui.horizontal(|ui| {
+            let button =
+                egui::Button::new(preset.name.as_str()).min_size(Vec2::new(NAME_WIDTH, 0.0));
+            if ui.add(button).clicked() {
+                applied = Some(preset.clone().into());
+            }
+            paint_preview(ui, &preset.data.control_points, preset.data.spline_mode);
+        });
+    }
+
+    ui.separator();
+    ui.strong("Template");
+    egui::Grid::new("colormaps_template_grid")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("Kind");
+            egui::ComboBox::from_id_salt("colormaps_template_kind")
+                .selected_text(data.settings.kind.label())
+                .show_ui(ui, |ui| {
+                    for kind in TemplateKind::ALL {
+                        ui.selectable_value(&mut data.settings.kind, kind, kind.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Colors");
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgba(&mut data.settings.start)
+                    .on_hover_text("Start");
+                if data.settings.kind == TemplateKind::Diverging {
+                    ui.color_edit_button_srgba(&mut data.settings.middle)
+                        .on_hover_text("Middle");
+                }
+                ui.color_edit_button_srgba(&mut data.settings.end)
+                    .on_hover_text(match data.settings.kind {
+                        TemplateKind::Cyclic => "Halfway",
+                        _ => "End",
+                    });
+            });
+            ui.end_row();
+
+            ui.label("Spline mode");
+            ui_spline_mode_combo(ui, "colormaps_spline_mode", &mut data.spline_mode);
+            ui.end_row();
+        });
+
+    data.update_generated();
+    if !data.generated.is_empty() {
+        paint_preview(ui, &data.generated, data.spline_mode);
+        ui.horizontal(|ui| {
+            if ui.button("Apply").clicked() {
+                applied = Some((data.generated.clone(), data.spline_mode, GradientMode::Open));
+            }
+            ui.label(format!("{} control points", data.generated.len()));
+        });
+    }
+
+    applied
+}
@@ -0,0 +1,250 @@
+use eframe::egui::{self, pos2, Color32, Sense, Stroke, Ui, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::gamut::GamutMapping,
+    compare::{ComparedGradient, GradientComparison},
+    preset::PresetEntity,
+    ui_egui::{
+        app::{GradientId, ZColorPickerAppContext},
+        gradient::paint_color_function_gradient,
+    },
+};
+
+const GRADIENT_HEIGHT: f32 = 24.0;
+const PLOT_HEIGHT: f32 = 80.0;
+/// Roughly the smallest difference most people notice side by side
+const JUST_NOTICEABLE_DELTA_E: f32 = 2.3;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum CompareSource {
+    ActiveGradient,
+    /// The preset the active gradient was loaded from, as saved
+    SelectedPreset,
+    Gradient(GradientId),
+    Preset(String),
+    Builtin(String),
+}
+
+impl CompareSource {
+    pub fn label(&self, ctx: &ZColorPickerAppContext) -> String {
+        match self {
+            CompareSource::ActiveGradient => "Active gradient".to_string(),
+            CompareSource::SelectedPreset => "Source preset (saved)".to_string(),
+            CompareSource::Gradient(id) => ctx
+                .gradient(*id)
+                .map_or(format!("Closed gradient {}", id + 1), |gradient| {
+                    gradient.name.clone()
+                }),
+            CompareSource::Preset(name) => format!("Preset [{}]", name),
+            CompareSource::Builtin(name) => format!("Built-in [{}]", name),
+        }
+    }
+
+    /// Every source that can currently be compared.
+    pub fn available(ctx: &ZColorPickerAppContext) -> Vec<CompareSource> {
+        let mut sources = vec![CompareSource::ActiveGradient, CompareSource::SelectedPreset];
+        sources.extend(
+            ctx.gradients
+                .iter()
+                .map(|gradient| CompareSource::Gradient(gradient.id)),
+        );
+        sources.extend(
+            ctx.preset_handler
+                .presets
+                .iter()
+                .map(|preset| CompareSource::Preset(preset.name.clone())),
+        );
+        sources.extend(
+            ctx.preset_handler
+                .builtin_presets()
+                .iter()
+                .map(|preset| CompareSource::Builtin(preset.name.clone())),
+        );
+        sources
+    }
+
+    pub fn resolve(&self, ctx: &ZColorPickerAppContext) -> Option<ComparedGradient> {
+        let from_preset = |preset: &PresetEntity| ComparedGradient {
+            control_points: preset.data.control_points.clone(),
+            spline_mode: preset.data.spline_mode,
+            gradient_mode: preset.data.gradient_mode,
+        };
+        let find_preset = |presets: &[PresetEntity], name: &str| {
+            presets
+                .iter()
+                .find(|preset| preset.name == name)
+                .map(from_preset)
+        };
+        let gradient = match self {
+            CompareSource::ActiveGradient => Some(ctx.active_gradient()),
+            CompareSource::Gradient(id) => ctx.gradient(*id),
+            CompareSource::SelectedPreset => {
+                return ctx
+                    .active_gradient()
+                    .source_preset
+                    .as_ref()
+                    .and_then(|name| find_preset(&ctx.preset_handler.presets, name));
+            }
+            CompareSource::Preset(name) => return find_preset(&ctx.preset_handler.presets, name),
+            CompareSource::Builtin(name) => {
+                return find_preset(ctx.preset_handler.builtin_presets(), name)
+            }
+        };
+        gradient.map(|gradient| ComparedGradient {
+            control_points: gradient.control_points.clone(),
+            spline_mode: gradient.spline_mode,
+            gradient_mode: gradient.gradient_mode,
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompareData {
+    pub a: CompareSource,
+    pub b: CompareSource,
+}
+
+impl Default for CompareData {
+    fn default() -> Self {
+        // The saved version against the current state
+        Self {
+            a: CompareSource::SelectedPreset,
+            b: CompareSource::ActiveGradient,
+        }
+    }
+}
+
+pub fn ui_source_combo(
+    ui: &mut Ui,
+    id_salt: &str,
+    source: &mut CompareSource,
+    ctx: &ZColorPickerAppContext,
+) {
+    egui::ComboBox::from_id_salt(id_salt)
+        .selected_text(source.label(ctx))
+        .show_ui(ui, |ui| {
+            for available in CompareSource::available(ctx) {
+                let label = available.label(ctx);
+                ui.selectable_value(source, available, label);
+            }
+        });
+}
+
+fn paint_delta_e_plot(ui: &mut Ui, comparison: &GradientComparison) {
+    let (rect, response) =
+        ui.allocate_exact_size(Vec2::new(ui.available_width(), PLOT_HEIGHT), Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+    let max_delta_e = comparison
+        .max_delta_e()
+        .map_or(0.0, |(delta_e, _)| delta_e)
+        .max(JUST_NOTICEABLE_DELTA_E * 2.0);
+    let y_of = |delta_e: f32| rect.bottom() - delta_e / max_delta_e * rect.height();
+    let last = comparison.delta_e.len().saturating_sub(1).max(1) as f32;
+    let x_of = |i: usize| rect.left() + i as f32 / last * rect.width();
+
+    let jnd_y = y_of(JUST_NOTICEABLE_DELTA_E);
+    painter.hline(
+        rect.x_range(),
+        jnd_y,
+        Stroke::new(1.0, ui.visuals().weak_text_color()),
+    );
+    let points = comparison
+        .delta_e
+        .iter()
+        .enumerate()
+        .map(|(i, delta_e)| pos2(x_of(i), y_of(*delta_e)))
+        .collect();
+    painter.add(egui::Shape::line(
+        points,
+        Stroke::new(1.5, ui.visuals().strong_text_color()),
+    ));
+
+    if let Some(hover_pos) = response.hover_pos() {
+        let x = ((hover_pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+        let i = (x * last).round() as usize;
+        if let Some(delta_e) = comparison.delta_e.get(i) {
+            painter.vline(
+                x_of(i),
+                rect.y_range(),
+                Stroke::new(1.0, Color32::from_gray(128)),
+            );
+            response.on_hover_text(format!("t {:.3}: ΔE {:.2}", x, delta_e));
+        }
+    } else {
+        response.on_hover_text(format!(
+            "CIEDE2000 per position, the line is ΔE {}",
+            JUST_NOTICEABLE_DELTA_E
+        ));
+    }
+}
+
+/// Two gradients stacked, the ΔE between them along t and which control points changed.
+pub fn ui_compare(
+    ui: &mut Ui,
+    data: &mut CompareData,
+    ctx: &ZColorPickerAppContext,
+    mapping: GamutMapping,
+) {
+    egui::Grid::new("compare_grid")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("A");
+            ui_source_combo(ui, "compare_a", &mut data.a, ctx);
+            ui.end_row();
+
+            ui.label("B");
+            ui.horizontal(|ui| {
+                ui_source_combo(ui, "compare_b", &mut data.b, ctx);
+                if ui.button("⇅").on_hover_text("Swap A and B").clicked() {
+                    std::mem::swap(&mut data.a, &mut data.b);
+                }
+            });
+            ui.end_row();
+        });
+
+    let (Some(a), Some(b)) = (data.a.resolve(ctx), data.b.resolve(ctx)) else {
+        ui.label(
+            "Select two gradients to compare, the gradient was not loaded from a preset or it \
+             was removed",
+        );
+        return;
+    };
+
+    let (rect, _) = ui.allocate_exact_size(
+        Vec2::new(ui.available_width(), GRADIENT_HEIGHT * 2.0),
+        Sense::hover(),
+    );
+    let (rect_a, rect_b) = rect.split_top_bottom_at_fraction(0.5);
+    paint_color_function_gradient(ui.painter(), rect_a, a.color_at(mapping));
+    paint_color_function_gradient(ui.painter(), rect_b, b.color_at(mapping));
+
+    let comparison = GradientComparison::new(&a, &b, mapping);
+    paint_delta_e_plot(ui, &comparison);
+
+    if let Some((max_delta_e, max_x)) = comparison.max_delta_e() {
+        ui.label(format!(
+            "Max ΔE {:.2} at t {:.3}, mean ΔE {:.2}",
+            max_delta_e,
+            max_x,
+            comparison.mean_delta_e()
+        ));
+    }
+
+    ui.separator();
+    if comparison.changes.is_empty() {
+        ui.label("No control points changed");
+    } else {
+        ui.strong(format!(
+            "{} control points changed",
+            comparison.changes.len()
+        ));
+        for change in &comparison.changes {
+            ui.label(change.describe());
+        }
+    }
+}
@@ -1,8 +1,11 @@
 use eframe::egui;
+use eframe::egui::DragValue;
 use eframe::egui::TextEdit;
 
+use crate::color::gamut::Gamut;
+use crate::color::gamut::GamutMapping;
 use crate::common::ColorStringCopy;
-use crate::common::SplineMode;
+use crate::common::{GradientMode, SplineMode};
 use crate::datatypes::control_point::ControlPoint;
 use crate::egui::InnerResponse;
 use crate::egui::PointerButton;
@@ -12,6 +15,9 @@ use crate::preset::PresetEntity;
 use crate::preset::PresetHandler;
 use crate::ui_egui::app::AppDataCtx;
 use crate::ui_egui::app::ZColorPickerOptions;
+use crate::ui_egui::curves::{
+    simplify_control_points, sub_divide_all_control_points, sub_divide_control_points,
+};
 use crate::{egui::Pos2, ui_egui::ui_common::ContentWindow};
 
 pub struct WindowPresetDrawResult {
@@ -96,6 +102,7 @@ impl WindowZColorPickerOptions {
         options: &mut ZColorPickerOptions,
         control_points: &mut Vec<ControlPoint>,
         spline_mode: &mut SplineMode,
+        gradient_mode: &mut GradientMode,
         color_copy_format: &mut ColorStringCopy,
     ) {
         ui.horizontal(|ui| {
@@ -142,6 +149,11 @@ impl WindowZColorPickerOptions {
                         SplineMode::HermiteBezier,
                         "Hermite",
                     );
+                    ui.selectable_value(
+                        &mut temp_spline_mode,
+                        SplineMode::QuadraticBezier,
+                        "Quadratic",
+                    );
                     *spline_mode = temp_spline_mode;
                     // TODO: enable Polynomial combo box
                     // ui.selectable_value(
@@ -153,6 +165,22 @@ impl WindowZColorPickerOptions {
                 .response
                 .on_hover_text("Spline Mode");
 
+            egui::ComboBox::new(12312314, "")
+                .selected_text(gradient_mode.label())
+                .show_ui(ui, |ui| {
+                    ui.set_min_width(60.0);
+                    for mode in GradientMode::ALL {
+                        let allowed = mode.allows_control_point_count(control_points.len());
+                        ui.add_enabled_ui(allowed, |ui| {
+                            ui.selectable_value(gradient_mode, mode, mode.label())
+                        })
+                        .inner
+                        .on_disabled_hover_text("Needs an odd number of control points");
+                    }
+                })
+                .response
+                .on_hover_text("Gradient Mode");
+
             if ui.button("Flip").clicked_by(PointerButton::Primary) {
                 // Also Flip the tangets
                 for cp in control_points.iter_mut() {
@@ -163,6 +191,104 @@ impl WindowZColorPickerOptions {
             }
         });
 
+        ui.horizontal(|ui| {
+            let gamut_settings = &mut options.gamut_settings;
+            egui::ComboBox::new("options_check_gamut", "")
+                .selected_text(gamut_settings.check_gamut.label())
+                .show_ui(ui, |ui| {
+                    for gamut in Gamut::ALL {
+                        ui.selectable_value(&mut gamut_settings.check_gamut, gamut, gamut.label());
+                    }
+                })
+                .response
+                .on_hover_text("Gamut to check against");
+            egui::ComboBox::new("options_gamut_mapping", "")
+                .selected_text(gamut_settings.mapping.label())
+                .show_ui(ui, |ui| {
+                    for mapping in GamutMapping::ALL {
+                        ui.selectable_value(&mut gamut_settings.mapping, mapping, mapping.label());
+                    }
+                })
+                .response
+                .on_hover_text("Gamut mapping for out of sRGB colors");
+            ui.checkbox(&mut gamut_settings.highlight_out_of_gamut, "⚠")
+                .on_hover_text("Highlight out of gamut colors");
+        });
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut options.show_curvature_comb, "Comb")
+                .on_hover_text("Show the curvature comb of the spline");
+            ui.checkbox(&mut options.show_continuity, "Continuity")
+                .on_hover_text("Show C0/G1/C1/G2 continuity at each control point");
+            ui.checkbox(&mut options.enforce_g1, "G1")
+                .on_hover_text("Keep Bezier tangents aligned so the curve has no kinks");
+        });
+
+        ui.horizontal(|ui| {
+            let simplify_id = egui::Id::new("options_simplify_delta_e");
+            let mut max_delta_e: f32 =
+                ui.memory(|mem| mem.data.get_temp(simplify_id).unwrap_or(1.0));
+            if ui
+                .button("Simplify")
+                .on_hover_text("Remove control points that barely change the gradient")
+                .clicked()
+            {
+                *control_points = simplify_control_points(
+                    control_points,
+                    *spline_mode,
+                    *gradient_mode,
+                    max_delta_e,
+                );
+            }
+            ui.add(
+                DragValue::new(&mut max_delta_e)
+                    .range(0.1..=10.0)
+                    .speed(0.05)
+                    .prefix("ΔE "),
+            )
+            .on_hover_text("Largest color change simplifying may cause");
+            ui.memory_mut(|mem| mem.data.insert_temp(simplify_id, max_delta_e));
+
+            let subdivide_id = egui::Id::new("options_subdivide_t");
+            let mut subdivide_t: f32 =
+                ui.memory(|mem| mem.data.get_temp(subdivide_id).unwrap_or(0.5));
+            let can_subdivide = gradient_mode.allows_control_point_count(control_points.len() + 1);
+            if ui
+                .add_enabled(can_subdivide, egui::Button::new("Subdivide"))
+                .on_hover_text(
+                    "Insert a control point on the curve. Segments are re-spaced evenly, \
+                     so colors shift along the gradient",
+                )
+                .on_disabled_hover_text("It would leave a diverging gradient even")
+                .clicked()
+            {
+                *control_points =
+                    sub_divide_control_points(control_points, *spline_mode, subdivide_t);
+            }
+            ui.add(
+                DragValue::new(&mut subdivide_t)
+                    .range(0.0..=1.0)
+                    .speed(0.005)
+                    .prefix("t "),
+            )
+            .on_hover_text("Where along the gradient to insert the control point");
+            ui.memory_mut(|mem| mem.data.insert_temp(subdivide_id, subdivide_t));
+
+            let can_split = gradient_mode
+                .allows_control_point_count((control_points.len() * 2).saturating_sub(1));
+            if ui
+                .add_enabled(can_split, egui::Button::new("Split all"))
+                .on_hover_text(
+                    "Split every segment in half without changing the gradient \
+                     (Hermite may change slightly)",
+                )
+                .on_disabled_hover_text("It would leave a diverging gradient even")
+                .clicked()
+            {
+                *control_points = sub_divide_all_control_points(control_points, *spline_mode);
+            }
+        });
+
         ui.horizontal(|ui| {
             let rename_button = ui.button("Rename");
 
@@ -189,6 +315,7 @@ impl WindowZColorPickerOptions {
         options: &mut ZColorPickerOptions,
         control_points: &mut Vec<ControlPoint>,
         spline_mode: &mut SplineMode,
+        gradient_mode: &mut GradientMode,
         color_copy_format: &mut ColorStringCopy,
     ) -> Option<InnerResponse<Option<()>>> {
         let prev_visuals = ui.visuals_mut().clone();
@@ -200,7 +327,14 @@ impl WindowZColorPickerOptions {
             .open(&mut open)
             .auto_sized()
             .show(ui.ctx(), |ui: &mut Ui| {
-                self.draw_content(ui, options, control_points, spline_mode, color_copy_format)
+                self.draw_content(
+                    ui,
+                    options,
+                    control_points,
+                    spline_mode,
+                    gradient_mode,
+                    color_copy_format,
+                )
             });
 
         if open {
@@ -215,6 +349,13 @@ impl WindowZColorPickerOptions {
     }
 }
 
+/// The gradient presets are saved from and loaded into.
+pub struct PresetTarget<'a> {
+    pub control_points: &'a mut Vec<ControlPoint>,
+    pub spline_mode: &'a mut SplineMode,
+    pub gradient_mode: &'a mut GradientMode,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct WindowPreset {
     pub open: bool,
@@ -239,11 +380,15 @@ impl WindowPreset {
         &mut self,
         ui: &mut Ui,
         preset_handler: &mut PresetHandler,
-        control_points: &mut Vec<ControlPoint>,
-        spline_mode: &mut SplineMode,
+        target: PresetTarget,
         color_copy_format: &mut ColorStringCopy,
         mut auto_save_preset: &mut bool,
     ) -> WindowPresetDrawResult {
+        let PresetTarget {
+            control_points,
+            spline_mode,
+            gradient_mode,
+        } = target;
         let mut draw_result = WindowPresetDrawResult::default();
 
         ui.horizontal(|ui| {
@@ -286,6 +431,18 @@ impl WindowPreset {
                         combobox_has_selected = false;
                         preset_handler.preset_selected_index = None;
                     }
+
+                    // Built-in, read-only so nothing is selected for Save to overwrite
+                    ui.separator();
+                    for preset in preset_handler.builtin_presets() {
+                        if ui.selectable_label(false, preset.name.as_str()).clicked() {
+                            draw_result.preset_result.should_apply = Some(preset.clone());
+                            log::info!("Selected built-in preset {}", preset.name);
+                        }
+                    }
+                    if draw_result.preset_result.should_apply.is_some() {
+                        preset_handler.preset_selected_index = None;
+                    }
                 })
                 .response
                 .on_hover_text("Presets");
@@ -311,6 +468,7 @@ impl WindowPreset {
                 if let Some(s) = preset_handler.preset_selected_index {
                     preset_handler.presets[s].data.spline_mode = *spline_mode;
                     preset_handler.presets[s].data.control_points = control_points.to_vec();
+                    preset_handler.presets[s].data.gradient_mode = *gradient_mode;
                     log::info!("Saved preset [{}]", preset_handler.presets[s].name);
                 } else {
                     log::info!("Could not save preset, None selected");
@@ -381,6 +539,7 @@ impl WindowPreset {
                             data: AppDataCtx {
                                 spline_mode: *spline_mode,
                                 control_points: control_points.to_vec(),
+                                gradient_mode: *gradient_mode,
                             },
                         };
                         preset_handler.presets.push(new_preset);
@@ -401,8 +560,7 @@ impl WindowPreset {
         &mut self,
         ui: &mut Ui,
         preset_handler: &mut PresetHandler,
-        control_points: &mut Vec<ControlPoint>,
-        spline_mode: &mut SplineMode,
+        target: PresetTarget,
         color_copy_format: &mut ColorStringCopy,
         auto_save_preset: &mut bool,
     ) -> Option<InnerResponse<Option<WindowPresetDrawResult>>> {
@@ -418,8 +576,7 @@ impl WindowPreset {
                 self.draw_content(
                     ui,
                     preset_handler,
-                    control_points,
-                    spline_mode,
+                    target,
                     color_copy_format,
                     auto_save_preset,
                 )
@@ -0,0 +1,129 @@
+use eframe::egui::{self, Align2, Color32, RichText, ScrollArea, Sense, TextStyle, Ui, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::contrast::{apca_contrast, wcag_contrast_ratio, ApcaLevel, WcagLevel},
+    common::ColorStringCopy,
+    ui_egui::{color_picker::format_color_as, ui_common::color_button},
+};
+
+const PASS_COLOR: Color32 = Color32::from_rgb(60, 170, 80);
+const FAIL_COLOR: Color32 = Color32::from_rgb(200, 60, 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContrastMatrixData {
+    pub background: Color32,
+    pub text: Color32,
+    pub show_wcag: bool,
+    pub show_apca: bool,
+}
+
+impl Default for ContrastMatrixData {
+    fn default() -> Self {
+        Self {
+            background: Color32::WHITE,
+            text: Color32::BLACK,
+            show_wcag: true,
+            show_apca: true,
+        }
+    }
+}
+
+fn badge(ui: &mut Ui, text: &str, pass: bool) {
+    let fill = if pass { PASS_COLOR } else { FAIL_COLOR };
+    egui::Frame::new()
+        .fill(fill)
+        .corner_radius(3.0)
+        .inner_margin(Vec2::new(3.0, 0.0))
+        .show(ui, |ui| {
+            ui.label(RichText::new(text).small().color(Color32::WHITE));
+        });
+}
+
+fn ui_contrast_cell(ui: &mut Ui, text: Color32, background: Color32, data: &ContrastMatrixData) {
+    ui.vertical(|ui| {
+        let (rect, _response) = ui.allocate_exact_size(Vec2::new(80.0, 24.0), Sense::hover());
+        ui.painter().rect_filled(rect, 2.0, background);
+        ui.painter().text(
+            rect.center(),
+            Align2::CENTER_CENTER,
+            "Aa",
+            TextStyle::Body.resolve(ui.style()),
+            text,
+        );
+
+        if data.show_wcag {
+            let ratio = wcag_contrast_ratio(text, background);
+            let level = WcagLevel::from_ratio(ratio);
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(format!("{:.2}:1", ratio)).small());
+                badge(ui, level.label(), level != WcagLevel::Fail);
+            })
+            .response
+            .on_hover_text("WCAG 2.1 contrast ratio");
+        }
+
+        if data.show_apca {
+            let lc = apca_contrast(text, background);
+            let level = ApcaLevel::from_lc(lc);
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(format!("Lc {:.1}", lc)).small());
+                badge(ui, level.label(), level != ApcaLevel::Fail);
+            })
+            .response
+            .on_hover_text("APCA lightness contrast, text on background");
+        }
+    });
+}
+
+fn ui_matrix_header(ui: &mut Ui, color: Color32, name: &str) {
+    ui.vertical(|ui| {
+        color_button(ui, Vec2::new(24.0, 16.0), color, false);
+        ui.label(RichText::new(name).small().monospace());
+    });
+}
+
+/// Contrast of every palette entry against each other, rows are drawn as text on the column color.
+pub fn ui_contrast_matrix(ui: &mut Ui, palette: &[Color32], data: &mut ContrastMatrixData) {
+    ui.horizontal(|ui| {
+        ui.label("Background");
+        ui.color_edit_button_srgba(&mut data.background);
+        ui.label("Text");
+        ui.color_edit_button_srgba(&mut data.text);
+        ui.checkbox(&mut data.show_wcag, "WCAG 2.1");
+        ui.checkbox(&mut data.show_apca, "APCA");
+    });
+
+    let mut text_colors: Vec<(String, Color32)> = palette
+        .iter()
+        .map(|c| (format_color_as(*c, ColorStringCopy::HEXNOA, None), *c))
+        .collect();
+    let mut background_colors = text_colors.clone();
+    text_colors.push(("Text".to_string(), data.text));
+    background_colors.push(("Background".to_string(), data.background));
+
+    ScrollArea::both().auto_shrink([false; 2]).show(ui, |ui| {
+        egui::Grid::new("contrast_matrix_grid")
+            .striped(true)
+            .spacing(Vec2::new(8.0, 8.0))
+            .show(ui, |ui| {
+                ui.label("Text \\ Bg");
+                for (name, color) in background_colors.iter() {
+                    ui_matrix_header(ui, *color, name);
+                }
+                ui.end_row();
+
+                for (row, (name, text)) in text_colors.iter().enumerate() {
+                    ui_matrix_header(ui, *text, name);
+                    for (column, (_, background)) in background_colors.iter().enumerate() {
+                        if row == column && row < palette.len() {
+                            ui.label("-");
+                        } else {
+                            ui_contrast_cell(ui, *text, *background, data);
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+    });
+}
@@ -7,9 +7,9 @@ use eframe::{
 use splines::{Interpolation, Key, Spline};
 
 use crate::{
-    common::SplineMode,
-    datatypes::control_point::{ControlPoint, ControlPointValue},
-    ui_egui::tangents::ui_control_point_tangents,
+    common::{GradientMode, SplineMode},
+    datatypes::control_point::{ControlPoint, ControlPointValue, HueInterpolation, TangentMode},
+    ui_egui::tangents::{ui_control_point_tangents, TangentDrawStyle},
 };
 
 #[derive(Default)]
@@ -19,6 +19,7 @@ pub struct ControlPointUiResult {
     pub hovering_control_point: Option<(egui::Response, usize)>,
     pub selected_tangent: Option<usize>,
     pub dragged_tangent: Option<egui::Response>,
+    pub tangent_mode_change: Option<(usize, TangentMode)>,
 }
 
 pub fn control_point_pos(cp: &ControlPoint) -> Pos2 {
@@ -38,7 +39,7 @@ pub fn ui_ordered_control_points(
     marked_control_point_index: Option<usize>,
     _is_middle_interpolated: bool,
     parent_response: &egui::Response,
-    show_bezier_tangents: bool,
+    spline_mode: SplineMode,
 ) -> ControlPointUiResult {
     use egui::PointerButton::Primary;
 
@@ -69,6 +70,7 @@ pub fn ui_ordered_control_points(
     let mut hovering_control_point = None;
     let mut dragged_point_response = None;
     let mut dragged_tangent_response = None;
+    let mut tangent_mode_change = None;
 
     let control_point_shapes_fill: Vec<Shape> = control_points
         .iter()
@@ -105,22 +107,33 @@ pub fn ui_ordered_control_points(
         })
         .collect();
 
+    let draw_style = TangentDrawStyle {
+        control_point_draw_size,
+        control_point_radius,
+        inactive_stroke,
+    };
     let mut tangent_shapes = Vec::new();
     let mut tangent_paths = Vec::new();
-    if show_bezier_tangents {
+    if matches!(
+        spline_mode,
+        SplineMode::Bezier | SplineMode::QuadraticBezier
+    ) {
         for (i, cp) in control_points.iter().enumerate() {
+            let quadratic_next = match spline_mode {
+                SplineMode::QuadraticBezier => control_points.get(i + 1),
+                _ => None,
+            };
             let result = ui_control_point_tangents(
                 ui,
                 i,
                 cp,
+                quadratic_next,
                 i == 0,
                 i == control_points.len() - 1,
                 selected_index == Some(i),
                 &to_screen,
                 parent_response,
-                control_point_draw_size,
-                control_point_radius,
-                inactive_stroke,
+                draw_style,
                 &mut tangent_shapes,
                 &mut tangent_paths,
             );
@@ -130,6 +143,10 @@ pub fn ui_ordered_control_points(
                 tangent_selected_index = result.selected_tangent;
             }
 
+            if let Some(mode) = result.new_tangent_mode {
+                tangent_mode_change = Some((i, mode));
+            }
+
             if result.selected_by_tangent {
                 selected_index = Some(i);
             }
@@ -189,31 +206,42 @@ pub fn ui_ordered_control_points(
         hovering_control_point,
         selected_tangent: tangent_selected_index,
         dragged_tangent: dragged_tangent_response,
+        tangent_mode_change,
+    }
+}
+
+/// Context menu entries for the hue direction of the segment after a control point, returns
+/// the picked method.
+pub fn ui_hue_interpolation_menu(
+    ui: &mut Ui,
+    hue_interpolation: HueInterpolation,
+) -> Option<HueInterpolation> {
+    let mut new_hue_interpolation = None;
+    ui.label("Hue direction");
+    for method in HueInterpolation::ALL {
+        if ui
+            .radio(hue_interpolation == method, method.label())
+            .clicked_by(egui::PointerButton::Primary)
+        {
+            new_hue_interpolation = Some(method);
+            ui.close_menu();
+        }
     }
+    new_hue_interpolation
 }
 
+/// Unwraps the hues so each segment travels the arc picked by its `HueInterpolation`, letting
+/// every interpolation work on hue as a plain number. Hues may end up outside [0, 1].
 pub fn flatten_control_points(control_points: &[ControlPoint]) -> Vec<ControlPoint> {
     let mut flattened: Vec<ControlPoint> = Vec::with_capacity(control_points.len());
 
     for (i, cp) in control_points.iter().enumerate() {
-        if i == 0 {
-            flattened.push(cp.clone());
-            continue;
+        let mut cp_clone = cp.clone();
+        if i > 0 {
+            let prev_hue = flattened[i - 1].val()[2];
+            let hue_interpolation = control_points[i - 1].hue_interpolation();
+            cp_clone.val_mut()[2] = hue_interpolation.unwrap(prev_hue, cp.val()[2]);
         }
-
-        let prev_hue = flattened[i - 1].val()[2];
-        let hue_diff = cp.val()[2] - prev_hue;
-
-        let cp_clone = cp.clone();
-
-        if hue_diff.abs() > 0.5 {
-            // Adjust all previous hues by ±1 to smooth wraparound
-            let adjustment = if hue_diff > 0.0 { 1.0 } else { -1.0 };
-            for prev_cp in &mut flattened {
-                prev_cp.val_mut()[2] += adjustment;
-            }
-        }
-
         flattened.push(cp_clone);
     }
 
@@ -278,6 +306,30 @@ pub fn control_points_to_spline(
 
             new_spline
         }
+        SplineMode::QuadraticBezier => Spline::from_vec(
+            control_points
+                .iter()
+                .enumerate()
+                .map(|(index, e)| {
+                    // splines only samples a quadratic segment when the next key is not a
+                    // Bezier key, so every segment is degree elevated to the equivalent cubic
+                    let value = *e.val();
+                    let handle_in = if index > 0 {
+                        let prev = &control_points[index - 1];
+                        let quadratic_handle = *prev.val() + prev.tangents()[1].unwrap_or_default();
+                        value + (quadratic_handle - value) * (2.0 / 3.0)
+                    } else {
+                        value
+                    };
+                    let handle_out = value + e.tangents()[1].unwrap_or_default() * (2.0 / 3.0);
+                    Key::new(
+                        index as f32,
+                        value,
+                        Interpolation::StrokeBezier(handle_in, handle_out),
+                    )
+                })
+                .collect(),
+        ),
         SplineMode::Polynomial => todo!(),
         _ => {
             log::info!("Not Implemented...");
@@ -291,3 +343,186 @@ pub fn control_points_to_spline(
         }
     }
 }
+
+/// Flattened control points with the first one appended after the last, its hue unwrapped
+/// with the last point's hue interpolation, so the gradient returns to where it started.
+pub fn close_control_points(flattened: &[ControlPoint]) -> Vec<ControlPoint> {
+    let mut closed = flattened.to_vec();
+    if let (Some(first), Some(last)) = (flattened.first(), flattened.last()) {
+        let mut closing = first.clone();
+        closing.val_mut()[2] = last
+            .hue_interpolation()
+            .unwrap(last.val()[2], first.val()[2]);
+        closed.push(closing);
+    }
+    closed
+}
+
+/// `control_points_to_spline` of a closed loop from `close_control_points`. Catmull-Rom gets
+/// the neighbours across the seam as its end keys instead of repeated ends, so the loop has no
+/// kink where it closes.
+pub fn closed_control_points_to_spline(
+    closed: &[ControlPoint],
+    spline_mode: SplineMode,
+) -> Spline<f32, ControlPointValue> {
+    if spline_mode != SplineMode::HermiteBezier || closed.len() < 3 {
+        return control_points_to_spline(closed, spline_mode);
+    }
+
+    let hue_turns = closed[closed.len() - 1].val()[2] - closed[0].val()[2];
+    let mut before = *closed[closed.len() - 2].val();
+    before[2] -= hue_turns;
+    let mut after = *closed[1].val();
+    after[2] += hue_turns;
+
+    let values: Vec<ControlPointValue> = std::iter::once(before)
+        .chain(closed.iter().map(|cp| *cp.val()))
+        .chain(std::iter::once(after))
+        .collect();
+    Spline::from_vec(
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(index, val)| Key::new(index as f32, val, Interpolation::CatmullRom))
+            .collect(),
+    )
+}
+
+/// Flattened control points and their spline, closed into a loop for cyclic gradients.
+pub fn gradient_spline(
+    control_points: &[ControlPoint],
+    spline_mode: SplineMode,
+    gradient_mode: GradientMode,
+) -> (Vec<ControlPoint>, Spline<f32, ControlPointValue>) {
+    let flattened = flatten_control_points(control_points);
+    if gradient_mode == GradientMode::Cyclic && flattened.len() >= 2 {
+        let closed = close_control_points(&flattened);
+        let spline = closed_control_points_to_spline(&closed, spline_mode);
+        (closed, spline)
+    } else {
+        let spline = control_points_to_spline(&flattened, spline_mode);
+        (flattened, spline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{
+            arb_control_points, arb_spline_mode, assert_hsv_close, IMPLEMENTED_SPLINE_MODES,
+        },
+        ui_egui::previewer::CurveSampler,
+    };
+    use proptest::prelude::*;
+
+    /// Spline t of control point `index`, HermiteBezier pads a key on each side.
+    fn key_t(index: usize, spline_mode: SplineMode) -> f32 {
+        match spline_mode {
+            SplineMode::HermiteBezier => index as f32 + 1.0,
+            _ => index as f32,
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn flatten_keeps_colors(control_points in arb_control_points(8)) {
+            let flattened = flatten_control_points(&control_points);
+            prop_assert_eq!(flattened.len(), control_points.len());
+            for (flat, original) in flattened.iter().zip(&control_points) {
+                assert_hsv_close(flat.val(), original.val());
+                prop_assert_eq!(flat.tangents(), original.tangents());
+            }
+        }
+
+        #[test]
+        fn flatten_follows_hue_interpolation(control_points in arb_control_points(8)) {
+            let flattened = flatten_control_points(&control_points);
+            for (i, pair) in flattened.windows(2).enumerate() {
+                let (from, to) = (pair[0].val()[2], pair[1].val()[2]);
+                let expected = control_points[i].hue_interpolation().unwrap(from, to);
+                prop_assert!((to - expected).abs() < 1e-4);
+            }
+        }
+
+        #[test]
+        fn spline_passes_through_control_points(
+            control_points in arb_control_points(8),
+            spline_mode in arb_spline_mode(),
+        ) {
+            let flattened = flatten_control_points(&control_points);
+            let spline = control_points_to_spline(&flattened, spline_mode);
+            // The last key is only reached through clamping, sample up to it
+            for (i, control_point) in flattened.iter().enumerate().take(flattened.len() - 1) {
+                let sample = spline.clamped_sample(key_t(i, spline_mode)).unwrap();
+                assert_hsv_close(&sample, control_point.val());
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn cyclic_gradient_returns_to_the_start(
+            control_points in arb_control_points(8),
+            spline_mode in arb_spline_mode(),
+        ) {
+            let sampler =
+                CurveSampler::with_gradient_mode(&control_points, spline_mode, GradientMode::Cyclic);
+            assert_hsv_close(&sampler.sample(0.0), control_points[0].val());
+            assert_hsv_close(&sampler.sample(1.0), control_points[0].val());
+        }
+    }
+
+    #[test]
+    fn cyclic_hermite_is_smooth_across_the_seam() {
+        let control_points = crate::test_utils::wrapping_control_points();
+        let sampler = CurveSampler::with_gradient_mode(
+            &control_points,
+            SplineMode::HermiteBezier,
+            GradientMode::Cyclic,
+        );
+        let h = 1e-3;
+        let before = (sampler.sample(1.0) - sampler.sample(1.0 - h)) * (1.0 / h);
+        let after = (sampler.sample(h) - sampler.sample(0.0)) * (1.0 / h);
+        for channel in 0..3 {
+            assert!(
+                (before[channel] - after[channel]).abs() < 0.05,
+                "{:?} != {:?}",
+                before,
+                after
+            );
+        }
+    }
+
+    #[test]
+    fn spline_keys_for_every_mode() {
+        let control_points = crate::test_utils::wrapping_control_points();
+        for spline_mode in IMPLEMENTED_SPLINE_MODES {
+            let spline = control_points_to_spline(&control_points, spline_mode);
+            let expected_len = match spline_mode {
+                SplineMode::HermiteBezier => control_points.len() + 2,
+                _ => control_points.len(),
+            };
+            assert_eq!(spline.len(), expected_len, "{:?}", spline_mode);
+            assert_eq!(
+                find_spline_max_t(&spline),
+                (expected_len - 1) as f32,
+                "{:?}",
+                spline_mode
+            );
+        }
+    }
+
+    #[test]
+    fn flatten_wraps_through_red() {
+        let control_points = crate::test_utils::wrapping_control_points();
+        let hues: Vec<f32> = flatten_control_points(&control_points)
+            .iter()
+            .map(|cp| cp.val()[2])
+            .collect();
+        let expected = [0.85, 1.05, 1.3, 1.6];
+        for (hue, expected) in hues.iter().zip(expected) {
+            assert!((hue - expected).abs() < 1e-5, "{:?}", hues);
+        }
+    }
+}
@@ -1,32 +1,66 @@
 //https://github.com/emilk/egui/blob/master/crates/egui_demo_lib/src/demo/paint_bezier.rs
 
-use crate::common::SplineMode;
-use crate::datatypes::control_point::{ControlPoint, ControlPointValue};
+use crate::color::delta_e::color32_to_lab;
+use crate::color::gamut::hsv_to_linear_srgb;
+use crate::color::gamut::GamutSettings;
+use crate::color::oklab::linear_srgb_to_oklab;
+use crate::common::{GradientMode, SplineMode};
+use crate::datatypes::control_point::{
+    ControlPoint, ControlPointValue, HueInterpolation, TangentMode,
+};
 #[allow(unused_imports)]
 use crate::error::Result;
 use crate::ui_egui::control_points::{
-    control_points_to_spline, find_spline_max_t, flatten_control_points,
+    control_points_to_spline, find_spline_max_t, flatten_control_points, gradient_spline,
 };
-use eframe::egui::{self, lerp, Sense, Ui};
+use crate::ui_egui::previewer::CurveSampler;
+use eframe::egui::{self, lerp, Color32, Sense, Shape, Ui};
 use eframe::emath;
 use eframe::epaint::{Pos2, Rect, Stroke, Vec2};
 use egui::epaint::PathShape;
+use palette::{color_difference::Ciede2000, Lab};
+use splines::Spline;
 
 use crate::math::{add_array_array, mul_array};
 
+pub const OUT_OF_GAMUT_HIGHLIGHT_COLOR: Color32 = Color32::from_rgb(255, 0, 255);
+
 pub fn generate_spline_points_with_distance(
     control_points: &[ControlPoint],
     spline_mode: SplineMode,
     t_distance: f32,
 ) -> Vec<ControlPointValue> {
-    let mut spline_samples = Vec::new();
-
     if control_points.len() <= 1 {
-        return spline_samples;
+        return Vec::new();
     }
 
     let spline = control_points_to_spline(&control_points, spline_mode);
-    let spline_max_t = find_spline_max_t(&spline) as f32;
+    sample_spline_with_distance(&spline, t_distance)
+}
+
+/// `generate_spline_points_with_distance` for unflattened control points, cyclic gradients
+/// continue back to the first control point.
+pub fn generate_gradient_points_with_distance(
+    control_points: &[ControlPoint],
+    spline_mode: SplineMode,
+    gradient_mode: GradientMode,
+    t_distance: f32,
+) -> Vec<ControlPointValue> {
+    if control_points.len() <= 1 {
+        return Vec::new();
+    }
+
+    let (_, spline) = gradient_spline(control_points, spline_mode, gradient_mode);
+    sample_spline_with_distance(&spline, t_distance)
+}
+
+fn sample_spline_with_distance(
+    spline: &Spline<f32, ControlPointValue>,
+    t_distance: f32,
+) -> Vec<ControlPointValue> {
+    let mut spline_samples = Vec::new();
+
+    let spline_max_t = find_spline_max_t(spline) as f32;
     let mut curr_t = 0.0;
     while curr_t <= spline_max_t {
         let spline_sample = spline.clamped_sample(curr_t);
@@ -48,52 +82,296 @@ pub fn generate_spline_points_with_distance(
     spline_samples
 }
 
+/// Path samples used to compare a simplified gradient against the original.
+const SIMPLIFY_SAMPLES: usize = 128;
+
+/// Segment and position within it of gradient position `x`, the mapping `CurveSampler` uses.
+fn segment_at(num_control_points: usize, x: f32) -> (usize, f32) {
+    let scaled = x.clamp(0.0, 1.0) * (num_control_points - 1) as f32;
+    let index = (scaled.floor() as usize).min(num_control_points - 2);
+    (index, scaled - index as f32)
+}
+
+fn lerp_value(a: ControlPointValue, b: ControlPointValue, u: f32) -> ControlPointValue {
+    a + (b - a) * u
+}
+
+/// Wraps the unwrapped hues of `flattened` back into [0, 1) and picks hue directions that
+/// keep every segment travelling the same way.
+fn wrap_flattened_hues(mut flattened: Vec<ControlPoint>) -> Vec<ControlPoint> {
+    for i in 0..flattened.len().saturating_sub(1) {
+        let delta = flattened[i + 1].val()[2] - flattened[i].val()[2];
+        *flattened[i].hue_interpolation_mut() = HueInterpolation::for_delta(delta);
+    }
+    for cp in flattened.iter_mut() {
+        cp.val_mut()[2] = cp.val()[2].rem_euclid(1.0);
+    }
+    flattened
+}
+
+/// Splits segment `index` of `flattened` at `u` in place, `sampled` is the color inserted for
+/// spline modes that can't be split exactly.
+fn split_flattened_segment(
+    flattened: &mut Vec<ControlPoint>,
+    spline_mode: SplineMode,
+    index: usize,
+    u: f32,
+    sampled: ControlPointValue,
+) {
+    let p0 = *flattened[index].val();
+    let p1 = *flattened[index + 1].val();
+    let t = lerp(*flattened[index].t()..=*flattened[index + 1].t(), u);
+
+    let new_cp = match spline_mode {
+        SplineMode::Linear => ControlPoint::new_simple(lerp_value(p0, p1, u), t),
+        SplineMode::Bezier => {
+            let c0 = p0 + flattened[index].tangents()[1].unwrap_or_default();
+            let c1 = p1 + flattened[index + 1].tangents()[0].unwrap_or_default();
+            // de Casteljau
+            let a = lerp_value(p0, c0, u);
+            let b = lerp_value(c0, c1, u);
+            let c = lerp_value(c1, p1, u);
+            let d = lerp_value(a, b, u);
+            let e = lerp_value(b, c, u);
+            let m = lerp_value(d, e, u);
+            flattened[index].tangents_mut()[1] = Some(a - p0);
+            flattened[index + 1].tangents_mut()[0] = Some(c - p1);
+            let mut new_cp = ControlPoint::new(m, t, [Some(d - m), Some(e - m)]);
+            *new_cp.tangent_mode_mut() = TangentMode::Aligned;
+            new_cp
+        }
+        SplineMode::QuadraticBezier => {
+            let c = p0 + flattened[index].tangents()[1].unwrap_or_default();
+            let a = lerp_value(p0, c, u);
+            let b = lerp_value(c, p1, u);
+            let m = lerp_value(a, b, u);
+            flattened[index].tangents_mut()[1] = Some(a - p0);
+            ControlPoint::new(m, t, [None, Some(b - m)])
+        }
+        _ => ControlPoint::new_simple(sampled, t),
+    };
+    flattened.insert(index + 1, new_cp);
+}
+
+/// Inserts a control point at gradient position `x` in [0, 1]. Linear, Bezier and Quadratic
+/// segments are split exactly so the path through color space stays the same, Hermite gets the
+/// sampled color. Every segment still takes an equal share of the gradient, so unless `x` is the
+/// middle of a segment the colors shift along the gradient, `sub_divide_all_control_points`
+/// doesn't.
 pub fn sub_divide_control_points(
     control_points: &[ControlPoint],
-    distance_per_point: f32,
+    spline_mode: SplineMode,
+    x: f32,
+) -> Vec<ControlPoint> {
+    if control_points.len() < 2 {
+        return control_points.to_vec();
+    }
+    let (index, u) = segment_at(control_points.len(), x);
+    if u <= f32::EPSILON || u >= 1.0 - f32::EPSILON {
+        return control_points.to_vec();
+    }
+
+    let sampled = CurveSampler::new(control_points, spline_mode).sample(x);
+    let mut flattened = flatten_control_points(control_points);
+    split_flattened_segment(&mut flattened, spline_mode, index, u, sampled);
+
+    wrap_flattened_hues(flattened)
+}
+
+/// Splits every segment in half. Each half keeps the share of the gradient it had, so Linear,
+/// Bezier and Quadratic gradients sample the same colors at every position. Hermite segments get
+/// the sampled middle color and can change slightly.
+pub fn sub_divide_all_control_points(
+    control_points: &[ControlPoint],
+    spline_mode: SplineMode,
 ) -> Vec<ControlPoint> {
-    let capacity: usize = control_points.len() * 4;
-    let mut sub_divided: Vec<ControlPoint> = Vec::with_capacity(capacity);
+    if control_points.len() < 2 {
+        return control_points.to_vec();
+    }
+
+    let sampler = CurveSampler::new(control_points, spline_mode);
+    let num_segments = control_points.len() - 1;
+    let mut flattened = flatten_control_points(control_points);
+    // Back to front so the indices of the segments left to split don't move
+    for index in (0..num_segments).rev() {
+        let sampled = sampler.sample((index as f32 + 0.5) / num_segments as f32);
+        split_flattened_segment(&mut flattened, spline_mode, index, 0.5, sampled);
+    }
 
-    for i in 1..control_points.len() {
-        sub_divided.push(control_points[i - 1].clone());
-        let hue_to_use = control_points[i - 1].val()[2];
-        let first = control_points[i - 1].val().pos2();
-        let last = control_points[i].val().pos2();
-        let dir = (last - first).normalized();
-        let mut sub_div_start = first;
-        let mut distance_to_end = (last - first).dot(last - first).sqrt();
-        while distance_to_end > distance_per_point {
-            let new: Pos2 = sub_div_start + distance_per_point * dir;
-            distance_to_end -= distance_per_point;
+    wrap_flattened_hues(flattened)
+}
 
-            let mut new_cp = ControlPoint::default();
-            *new_cp.val_mut() = ControlPointValue::new(new.x, new.y, hue_to_use);
-            *new_cp.t_mut() = lerp(*control_points[i - 1].t()..=*control_points[i].t(), 0.5);
+fn value_length(value: ControlPointValue) -> f32 {
+    (value[0] * value[0] + value[1] * value[1] + value[2] * value[2]).sqrt()
+}
 
-            sub_divided.push(new_cp);
-            sub_div_start = new;
+/// Removes control point `index`, stretching the neighbouring handles over the merged segment.
+/// A point made by `sub_divide_control_points` gets its original segment back exactly.
+fn remove_control_point(control_points: &[ControlPoint], index: usize) -> Vec<ControlPoint> {
+    let mut flattened = flatten_control_points(control_points);
+    let removed = flattened.remove(index);
+    if index > 0 && index < flattened.len() {
+        // Where the removed point split the merged segment, from its handles like de Casteljau
+        let m = *removed.val();
+        let handle_in = match removed.tangents()[0] {
+            Some(tangent) => Some(m + tangent),
+            None => flattened[index - 1].tangents()[1]
+                .map(|tangent| *flattened[index - 1].val() + tangent),
+        };
+        let handle_out = removed.tangents()[1].map(|tangent| m + tangent);
+        if let (Some(handle_in), Some(handle_out)) = (handle_in, handle_out) {
+            let len_in = value_length(m - handle_in);
+            let len_out = value_length(handle_out - m);
+            if len_in + len_out > f32::EPSILON {
+                let u = (len_in / (len_in + len_out)).clamp(0.05, 0.95);
+                if let Some(tangent) = &mut flattened[index - 1].tangents_mut()[1] {
+                    *tangent = *tangent * (1.0 / u);
+                }
+                if let Some(tangent) = &mut flattened[index].tangents_mut()[0] {
+                    *tangent = *tangent * (1.0 / (1.0 - u));
+                }
+            }
         }
-        let last_new = sub_div_start + distance_to_end.max(0.0) * dir;
+    }
+
+    wrap_flattened_hues(flattened)
+}
+
+fn path_lab_samples(control_points: &[ControlPoint], spline_mode: SplineMode) -> Vec<Lab> {
+    CurveSampler::new(control_points, spline_mode)
+        .sample_n(SIMPLIFY_SAMPLES)
+        .iter()
+        .map(|sample| color32_to_lab(sample.color()))
+        .collect()
+}
+
+/// Largest CIEDE2000 difference between two gradients sampled at the same positions, measured
+/// both ways since CIEDE2000 isn't symmetric.
+fn gradient_delta_e(a: &[Lab], b: &[Lab]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| a.difference(*b).max(b.difference(*a)))
+        .fold(0.0, f32::max)
+}
 
-        let mut new_cp = ControlPoint::default();
-        *new_cp.val_mut() = ControlPointValue::new(last_new.x, last_new.y, hue_to_use);
-        *new_cp.t_mut() = *control_points[i].t();
+/// Removes the interior control points at odd indices, undoing `sub_divide_all_control_points`.
+fn remove_every_other_control_point(control_points: &[ControlPoint]) -> Vec<ControlPoint> {
+    let odd_indices: Vec<usize> = (1..control_points.len() - 1).step_by(2).collect();
+    odd_indices
+        .into_iter()
+        .rev()
+        .fold(control_points.to_vec(), |result, index| {
+            remove_control_point(&result, index)
+        })
+}
 
-        let last_cp = new_cp;
-        sub_divided.push(last_cp);
+/// Greedily removes the control point that changes the gradient least, Ramer–Douglas–Peucker
+/// style, while the color at every position stays within `max_delta_e` of the original. Segments
+/// share the gradient equally, so dropping every other point is tried too, a single removal
+/// shifts the colors of a finely subdivided gradient. The first and last control points are
+/// always kept, and the result has a count `gradient_mode` allows if the input does.
+pub fn simplify_control_points(
+    control_points: &[ControlPoint],
+    spline_mode: SplineMode,
+    gradient_mode: GradientMode,
+    max_delta_e: f32,
+) -> Vec<ControlPoint> {
+    let reference = path_lab_samples(control_points, spline_mode);
+    let mut simplified = control_points.to_vec();
+    let mut allowed = None;
+
+    while simplified.len() > 2 {
+        let best = (1..simplified.len() - 1)
+            .map(|index| remove_control_point(&simplified, index))
+            .chain((simplified.len() > 3).then(|| remove_every_other_control_point(&simplified)))
+            .map(|candidate| {
+                let error =
+                    gradient_delta_e(&reference, &path_lab_samples(&candidate, spline_mode));
+                (error, candidate)
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b));
+        match best {
+            Some((error, candidate)) if error <= max_delta_e => {
+                if gradient_mode.allows_control_point_count(candidate.len()) {
+                    allowed = None;
+                } else if allowed.is_none() {
+                    allowed = Some(simplified.clone());
+                }
+                simplified = candidate;
+            }
+            _ => break,
+        }
     }
-    if control_points.last().is_some() {
-        sub_divided.push(control_points.last().unwrap().clone());
+
+    // E.g. back to the last odd count for a diverging gradient
+    match allowed {
+        Some(allowed) if gradient_mode.allows_control_point_count(control_points.len()) => allowed,
+        _ => simplified,
     }
+}
+
+fn oklab_lightness(value: &ControlPointValue) -> f32 {
+    linear_srgb_to_oklab(hsv_to_linear_srgb(value))[0]
+}
 
-    sub_divided
+/// Sets the HSV value of `value` so its OKLab lightness is `lightness`, as near as the value
+/// range allows, returns the lightness reached.
+fn set_oklab_lightness(value: &mut ControlPointValue, lightness: f32) -> f32 {
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..24 {
+        value[1] = (low + high) * 0.5;
+        if oklab_lightness(value) < lightness {
+            low = value[1];
+        } else {
+            high = value[1];
+        }
+    }
+    oklab_lightness(value)
+}
+
+/// Keeps a diverging gradient diverging: the control point at the middle of the gradient fully
+/// desaturated and each pair mirrored around it at the same OKLab lightness. A pair takes the
+/// lightness of `modified_index` when it is one of them, otherwise their average. An even number
+/// of control points has no middle and is left alone, see
+/// `GradientMode::allows_control_point_count`.
+pub fn enforce_diverging(control_points: &mut [ControlPoint], modified_index: Option<usize>) {
+    if !GradientMode::Diverging.allows_control_point_count(control_points.len()) {
+        return;
+    }
+
+    let num_control_points = control_points.len();
+    let middle = num_control_points / 2;
+    control_points[middle].val_mut()[0] = 0.0;
+
+    for i in 0..middle {
+        let mirrored = num_control_points - 1 - i;
+        let lightness = oklab_lightness(control_points[i].val());
+        let mirrored_lightness = oklab_lightness(control_points[mirrored].val());
+        if (lightness - mirrored_lightness).abs() < 1e-3 {
+            continue;
+        }
+        let target = match modified_index {
+            Some(index) if index == i => lightness,
+            Some(index) if index == mirrored => mirrored_lightness,
+            _ => (lightness + mirrored_lightness) * 0.5,
+        };
+        let reached = set_oklab_lightness(control_points[i].val_mut(), target).min(
+            set_oklab_lightness(control_points[mirrored].val_mut(), target),
+        );
+        // Saturated colors can be too dark for the target even at full value
+        if reached < target - 1e-3 {
+            set_oklab_lightness(control_points[i].val_mut(), reached);
+            set_oklab_lightness(control_points[mirrored].val_mut(), reached);
+        }
+    }
 }
 
 pub fn ui_ordered_spline_gradient(
     ui: &mut Ui,
     control_points: &[ControlPoint],
     spline_mode: SplineMode,
+    gradient_mode: GradientMode,
+    gamut_settings: &GamutSettings,
     parent_response: &egui::Response,
 ) -> Option<egui::Response> {
     let num_control_points = control_points.len();
@@ -112,17 +390,17 @@ pub fn ui_ordered_spline_gradient(
         response.rect,
     );
 
-    // let sub_divided_control_points = sub_divide_control_points(control_points, 0.01);
-    let flattened_points = flatten_control_points(control_points);
     let spline_points =
-        generate_spline_points_with_distance(&flattened_points[..], spline_mode, 0.01);
+        generate_gradient_points_with_distance(control_points, spline_mode, gradient_mode, 0.01);
 
+    let mut out_of_gamut_runs: Vec<Vec<Pos2>> = Vec::new();
+    let mut is_in_out_of_gamut_run = false;
     for i in 1..spline_points.len() {
         let first = spline_points[i - 1];
         let next = spline_points[i];
 
         // let spline = control_points_to_spline(&sub_divided_control_points, spline_mode);
-        let segment_color = first.color();
+        let segment_color = first.color_gamut_mapped(gamut_settings.mapping);
 
         let control_point_radius = 8.0;
 
@@ -134,6 +412,19 @@ pub fn ui_ordered_spline_gradient(
         points_in_screen.push(point_in_screen_first);
         points_in_screen.push(point_in_screen_next);
 
+        if gamut_settings.highlight_out_of_gamut && gamut_settings.is_out_of_gamut(&first) {
+            if !is_in_out_of_gamut_run {
+                out_of_gamut_runs.push(vec![point_in_screen_first]);
+            }
+            out_of_gamut_runs
+                .last_mut()
+                .unwrap()
+                .push(point_in_screen_next);
+            is_in_out_of_gamut_run = true;
+        } else {
+            is_in_out_of_gamut_run = false;
+        }
+
         let shape = PathShape::line(
             points_in_screen,
             Stroke::new(control_point_radius * 1.6, segment_color),
@@ -142,9 +433,282 @@ pub fn ui_ordered_spline_gradient(
         ui.painter().add(shape);
     }
 
+    for run in out_of_gamut_runs {
+        ui.painter().extend(Shape::dashed_line(
+            &run,
+            Stroke::new(2.0, OUT_OF_GAMUT_HIGHLIGHT_COLOR),
+            6.0,
+            4.0,
+        ));
+    }
+
     Some(response)
 }
 
+const CURVATURE_COMB_SCALE: f32 = 0.01;
+const CURVATURE_COMB_MAX_LENGTH: f32 = 0.2;
+const CURVATURE_COMB_COLOR: Color32 = Color32::from_rgb(80, 200, 255);
+
+/// Signed curvature of the S/V curve at every sample, using the circle through each sample
+/// and its neighbours. The ends get the curvature of their neighbour.
+pub fn spline_curvature(points: &[Pos2]) -> Vec<f32> {
+    let mut curvature = vec![0.0; points.len()];
+    if points.len() < 3 {
+        return curvature;
+    }
+
+    for i in 1..points.len() - 1 {
+        let a = points[i - 1];
+        let b = points[i];
+        let c = points[i + 1];
+        let ab = b - a;
+        let bc = c - b;
+        let denominator = ab.length() * bc.length() * (c - a).length();
+        if denominator > f32::EPSILON {
+            curvature[i] = 2.0 * (ab.x * bc.y - ab.y * bc.x) / denominator;
+        }
+    }
+    curvature[0] = curvature[1];
+    curvature[points.len() - 1] = curvature[points.len() - 2];
+
+    curvature
+}
+
+/// Curvature comb over the picker's S/V curve, the teeth point along the normal with a length
+/// proportional to the curvature.
+pub fn ui_curvature_comb(
+    ui: &mut Ui,
+    control_points: &[ControlPoint],
+    spline_mode: SplineMode,
+    gradient_mode: GradientMode,
+    parent_response: &egui::Response,
+) {
+    if control_points.len() <= 1 {
+        return;
+    }
+
+    let to_screen = emath::RectTransform::from_to(
+        Rect::from_min_size(Pos2::ZERO, Vec2::new(1.0, 1.0)),
+        parent_response.rect,
+    );
+
+    let points: Vec<Pos2> =
+        generate_gradient_points_with_distance(control_points, spline_mode, gradient_mode, 0.01)
+            .iter()
+            .map(|p| p.pos2())
+            .collect();
+    let curvature = spline_curvature(&points);
+
+    let mut tips: Vec<Pos2> = Vec::with_capacity(points.len());
+    let tooth_stroke = Stroke::new(1.0, CURVATURE_COMB_COLOR.linear_multiply(0.5));
+    for i in 0..points.len() {
+        let prev = points[i.saturating_sub(1)];
+        let next = points[(i + 1).min(points.len() - 1)];
+        let tangent = (next - prev).normalized();
+        // Teeth on the outside of the bend, like CAD tools draw them
+        let normal = Vec2::new(tangent.y, -tangent.x);
+        let length = (curvature[i] * CURVATURE_COMB_SCALE)
+            .clamp(-CURVATURE_COMB_MAX_LENGTH, CURVATURE_COMB_MAX_LENGTH);
+        let tip = points[i] + normal * length;
+
+        let base_screen = to_screen * Pos2::new(points[i].x, 1.0 - points[i].y);
+        let tip_screen = to_screen * Pos2::new(tip.x, 1.0 - tip.y);
+        ui.painter()
+            .line_segment([base_screen, tip_screen], tooth_stroke);
+        tips.push(tip_screen);
+    }
+
+    ui.painter().add(PathShape::line(
+        tips,
+        Stroke::new(1.5, CURVATURE_COMB_COLOR),
+    ));
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Continuity {
+    /// Position only, the curve has a kink
+    C0,
+    /// Tangent directions match
+    G1,
+    /// Tangents match in direction and magnitude
+    C1,
+    /// Tangent directions and curvature match
+    G2,
+}
+
+impl Continuity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Continuity::C0 => "C0",
+            Continuity::G1 => "G1",
+            Continuity::C1 => "C1",
+            Continuity::G2 => "G2",
+        }
+    }
+
+    pub fn color(&self) -> Color32 {
+        match self {
+            Continuity::C0 => Color32::from_rgb(230, 70, 70),
+            Continuity::G1 => Color32::from_rgb(240, 170, 60),
+            Continuity::C1 => Color32::from_rgb(200, 220, 80),
+            Continuity::G2 => Color32::from_rgb(90, 210, 110),
+        }
+    }
+}
+
+/// Spline key time of the control point at `index`, HermiteBezier pads the first key.
+fn control_point_spline_t(index: usize, spline_mode: SplineMode) -> f32 {
+    match spline_mode {
+        SplineMode::HermiteBezier => index as f32 + 1.0,
+        _ => index as f32,
+    }
+}
+
+fn sub3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn length3(a: [f32; 3]) -> f32 {
+    (a[0] * a[0] + a[1] * a[1] + a[2] * a[2]).sqrt()
+}
+
+fn curvature3(d1: [f32; 3], d2: [f32; 3]) -> f32 {
+    let cross = [
+        d1[1] * d2[2] - d1[2] * d2[1],
+        d1[2] * d2[0] - d1[0] * d2[2],
+        d1[0] * d2[1] - d1[1] * d2[0],
+    ];
+    length3(cross) / length3(d1).powi(3)
+}
+
+/// Continuity of the spline at every interior control point in (s, v, unwrapped hue) space,
+/// estimated with one sided finite differences. End points are `None`, unless a cyclic gradient
+/// closes the loop through them.
+pub fn classify_continuity(
+    control_points: &[ControlPoint],
+    spline_mode: SplineMode,
+    gradient_mode: GradientMode,
+) -> Vec<Option<Continuity>> {
+    const H: f32 = 0.01;
+    const MIN_DERIVATIVE: f32 = 1e-4;
+    const DIRECTION_TOLERANCE: f32 = 0.999;
+    const MAGNITUDE_TOLERANCE: f32 = 0.02;
+    const CURVATURE_TOLERANCE: f32 = 0.1;
+
+    let mut result = vec![None; control_points.len()];
+    if control_points.len() < 3 {
+        return result;
+    }
+
+    let (flattened_points, spline) = gradient_spline(control_points, spline_mode, gradient_mode);
+    let is_closed = flattened_points.len() > control_points.len();
+    // Samples before the start continue from the end of a closed loop, a turn of hue lower
+    let start = control_point_spline_t(0, spline_mode);
+    let period = control_point_spline_t(control_points.len(), spline_mode) - start;
+    let hue_turns =
+        flattened_points[flattened_points.len() - 1].val()[2] - flattened_points[0].val()[2];
+    let sample = |t: f32| {
+        if is_closed && t < start {
+            let mut val = spline.clamped_sample(t + period).unwrap_or_default().val;
+            val[2] -= hue_turns;
+            val
+        } else {
+            spline.clamped_sample(t).unwrap_or_default().val
+        }
+    };
+
+    // Every control point of a closed loop has a segment on both sides
+    let (first, end) = if is_closed {
+        (0, control_points.len())
+    } else {
+        (1, control_points.len() - 1)
+    };
+    for (i, continuity) in result.iter_mut().enumerate().take(end).skip(first) {
+        let t = control_point_spline_t(i, spline_mode);
+        let p = flattened_points[i].val().val;
+        let left = [sample(t - H), sample(t - 2.0 * H), sample(t - 3.0 * H)];
+        let right = [sample(t + H), sample(t + 2.0 * H), sample(t + 3.0 * H)];
+
+        // Second order one sided differences, pointing along increasing t on both sides
+        let d1 = |s: &[[f32; 3]; 3], sign: f32| -> [f32; 3] {
+            std::array::from_fn(|c| sign * (3.0 * p[c] - 4.0 * s[0][c] + s[1][c]) / (2.0 * H))
+        };
+        let d2 = |s: &[[f32; 3]; 3]| -> [f32; 3] {
+            std::array::from_fn(|c| {
+                (2.0 * p[c] - 5.0 * s[0][c] + 4.0 * s[1][c] - s[2][c]) / (H * H)
+            })
+        };
+        let d1_left = d1(&left, 1.0);
+        let d1_right = d1(&right, -1.0);
+        let d2_left = d2(&left);
+        let d2_right = d2(&right);
+
+        let left_length = length3(d1_left);
+        let right_length = length3(d1_right);
+        if left_length < MIN_DERIVATIVE || right_length < MIN_DERIVATIVE {
+            *continuity = Some(Continuity::C0);
+            continue;
+        }
+
+        let cos_angle =
+            (d1_left[0] * d1_right[0] + d1_left[1] * d1_right[1] + d1_left[2] * d1_right[2])
+                / (left_length * right_length);
+        let is_g1 = cos_angle > DIRECTION_TOLERANCE;
+        let is_c1 = is_g1
+            && length3(sub3(d1_left, d1_right))
+                <= MAGNITUDE_TOLERANCE * left_length.max(right_length);
+        let curvature_left = curvature3(d1_left, d2_left);
+        let curvature_right = curvature3(d1_right, d2_right);
+        let is_g2 = is_g1
+            && (curvature_left - curvature_right).abs()
+                <= CURVATURE_TOLERANCE * curvature_left.max(curvature_right).max(1.0);
+
+        *continuity = Some(if is_g2 {
+            Continuity::G2
+        } else if is_c1 {
+            Continuity::C1
+        } else if is_g1 {
+            Continuity::G1
+        } else {
+            Continuity::C0
+        });
+    }
+
+    result
+}
+
+pub fn ui_continuity_labels(
+    ui: &mut Ui,
+    control_points: &[ControlPoint],
+    spline_mode: SplineMode,
+    gradient_mode: GradientMode,
+    parent_response: &egui::Response,
+) {
+    let to_screen = emath::RectTransform::from_to(
+        Rect::from_min_size(Pos2::ZERO, Vec2::new(1.0, 1.0)),
+        parent_response.rect,
+    );
+
+    let continuities = classify_continuity(control_points, spline_mode, gradient_mode);
+    for (cp, continuity) in control_points.iter().zip(continuities) {
+        let Some(continuity) = continuity else {
+            continue;
+        };
+        let pos = to_screen
+            * Pos2::new(
+                cp.val()[0].clamp(0.0, 1.0),
+                1.0 - cp.val()[1].clamp(0.0, 1.0),
+            );
+        ui.painter().text(
+            pos + Vec2::new(10.0, -10.0),
+            egui::Align2::LEFT_BOTTOM,
+            continuity.label(),
+            egui::TextStyle::Small.resolve(ui.style()),
+            continuity.color(),
+        );
+    }
+}
+
 pub struct Bezier<const D: usize, const N: usize> {
     pub control_points: [[f32; D]; N],
 }
@@ -171,3 +735,255 @@ impl<const D: usize, const N: usize> Bezier<D, N> {
         outer_sum
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::delta_e::delta_e;
+    use crate::datatypes::hsv_key_value::HsvKeyValue;
+    use crate::test_utils::{arb_control_points, arb_spline_mode, assert_hsv_close};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn spline_points_start_and_end_on_control_points(
+            control_points in arb_control_points(8),
+            spline_mode in arb_spline_mode(),
+        ) {
+            let flattened = flatten_control_points(&control_points);
+            let points = generate_spline_points_with_distance(&flattened, spline_mode, 0.05);
+            assert_hsv_close(&points[0], flattened[0].val());
+            assert_hsv_close(points.last().unwrap(), flattened.last().unwrap().val());
+        }
+
+        #[test]
+        fn spline_points_are_dense_enough(
+            control_points in arb_control_points(8),
+            spline_mode in arb_spline_mode(),
+            t_distance in 0.01f32..0.5,
+        ) {
+            let flattened = flatten_control_points(&control_points);
+            let spline = control_points_to_spline(&flattened, spline_mode);
+            let max_t = find_spline_max_t(&spline);
+            let points = generate_spline_points_with_distance(&flattened, spline_mode, t_distance);
+            // HermiteBezier's padding segments sample as None and are skipped
+            let sampled_t = match spline_mode {
+                SplineMode::HermiteBezier => max_t - 2.0,
+                _ => max_t,
+            };
+            // One sample per step plus the explicit last sample, float steps may add or drop one
+            // at each end
+            let expected = (sampled_t / t_distance).floor() as usize + 2;
+            prop_assert!(points.len().abs_diff(expected) <= 2, "{} vs {}", points.len(), expected);
+        }
+    }
+
+    #[test]
+    fn spline_points_need_two_control_points() {
+        let control_points = crate::test_utils::wrapping_control_points();
+        for spline_mode in crate::test_utils::IMPLEMENTED_SPLINE_MODES {
+            assert!(generate_spline_points_with_distance(&[], spline_mode, 0.1).is_empty());
+            assert!(
+                generate_spline_points_with_distance(&control_points[..1], spline_mode, 0.1)
+                    .is_empty()
+            );
+        }
+    }
+
+    #[test]
+    fn diverging_mirrors_lightness_around_a_neutral_middle() {
+        let mut control_points = crate::test_utils::wrapping_control_points();
+        control_points.insert(2, control_points[1].clone());
+        enforce_diverging(&mut control_points, None);
+        assert_eq!(control_points.len(), 5);
+        assert_eq!(control_points[2].val().s(), 0.0);
+        let lightness: Vec<f32> = control_points
+            .iter()
+            .map(|cp| oklab_lightness(cp.val()))
+            .collect();
+        for i in 0..2 {
+            assert!(
+                (lightness[i] - lightness[4 - i]).abs() < 1e-3,
+                "{:?}",
+                lightness
+            );
+        }
+
+        // The modified point keeps its lightness and its mirror follows
+        control_points[1].val_mut()[1] -= 0.2;
+        let modified_lightness = oklab_lightness(control_points[1].val());
+        enforce_diverging(&mut control_points, Some(1));
+        assert_eq!(control_points.len(), 5);
+        assert!((oklab_lightness(control_points[1].val()) - modified_lightness).abs() < 1e-3);
+        assert!((oklab_lightness(control_points[3].val()) - modified_lightness).abs() < 1e-3);
+    }
+
+    #[test]
+    fn diverging_leaves_even_point_counts_alone() {
+        let mut control_points = crate::test_utils::wrapping_control_points();
+        enforce_diverging(&mut control_points, None);
+        assert_eq!(control_points, crate::test_utils::wrapping_control_points());
+    }
+
+    #[test]
+    fn subdivide_inserts_a_point_on_the_curve() {
+        let control_points = crate::test_utils::wrapping_control_points();
+        for spline_mode in [
+            SplineMode::Linear,
+            SplineMode::Bezier,
+            SplineMode::QuadraticBezier,
+        ] {
+            let sampler = CurveSampler::new(&control_points, spline_mode);
+            for (x, index) in [(0.1, 1), (0.5, 2), (0.9, 3)] {
+                let sub_divided = sub_divide_control_points(&control_points, spline_mode, x);
+                assert_eq!(sub_divided.len(), control_points.len() + 1);
+                let error = delta_e(sampler.sample(x).color(), sub_divided[index].val().color());
+                assert!(error < 1.0, "{:?} at {}: ΔE {}", spline_mode, x, error);
+            }
+        }
+    }
+
+    fn linear_control_points(vals: &[[f32; 3]]) -> Vec<ControlPoint> {
+        vals.iter()
+            .enumerate()
+            .map(|(i, val)| {
+                ControlPoint::new_simple(HsvKeyValue::new(val[0], val[1], val[2]), i as f32)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn straight_lines_are_smooth_and_kinks_are_c0() {
+        let classify = |vals: &[[f32; 3]]| {
+            classify_continuity(
+                &linear_control_points(vals),
+                SplineMode::Linear,
+                GradientMode::Open,
+            )[1]
+        };
+
+        // Equal steps along a line keep the tangent, uneven steps only keep its direction (and the
+        // zero curvature)
+        let even = classify(&[[0.1, 0.2, 0.5], [0.3, 0.4, 0.5], [0.5, 0.6, 0.5]]);
+        assert!(
+            matches!(even, Some(Continuity::C1 | Continuity::G2)),
+            "{:?}",
+            even
+        );
+        let uneven = classify(&[[0.1, 0.2, 0.5], [0.3, 0.4, 0.5], [0.7, 0.8, 0.5]]);
+        assert!(
+            matches!(uneven, Some(Continuity::G1 | Continuity::G2)),
+            "{:?}",
+            uneven
+        );
+
+        let kink = classify(&[[0.1, 0.2, 0.5], [0.5, 0.2, 0.5], [0.5, 0.6, 0.5]]);
+        assert_eq!(kink, Some(Continuity::C0));
+    }
+
+    #[test]
+    fn cyclic_gradients_are_classified_across_the_seam() {
+        let control_points = crate::test_utils::wrapping_control_points();
+        let open = classify_continuity(
+            &control_points,
+            SplineMode::HermiteBezier,
+            GradientMode::Open,
+        );
+        assert_eq!((open[0], open[3]), (None, None));
+
+        let cyclic = classify_continuity(
+            &control_points,
+            SplineMode::HermiteBezier,
+            GradientMode::Cyclic,
+        );
+        for continuity in cyclic {
+            assert!(
+                matches!(continuity, Some(Continuity::C1 | Continuity::G2)),
+                "{:?}",
+                continuity
+            );
+        }
+    }
+
+    #[test]
+    fn subdivide_all_keeps_the_gradient() {
+        let control_points = crate::test_utils::wrapping_control_points();
+        for spline_mode in [
+            SplineMode::Linear,
+            SplineMode::Bezier,
+            SplineMode::QuadraticBezier,
+        ] {
+            let sub_divided = sub_divide_all_control_points(&control_points, spline_mode);
+            assert_eq!(sub_divided.len(), 2 * control_points.len() - 1);
+            let error = gradient_delta_e(
+                &path_lab_samples(&control_points, spline_mode),
+                &path_lab_samples(&sub_divided, spline_mode),
+            );
+            assert!(error < 1.0, "{:?}: ΔE {}", spline_mode, error);
+        }
+    }
+
+    #[test]
+    fn simplify_undoes_subdivide() {
+        let control_points = crate::test_utils::wrapping_control_points();
+        for spline_mode in [
+            SplineMode::Linear,
+            SplineMode::Bezier,
+            SplineMode::QuadraticBezier,
+        ] {
+            let sub_divided = sub_divide_all_control_points(
+                &sub_divide_all_control_points(&control_points, spline_mode),
+                spline_mode,
+            );
+            let simplified =
+                simplify_control_points(&sub_divided, spline_mode, GradientMode::Open, 1.0);
+            assert_eq!(simplified.len(), control_points.len(), "{:?}", spline_mode);
+        }
+    }
+
+    #[test]
+    fn simplify_keeps_points_that_space_the_gradient() {
+        // Removing the middle point would give the first segment half the gradient
+        let control_points: Vec<ControlPoint> = [0.0, 0.2, 1.0]
+            .into_iter()
+            .map(|v| ControlPoint::new_simple(ControlPointValue::new(0.5, v, 0.6), 0.0))
+            .collect();
+        let simplified =
+            simplify_control_points(&control_points, SplineMode::Linear, GradientMode::Open, 1.0);
+        assert_eq!(simplified.len(), 3);
+    }
+
+    #[test]
+    fn simplify_keeps_diverging_gradients_odd() {
+        let control_points: Vec<ControlPoint> = [0.0, 0.25, 0.5, 0.75, 1.0]
+            .into_iter()
+            .map(|v| ControlPoint::new_simple(ControlPointValue::new(0.5, v, 0.6), 0.0))
+            .collect();
+        let simplify = |gradient_mode| {
+            simplify_control_points(&control_points, SplineMode::Linear, gradient_mode, 1.0)
+        };
+        assert_eq!(simplify(GradientMode::Open).len(), 2);
+        assert_eq!(simplify(GradientMode::Diverging).len(), 3);
+    }
+
+    #[test]
+    fn simplify_stays_within_tolerance() {
+        let control_points = crate::test_utils::wrapping_control_points();
+        for spline_mode in crate::test_utils::IMPLEMENTED_SPLINE_MODES {
+            let max_delta_e = 5.0;
+            let simplified = simplify_control_points(
+                &control_points,
+                spline_mode,
+                GradientMode::Open,
+                max_delta_e,
+            );
+            assert_eq!(simplified.first(), control_points.first());
+            assert!(simplified.len() >= 2);
+            let error = gradient_delta_e(
+                &path_lab_samples(&control_points, spline_mode),
+                &path_lab_samples(&simplified, spline_mode),
+            );
+            assert!(error <= max_delta_e);
+        }
+    }
+}
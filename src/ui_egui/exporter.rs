@@ -0,0 +1,91 @@
+use eframe::egui::{self, DragValue, Ui};
+
+use crate::{
+    color::gamut::{Gamut, GamutMapping},
+    common::{GradientMode, SplineMode},
+    datatypes::control_point::ControlPoint,
+    export::{export_gradient, ExportFormat, ExportSettings, TransferFunction},
+    ui_egui::previewer::CurveSampler,
+};
+
+pub fn ui_exporter(
+    ui: &mut Ui,
+    settings: &mut ExportSettings,
+    control_points: &[ControlPoint],
+    spline_mode: SplineMode,
+    gradient_mode: GradientMode,
+) {
+    egui::Grid::new("exporter_grid")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("Samples");
+            ui.add(DragValue::new(&mut settings.num_samples).range(2..=65536));
+            ui.end_row();
+
+            ui.label("Primaries");
+            egui::ComboBox::from_id_salt("exporter_primaries")
+                .selected_text(settings.primaries.label())
+                .show_ui(ui, |ui| {
+                    for gamut in Gamut::ALL {
+                        ui.selectable_value(&mut settings.primaries, gamut, gamut.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Transfer function");
+            egui::ComboBox::from_id_salt("exporter_transfer_function")
+                .selected_text(settings.transfer_function.label())
+                .show_ui(ui, |ui| {
+                    for transfer_function in TransferFunction::ALL {
+                        ui.selectable_value(
+                            &mut settings.transfer_function,
+                            transfer_function,
+                            transfer_function.label(),
+                        );
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Gamut mapping");
+            egui::ComboBox::from_id_salt("exporter_gamut_mapping")
+                .selected_text(settings.mapping.label())
+                .show_ui(ui, |ui| {
+                    for mapping in GamutMapping::ALL {
+                        ui.selectable_value(&mut settings.mapping, mapping, mapping.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Format");
+            egui::ComboBox::from_id_salt("exporter_format")
+                .selected_text(settings.format.label())
+                .show_ui(ui, |ui| {
+                    for format in ExportFormat::ALL {
+                        ui.selectable_value(&mut settings.format, format, format.label());
+                    }
+                });
+            ui.end_row();
+
+            if settings.format.is_image() {
+                ui.label("Image height");
+                ui.add(DragValue::new(&mut settings.image_height).range(1..=4096));
+                ui.end_row();
+            }
+
+            ui.label("File");
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut settings.file_stem);
+                ui.label(format!(".{}", settings.format.extension()));
+            });
+            ui.end_row();
+        });
+
+    if ui.button("Export").clicked() {
+        let samples = CurveSampler::with_gradient_mode(control_points, spline_mode, gradient_mode)
+            .sample_n(settings.num_samples);
+        if let Err(e) = export_gradient(&samples, settings) {
+            log::error!("Export failed: {}", e);
+        }
+    }
+}
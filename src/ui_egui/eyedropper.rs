@@ -0,0 +1,372 @@
+use std::sync::Arc;
+
+use eframe::egui::{
+    self, Color32, ColorImage, CursorIcon, Painter, Pos2, Rect, Sense, Stroke, TextureHandle,
+    TextureOptions, Ui, Vec2,
+};
+use image::RgbaImage;
+
+use crate::{
+    common::ColorStringCopy, screen_capture::DesktopCapture, ui_egui::color_picker::format_color_as,
+};
+
+/// Pixels shown on each side of the picked one.
+const LOUPE_RADIUS: i32 = 5;
+const LOUPE_CELL_SIZE: f32 = 9.0;
+const LOUPE_OFFSET: f32 = 24.0;
+
+#[derive(Default)]
+enum EyedropperState {
+    #[default]
+    Idle,
+    WaitingForDesktopCapture(DesktopCapture),
+    /// Desktop capture failed, picking from a screenshot of our own window instead
+    WaitingForWindowScreenshot,
+    Picking {
+        image: Arc<ColorImage>,
+        texture: TextureHandle,
+        /// Shown fullscreen in its own viewport instead of over the main window
+        desktop: bool,
+    },
+}
+
+enum PickOutcome {
+    Picked(Color32),
+    Cancelled,
+}
+
+/// Picks a color from anywhere on the screen with a magnifier loupe.
+#[derive(Default)]
+pub struct Eyedropper {
+    state: EyedropperState,
+}
+
+impl std::fmt::Debug for Eyedropper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Eyedropper")
+            .field("active", &self.is_active())
+            .finish()
+    }
+}
+
+impl Eyedropper {
+    pub fn is_active(&self) -> bool {
+        !matches!(self.state, EyedropperState::Idle)
+    }
+
+    pub fn start(&mut self, ctx: &egui::Context) {
+        self.state = EyedropperState::WaitingForDesktopCapture(DesktopCapture::start());
+        ctx.request_repaint();
+    }
+
+    fn set_image(&mut self, ctx: &egui::Context, image: Arc<ColorImage>, desktop: bool) {
+        let texture = ctx.load_texture("eyedropper", image.clone(), TextureOptions::NEAREST);
+        self.state = EyedropperState::Picking {
+            image,
+            texture,
+            desktop,
+        };
+    }
+
+    /// Shows the picker while active, returns the color once one is clicked.
+    pub fn update(&mut self, ctx: &egui::Context) -> Option<Color32> {
+        let outcome = match &self.state {
+            EyedropperState::Idle => None,
+            EyedropperState::WaitingForDesktopCapture(capture) => {
+                match capture.poll() {
+                    Some(Ok(image)) => self.set_image(ctx, color_image_from_rgba(&image), true),
+                    Some(Err(e)) => {
+                        log::warn!(
+                            "Desktop capture failed ({}), picking from this window instead",
+                            e
+                        );
+                        ctx.send_viewport_cmd(
+                            egui::ViewportCommand::Screenshot(Default::default()),
+                        );
+                        self.state = EyedropperState::WaitingForWindowScreenshot;
+                    }
+                    // Nothing else wakes the ui up when the worker finishes
+                    None => ctx.request_repaint_after(std::time::Duration::from_millis(50)),
+                }
+                None
+            }
+            EyedropperState::WaitingForWindowScreenshot => {
+                let screenshot = ctx.input(|i| {
+                    i.raw.events.iter().find_map(|event| match event {
+                        egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                        _ => None,
+                    })
+                });
+                if let Some(image) = screenshot {
+                    self.set_image(ctx, image, false);
+                }
+                None
+            }
+            EyedropperState::Picking {
+                image,
+                texture,
+                desktop: true,
+            } => ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("eyedropper"),
+                egui::ViewportBuilder::default()
+                    .with_title("Eyedropper")
+                    .with_fullscreen(true)
+                    .with_decorations(false)
+                    .with_always_on_top(),
+                |ctx, _class| {
+                    // The capture spans every monitor, the viewport only covers one of them
+                    let uv = ctx.input(|i| {
+                        monitor_uv(
+                            i.viewport().inner_rect,
+                            i.viewport()
+                                .native_pixels_per_point
+                                .unwrap_or(i.pixels_per_point),
+                            image.size,
+                        )
+                    });
+                    egui::CentralPanel::default()
+                        .frame(egui::Frame::NONE)
+                        .show(ctx, |ui| ui_pick_from_image(ui, image, texture, uv))
+                        .inner
+                },
+            ),
+            EyedropperState::Picking {
+                image,
+                texture,
+                desktop: false,
+            } => {
+                // The screenshot covers the window exactly, so the overlay looks like the window
+                egui::Area::new(egui::Id::new("eyedropper"))
+                    .order(egui::Order::Foreground)
+                    .fixed_pos(Pos2::ZERO)
+                    .show(ctx, |ui| {
+                        ui.set_min_size(ctx.screen_rect().size());
+                        ui_pick_from_image(ui, image, texture, FULL_UV)
+                    })
+                    .inner
+            }
+        };
+
+        match outcome? {
+            PickOutcome::Picked(color) => {
+                self.state = EyedropperState::Idle;
+                Some(color)
+            }
+            PickOutcome::Cancelled => {
+                log::info!("Eyedropper cancelled");
+                self.state = EyedropperState::Idle;
+                None
+            }
+        }
+    }
+}
+
+fn color_image_from_rgba(image: &RgbaImage) -> Arc<ColorImage> {
+    Arc::new(ColorImage::from_rgba_unmultiplied(
+        [image.width() as usize, image.height() as usize],
+        image.as_raw(),
+    ))
+}
+
+const FULL_UV: Rect = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+
+/// Part of a desktop capture behind a viewport, from the viewport's position on the virtual
+/// desktop. The whole capture when the position is unknown, as on Wayland.
+fn monitor_uv(inner_rect: Option<Rect>, pixels_per_point: f32, image_size: [usize; 2]) -> Rect {
+    let Some(inner_rect) = inner_rect else {
+        return FULL_UV;
+    };
+    let image_size = Vec2::new(image_size[0] as f32, image_size[1] as f32);
+    let uv = Rect::from_min_max(
+        (inner_rect.min.to_vec2() * pixels_per_point / image_size).to_pos2(),
+        (inner_rect.max.to_vec2() * pixels_per_point / image_size).to_pos2(),
+    )
+    .intersect(FULL_UV);
+    if uv.is_positive() {
+        uv
+    } else {
+        FULL_UV
+    }
+}
+
+/// Pixel under `pos` with the `uv` part of the image stretched over `rect`.
+fn image_pixel_at(image: &ColorImage, rect: Rect, uv: Rect, pos: Pos2) -> [i32; 2] {
+    let in_rect = (pos - rect.min) / rect.size();
+    let uv = uv.min.to_vec2() + in_rect * uv.size();
+    [
+        ((uv.x * image.width() as f32) as i32).clamp(0, image.width() as i32 - 1),
+        ((uv.y * image.height() as f32) as i32).clamp(0, image.height() as i32 - 1),
+    ]
+}
+
+fn image_pixel(image: &ColorImage, [x, y]: [i32; 2]) -> Option<Color32> {
+    let inside = (0..image.width() as i32).contains(&x) && (0..image.height() as i32).contains(&y);
+    inside.then(|| image.pixels[y as usize * image.width() + x as usize])
+}
+
+/// Fills the ui with the screenshot, picks with a left click, cancels with a right click or Esc.
+fn ui_pick_from_image(
+    ui: &mut Ui,
+    image: &ColorImage,
+    texture: &TextureHandle,
+    uv: Rect,
+) -> Option<PickOutcome> {
+    let rect = ui.max_rect();
+    let response = ui.allocate_rect(rect, Sense::click());
+    ui.painter().image(texture.id(), rect, uv, Color32::WHITE);
+
+    let hover_pixel = response
+        .hover_pos()
+        .map(|pos| (pos, image_pixel_at(image, rect, uv, pos)));
+    if let Some((pos, pixel)) = hover_pixel {
+        ui.ctx().set_cursor_icon(CursorIcon::Crosshair);
+        paint_loupe(ui.painter(), rect, image, pixel, pos);
+    }
+
+    if response.clicked() {
+        return hover_pixel
+            .and_then(|(_, pixel)| image_pixel(image, pixel))
+            .map(PickOutcome::Picked);
+    }
+    // Released, a held Esc would also reach the main window and quit
+    if response.secondary_clicked() || ui.input(|i| i.key_released(egui::Key::Escape)) {
+        return Some(PickOutcome::Cancelled);
+    }
+    None
+}
+
+/// Magnified pixels around `pixel` next to the pointer, with the picked color's hex below.
+pub fn paint_loupe(
+    painter: &Painter,
+    bounds: Rect,
+    image: &ColorImage,
+    pixel: [i32; 2],
+    pointer: Pos2,
+) {
+    let cells = (2 * LOUPE_RADIUS + 1) as f32;
+    let grid_size = Vec2::splat(cells * LOUPE_CELL_SIZE);
+    let label_height = 18.0;
+    let size = grid_size + Vec2::new(0.0, label_height);
+
+    // Below right of the pointer, flipped to stay inside the bounds
+    let mut min = pointer + Vec2::splat(LOUPE_OFFSET);
+    if min.x + size.x > bounds.max.x {
+        min.x = pointer.x - LOUPE_OFFSET - size.x;
+    }
+    if min.y + size.y > bounds.max.y {
+        min.y = pointer.y - LOUPE_OFFSET - size.y;
+    }
+    let grid_rect = Rect::from_min_size(min, grid_size);
+    let loupe_rect = Rect::from_min_size(min, size);
+
+    painter.rect_filled(loupe_rect.expand(2.0), 3.0, Color32::from_gray(20));
+    for dy in -LOUPE_RADIUS..=LOUPE_RADIUS {
+        for dx in -LOUPE_RADIUS..=LOUPE_RADIUS {
+            let color = image_pixel(image, [pixel[0] + dx, pixel[1] + dy])
+                .unwrap_or(Color32::from_gray(20));
+            let cell_min = grid_rect.min
+                + Vec2::new(
+                    (dx + LOUPE_RADIUS) as f32 * LOUPE_CELL_SIZE,
+                    (dy + LOUPE_RADIUS) as f32 * LOUPE_CELL_SIZE,
+                );
+            painter.rect_filled(
+                Rect::from_min_size(cell_min, Vec2::splat(LOUPE_CELL_SIZE)),
+                0.0,
+                color,
+            );
+        }
+    }
+
+    let center = Rect::from_center_size(grid_rect.center(), Vec2::splat(LOUPE_CELL_SIZE));
+    painter.rect_stroke(
+        center.expand(1.0),
+        0.0,
+        Stroke::new(1.0, Color32::BLACK),
+        egui::StrokeKind::Outside,
+    );
+    painter.rect_stroke(
+        center,
+        0.0,
+        Stroke::new(1.0, Color32::WHITE),
+        egui::StrokeKind::Outside,
+    );
+
+    if let Some(color) = image_pixel(image, pixel) {
+        painter.text(
+            Pos2::new(grid_rect.center().x, grid_rect.max.y + label_height * 0.5),
+            egui::Align2::CENTER_CENTER,
+            format_color_as(color, ColorStringCopy::HEXNOA, None),
+            egui::FontId::monospace(12.0),
+            Color32::WHITE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::assert_golden, ui_egui::snapshot::SnapshotRenderer};
+
+    fn checker_image() -> ColorImage {
+        let size = [16, 16];
+        let pixels = (0..size[0] * size[1])
+            .map(|i| {
+                let (x, y) = (i % size[0], i / size[0]);
+                Color32::from_rgb(
+                    (x * 16) as u8,
+                    (y * 16) as u8,
+                    if (x + y) % 2 == 0 { 200 } else { 40 },
+                )
+            })
+            .collect();
+        ColorImage { size, pixels }
+    }
+
+    #[test]
+    fn maps_positions_to_image_pixels() {
+        let image = checker_image();
+        let rect = Rect::from_min_size(Pos2::new(10.0, 10.0), Vec2::splat(160.0));
+        let pixel_at = |pos| image_pixel_at(&image, rect, FULL_UV, pos);
+        assert_eq!(pixel_at(Pos2::new(10.0, 10.0)), [0, 0]);
+        assert_eq!(pixel_at(Pos2::new(95.0, 25.0)), [8, 1]);
+        assert_eq!(pixel_at(Pos2::new(500.0, 500.0)), [15, 15]);
+
+        // Right half of the image shown over the whole rect
+        let right_half = Rect::from_min_max(Pos2::new(0.5, 0.0), Pos2::new(1.0, 1.0));
+        assert_eq!(
+            image_pixel_at(&image, rect, right_half, Pos2::new(10.0, 10.0)),
+            [8, 0]
+        );
+        assert_eq!(
+            image_pixel_at(&image, rect, right_half, Pos2::new(95.0, 25.0)),
+            [12, 1]
+        );
+        assert_eq!(image_pixel(&image, [-1, 0]), None);
+        assert_eq!(image_pixel(&image, [3, 2]), Some(image.pixels[2 * 16 + 3]));
+    }
+
+    #[test]
+    fn crops_desktop_to_monitor() {
+        // Two 1920x1080 monitors side by side, the viewport on the right one at 2x scaling
+        let right_monitor = Rect::from_min_size(Pos2::new(960.0, 0.0), Vec2::new(960.0, 540.0));
+        assert_eq!(
+            monitor_uv(Some(right_monitor), 2.0, [3840, 1080]),
+            Rect::from_min_max(Pos2::new(0.5, 0.0), Pos2::new(1.0, 1.0))
+        );
+        assert_eq!(monitor_uv(None, 1.0, [3840, 1080]), FULL_UV);
+        // Outside the capture entirely
+        let elsewhere = Rect::from_min_size(Pos2::new(5000.0, 0.0), Vec2::splat(100.0));
+        assert_eq!(monitor_uv(Some(elsewhere), 1.0, [3840, 1080]), FULL_UV);
+    }
+
+    #[test]
+    fn loupe_snapshot() {
+        let image = checker_image();
+        let snapshot = SnapshotRenderer::new(200, 160).render(|ui| {
+            let bounds = ui.max_rect();
+            // Close to the right edge so the loupe flips to the left of the pointer
+            paint_loupe(ui.painter(), bounds, &image, [1, 8], Pos2::new(180.0, 10.0));
+        });
+        assert_golden("eyedropper_loupe", &snapshot);
+    }
+}
@@ -14,28 +14,34 @@ pub fn color_function_gradient(
 
         // background_checkers(ui.painter(), rect); // for alpha:
 
-        {
-            let num_: u32 = 6 * 6;
-            // fill color:
-            let mut mesh = Mesh::default();
-            for i in 0..=num_ {
-                let t = i as f32 / (num_ as f32);
-                let color = color_at(t);
-                let x = lerp(rect.left()..=rect.right(), t);
-                mesh.colored_vertex(pos2(x, rect.top()), color);
-                mesh.colored_vertex(pos2(x, rect.bottom()), color);
-                if i < num_ {
-                    mesh.add_triangle(2 * i + 0, 2 * i + 1, 2 * i + 2);
-                    mesh.add_triangle(2 * i + 1, 2 * i + 2, 2 * i + 3);
-                }
-            }
-            ui.painter().add(Shape::mesh(mesh));
-        }
+        paint_color_function_gradient(ui.painter(), rect, color_at);
     }
 
     response
 }
 
+pub fn paint_color_function_gradient(
+    painter: &Painter,
+    rect: Rect,
+    color_at: impl Fn(f32) -> Color32,
+) {
+    let num_: u32 = 6 * 6;
+    // fill color:
+    let mut mesh = Mesh::default();
+    for i in 0..=num_ {
+        let t = i as f32 / (num_ as f32);
+        let color = color_at(t);
+        let x = lerp(rect.left()..=rect.right(), t);
+        mesh.colored_vertex(pos2(x, rect.top()), color);
+        mesh.colored_vertex(pos2(x, rect.bottom()), color);
+        if i < num_ {
+            mesh.add_triangle(2 * i, 2 * i + 1, 2 * i + 2);
+            mesh.add_triangle(2 * i + 1, 2 * i + 2, 2 * i + 3);
+        }
+    }
+    painter.add(Shape::mesh(mesh));
+}
+
 pub fn mesh_gradient(ui: &mut Ui, size: Vec2, vertex_colors: &[Color32]) -> Response {
     let (rect, response) = ui.allocate_at_least(size, Sense::click_and_drag());
 
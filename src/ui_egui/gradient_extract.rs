@@ -0,0 +1,152 @@
+use eframe::egui::{self, DragValue, Sense, Ui, Vec2};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::SplineMode,
+    datatypes::control_point::ControlPoint,
+    gradient_extract::{
+        extract_gradient, oklab_to_hsv, ColorOrdering, ExtractSettings, ExtractedGradient,
+    },
+    gradient_map::load_image,
+    ui_egui::{
+        clipboard::read_image_from_clipboard, gradient::paint_color_function_gradient,
+        previewer::CurveSampler,
+    },
+};
+
+const PREVIEW_HEIGHT: f32 = 32.0;
+const MAX_COLORS: usize = 16;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct GradientExtractData {
+    pub input_path: String,
+    pub settings: ExtractSettings,
+    #[serde(skip)]
+    source: Option<RgbaImage>,
+    #[serde(skip)]
+    extracted: Option<ExtractedGradient>,
+    /// Settings `extracted` was computed with
+    #[serde(skip)]
+    extracted_with: Option<ExtractSettings>,
+}
+
+impl GradientExtractData {
+    fn set_source(&mut self, source: crate::error::Result<RgbaImage>, origin: &str) {
+        match source {
+            Ok(source) => {
+                log::info!(
+                    "Extracting from {} ({}x{})",
+                    origin,
+                    source.width(),
+                    source.height()
+                );
+                self.source = Some(source);
+                self.extracted_with = None;
+            }
+            Err(e) => log::error!("Failed to read image from {}: {}", origin, e),
+        }
+    }
+
+    fn update_extracted(&mut self) {
+        let Some(source) = &self.source else {
+            return;
+        };
+        if self.extracted_with == Some(self.settings) {
+            return;
+        }
+        self.extracted = extract_gradient(source, &self.settings)
+            .inspect_err(|e| log::error!("Failed to extract a gradient: {}", e))
+            .ok();
+        self.extracted_with = Some(self.settings);
+    }
+}
+
+/// Picks the dominant colors of an image as a new gradient, returns it when applied.
+pub fn ui_gradient_extract(
+    ui: &mut Ui,
+    data: &mut GradientExtractData,
+) -> Option<(Vec<ControlPoint>, SplineMode)> {
+    egui::Grid::new("gradient_extract_grid")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("Image");
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut data.input_path);
+                if ui.button("Load").clicked() {
+                    let path = data.input_path.clone();
+                    data.set_source(load_image(&path), &path);
+                }
+                if ui.button("Paste").clicked() {
+                    data.set_source(read_image_from_clipboard(), "clipboard");
+                }
+            });
+            ui.end_row();
+
+            ui.label("Colors");
+            ui.add(DragValue::new(&mut data.settings.num_colors).range(2..=MAX_COLORS));
+            ui.end_row();
+
+            ui.label("Order");
+            egui::ComboBox::from_id_salt("gradient_extract_ordering")
+                .selected_text(data.settings.ordering.label())
+                .show_ui(ui, |ui| {
+                    for ordering in ColorOrdering::ALL {
+                        ui.selectable_value(
+                            &mut data.settings.ordering,
+                            ordering,
+                            ordering.label(),
+                        );
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Tolerance (ΔE)");
+            ui.add(
+                DragValue::new(&mut data.settings.tolerance)
+                    .range(0.1..=20.0)
+                    .speed(0.05),
+            )
+            .on_hover_text("How closely the gradient follows the extracted colors");
+            ui.end_row();
+        });
+
+    data.update_extracted();
+    let Some(extracted) = &data.extracted else {
+        ui.weak("Load or paste an image to extract a gradient from it");
+        return None;
+    };
+
+    let (rect, _) = ui.allocate_exact_size(
+        Vec2::new(ui.available_width(), PREVIEW_HEIGHT),
+        Sense::hover(),
+    );
+    let (gradient_rect, swatches_rect) = rect.split_top_bottom_at_fraction(0.5);
+    let sampler = CurveSampler::new(&extracted.control_points, extracted.spline_mode);
+    paint_color_function_gradient(ui.painter(), gradient_rect, |x| sampler.sample(x).color());
+    let swatch_width = rect.width() / extracted.colors.len() as f32;
+    for (i, color) in extracted.colors.iter().enumerate() {
+        let swatch = egui::Rect::from_min_size(
+            swatches_rect.min + Vec2::new(i as f32 * swatch_width, 0.0),
+            Vec2::new(swatch_width, swatches_rect.height()),
+        );
+        ui.painter()
+            .rect_filled(swatch, 0.0, oklab_to_hsv(color.lab).color());
+    }
+
+    let mut applied = None;
+    ui.horizontal(|ui| {
+        if ui.button("Apply").clicked() {
+            applied = Some((extracted.control_points.clone(), extracted.spline_mode));
+        }
+        ui.label(format!(
+            "{} control points, {:?}, max ΔE {:.2}",
+            extracted.control_points.len(),
+            extracted.spline_mode,
+            extracted.max_delta_e
+        ));
+    });
+
+    applied
+}
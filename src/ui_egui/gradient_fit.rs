@@ -0,0 +1,383 @@
+use eframe::egui::{
+    self, Color32, DragValue, Pos2, Rect, Sense, Stroke, TextureHandle, TextureOptions, Ui, Vec2,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::SplineMode,
+    datatypes::control_point::ControlPoint,
+    gradient_fit::{fit_control_points, parse_color_list, FitResult, FitSettings},
+    gradient_map::load_image,
+    image_processing::{sample_line, FramePixelRead},
+    screen_capture::DesktopCapture,
+    ui_egui::{
+        clipboard::read_image_from_clipboard, gradient::paint_color_function_gradient,
+        previewer::CurveSampler, ui_common::ui_spline_mode_combo,
+    },
+};
+
+const PREVIEW_HEIGHT: f32 = 32.0;
+const MAX_CONTROL_POINTS: usize = 32;
+const IMAGE_MAX_HEIGHT: f32 = 240.0;
+const MAX_LINE_SAMPLES: usize = 1024;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GradientFitData {
+    pub colors_text: String,
+    pub image_path: String,
+    pub image_row: u32,
+    /// Pixels around the line averaged into each sample
+    pub line_radius: f32,
+    pub settings: FitSettings,
+    #[serde(skip)]
+    image: Option<FramePixelRead>,
+    #[serde(skip)]
+    texture: Option<TextureHandle>,
+    /// Line being drawn across the image, in pixels
+    #[serde(skip)]
+    line: Option<(Pos2, Pos2)>,
+    #[serde(skip)]
+    waiting_for_window_screenshot: bool,
+    #[serde(skip)]
+    desktop_capture: Option<DesktopCapture>,
+    #[serde(skip)]
+    samples: Vec<Color32>,
+    #[serde(skip)]
+    result: Option<FitResult>,
+    /// Settings `result` was fitted with
+    #[serde(skip)]
+    fitted_with: Option<FitSettings>,
+}
+
+impl Default for GradientFitData {
+    fn default() -> Self {
+        Self {
+            colors_text: String::new(),
+            image_path: String::new(),
+            image_row: 0,
+            line_radius: 2.0,
+            settings: FitSettings::default(),
+            image: None,
+            texture: None,
+            line: None,
+            waiting_for_window_screenshot: false,
+            desktop_capture: None,
+            samples: Vec::new(),
+            result: None,
+            fitted_with: None,
+        }
+    }
+}
+
+impl GradientFitData {
+    fn set_samples(&mut self, samples: crate::error::Result<Vec<Color32>>, origin: &str) {
+        match samples {
+            Ok(samples) => {
+                log::info!("Fitting {} colors from {}", samples.len(), origin);
+                self.samples = samples;
+                self.fit();
+            }
+            Err(e) => log::error!("Failed to read colors from {}: {}", origin, e),
+        }
+    }
+
+    fn set_image(
+        &mut self,
+        ctx: &egui::Context,
+        image: crate::error::Result<FramePixelRead>,
+        origin: &str,
+    ) {
+        match image {
+            Ok(image) => {
+                log::info!("Picking from {} ({}x{})", origin, image.width, image.height);
+                let color_image = image.to_color_image();
+                match &mut self.texture {
+                    Some(texture) => texture.set(color_image, TextureOptions::NEAREST),
+                    None => {
+                        self.texture = Some(ctx.load_texture(
+                            "gradient_fit_image",
+                            color_image,
+                            TextureOptions::NEAREST,
+                        ))
+                    }
+                }
+                self.image_row = self.image_row.min(image.height as u32 - 1);
+                self.image = Some(image);
+                self.line = None;
+            }
+            Err(e) => log::error!("Failed to read image from {}: {}", origin, e),
+        }
+    }
+
+    fn update_captures(&mut self, ctx: &egui::Context) {
+        if let Some(capture) = &self.desktop_capture {
+            match capture.poll() {
+                Some(image) => {
+                    self.desktop_capture = None;
+                    let image = image.map(|image| FramePixelRead::from_rgba_image(&image));
+                    self.set_image(ctx, image, "screen");
+                }
+                None => ctx.request_repaint_after(std::time::Duration::from_millis(50)),
+            }
+        }
+
+        if !self.waiting_for_window_screenshot {
+            return;
+        }
+        let screenshot = ctx.input(|i| {
+            i.raw.events.iter().find_map(|event| match event {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        if let Some(screenshot) = screenshot {
+            self.waiting_for_window_screenshot = false;
+            self.set_image(
+                ctx,
+                Ok(FramePixelRead::from_color_image(&screenshot)),
+                "window",
+            );
+        }
+    }
+
+    fn sample_line(&mut self, from: Pos2, to: Pos2) {
+        let Some(image) = &self.image else {
+            return;
+        };
+        let num_samples = (from.distance(to).round() as usize).clamp(2, MAX_LINE_SAMPLES);
+        let samples = sample_line(image, from, to, num_samples, self.line_radius);
+        self.set_samples(Ok(samples), "line");
+    }
+
+    /// Every pixel of `image_row`, for lookup table strips.
+    fn sample_row(&mut self) {
+        let Some(image) = &self.image else {
+            return;
+        };
+        let y = self.image_row as f32 + 0.5;
+        let line = (Pos2::new(0.5, y), Pos2::new(image.width as f32 - 0.5, y));
+        let samples = sample_line(
+            image,
+            line.0,
+            line.1,
+            image.width.clamp(2, MAX_LINE_SAMPLES),
+            0.0,
+        );
+        self.line = Some(line);
+        self.set_samples(Ok(samples), "row");
+    }
+
+    fn fit(&mut self) {
+        match fit_control_points(&self.samples, &self.settings) {
+            Ok(result) => {
+                log::info!(
+                    "Fitted {} control points, max ΔE {:.2}",
+                    result.control_points.len(),
+                    result.max_delta_e
+                );
+                self.result = Some(result);
+                self.fitted_with = Some(self.settings);
+            }
+            Err(e) => log::error!("Failed to fit: {}", e),
+        }
+    }
+}
+
+/// Shows the image, a line dragged across it is sampled and fitted.
+fn ui_line_picker(ui: &mut Ui, data: &mut GradientFitData) {
+    let (Some(image), Some(texture)) = (&data.image, &data.texture) else {
+        return;
+    };
+    let image_size = Vec2::new(image.width as f32, image.height as f32);
+    let scale = (ui.available_width() / image_size.x)
+        .min(IMAGE_MAX_HEIGHT / image_size.y)
+        .min(1.0);
+    let (rect, response) = ui.allocate_exact_size(image_size * scale, Sense::drag());
+    ui.painter().image(
+        texture.id(),
+        rect,
+        Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+        Color32::WHITE,
+    );
+
+    let to_pixels = |pos: Pos2| Pos2::ZERO + (pos - rect.min) / scale;
+    let to_screen = |pixels: Pos2| rect.min + pixels.to_vec2() * scale;
+    if let Some(pos) = response.interact_pointer_pos() {
+        if response.drag_started() {
+            data.line = Some((to_pixels(pos), to_pixels(pos)));
+        } else if let Some((_, end)) = &mut data.line {
+            *end = to_pixels(pos);
+        }
+    }
+
+    if let Some((from, to)) = data.line {
+        let (from_screen, to_screen) = (to_screen(from), to_screen(to));
+        let painter = ui.painter_at(rect);
+        painter.line_segment([from_screen, to_screen], Stroke::new(3.0, Color32::BLACK));
+        painter.line_segment([from_screen, to_screen], Stroke::new(1.0, Color32::WHITE));
+        painter.circle(
+            from_screen,
+            4.0,
+            Color32::WHITE,
+            Stroke::new(1.0, Color32::BLACK),
+        );
+        painter.circle(
+            to_screen,
+            4.0,
+            Color32::BLACK,
+            Stroke::new(1.0, Color32::WHITE),
+        );
+
+        if response.drag_stopped() {
+            data.sample_line(from, to);
+        }
+    }
+}
+
+/// Fits control points to a list of colors, or to a line drawn across an image, returns them
+/// when applied.
+pub fn ui_gradient_fit(
+    ui: &mut Ui,
+    data: &mut GradientFitData,
+) -> Option<(Vec<ControlPoint>, SplineMode)> {
+    data.update_captures(ui.ctx());
+
+    egui::Grid::new("gradient_fit_grid")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("Colors");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut data.colors_text)
+                        .hint_text("#ff0000, #00ff00, ..."),
+                );
+                if ui.button("Use").clicked() {
+                    let samples = parse_color_list(&data.colors_text);
+                    data.set_samples(samples, "text");
+                }
+            });
+            ui.end_row();
+
+            ui.label("Image");
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut data.image_path);
+                if ui.button("Load").clicked() {
+                    let path = data.image_path.clone();
+                    let image =
+                        load_image(&path).map(|image| FramePixelRead::from_rgba_image(&image));
+                    data.set_image(ui.ctx(), image, &path);
+                }
+                if ui.button("Paste").clicked() {
+                    let image = read_image_from_clipboard()
+                        .map(|image| FramePixelRead::from_rgba_image(&image));
+                    data.set_image(ui.ctx(), image, "clipboard");
+                }
+                let capturing = data.desktop_capture.is_some();
+                if ui
+                    .add_enabled(!capturing, egui::Button::new("Screen"))
+                    .on_hover_text("Capture the whole desktop")
+                    .on_disabled_hover_text("Capturing the desktop…")
+                    .clicked()
+                {
+                    data.desktop_capture = Some(DesktopCapture::start());
+                }
+                if ui
+                    .button("Window")
+                    .on_hover_text("Capture this window, previewer included")
+                    .clicked()
+                {
+                    ui.ctx()
+                        .send_viewport_cmd(egui::ViewportCommand::Screenshot(Default::default()));
+                    data.waiting_for_window_screenshot = true;
+                }
+            });
+            ui.end_row();
+
+            if let Some(height) = data.image.as_ref().map(|image| image.height) {
+                ui.label("Row");
+                ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut data.image_row).range(0..=height - 1));
+                    if ui.button("Use").clicked() {
+                        data.sample_row();
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Line radius");
+                ui.add(
+                    DragValue::new(&mut data.line_radius)
+                        .range(0.0..=16.0)
+                        .speed(0.1),
+                )
+                .on_hover_text("Pixels around the line averaged into each sample");
+                ui.end_row();
+            }
+
+            ui.label("Spline mode");
+            ui_spline_mode_combo(
+                ui,
+                "gradient_fit_spline_mode",
+                &mut data.settings.spline_mode,
+            );
+            ui.end_row();
+
+            ui.label("Tolerance (ΔE)");
+            ui.add(
+                DragValue::new(&mut data.settings.tolerance)
+                    .range(0.1..=20.0)
+                    .speed(0.05),
+            );
+            ui.end_row();
+
+            ui.label("Max points");
+            ui.add(
+                DragValue::new(&mut data.settings.max_control_points).range(2..=MAX_CONTROL_POINTS),
+            );
+            ui.end_row();
+        });
+
+    ui_line_picker(ui, data);
+
+    if data.fitted_with != Some(data.settings) {
+        data.result = None;
+    }
+    if data.samples.is_empty() {
+        ui.weak("Enter hex colors, or load an image and drag a line across it");
+        return None;
+    }
+
+    let (rect, _) = ui.allocate_exact_size(
+        Vec2::new(ui.available_width(), PREVIEW_HEIGHT),
+        Sense::hover(),
+    );
+    let (samples_rect, fitted_rect) = rect.split_top_bottom_at_fraction(0.5);
+    let samples = &data.samples;
+    paint_color_function_gradient(ui.painter(), samples_rect, |x| {
+        samples[(x * (samples.len() - 1) as f32).round() as usize]
+    });
+    if let Some(result) = &data.result {
+        let sampler = CurveSampler::new(&result.control_points, data.settings.spline_mode);
+        paint_color_function_gradient(ui.painter(), fitted_rect, |x| sampler.sample(x).color());
+    }
+
+    let mut applied = None;
+    ui.horizontal(|ui| {
+        if ui.button("Fit").clicked() {
+            data.fit();
+        }
+        if let Some(result) = &data.result {
+            if ui.button("Apply").clicked() {
+                applied = Some((result.control_points.clone(), data.settings.spline_mode));
+            }
+            ui.label(format!(
+                "{} control points, max ΔE {:.2}",
+                result.control_points.len(),
+                result.max_delta_e
+            ));
+        }
+    });
+
+    applied
+}
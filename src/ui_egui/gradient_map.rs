@@ -0,0 +1,210 @@
+use eframe::egui::{self, ColorImage, TextureHandle, TextureOptions, Ui};
+use image::{imageops::FilterType, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{GradientMode, SplineMode},
+    datatypes::control_point::ControlPoint,
+    gradient_map::{gradient_map_image, load_image, save_image, GradientMapSettings, MapChannel},
+};
+
+/// The live preview is remapped from a downscaled copy, saving uses the full image.
+const PREVIEW_MAX_SIZE: u32 = 512;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GradientMapData {
+    pub input_path: String,
+    pub output_path: String,
+    pub settings: GradientMapSettings,
+    #[serde(skip)]
+    source: Option<RgbaImage>,
+    #[serde(skip)]
+    preview_source: Option<RgbaImage>,
+    #[serde(skip)]
+    preview_texture: Option<TextureHandle>,
+    /// Inputs the preview was last mapped with
+    #[serde(skip)]
+    mapped_with: Option<(
+        Vec<ControlPoint>,
+        SplineMode,
+        GradientMode,
+        GradientMapSettings,
+    )>,
+}
+
+impl Default for GradientMapData {
+    fn default() -> Self {
+        Self {
+            input_path: String::new(),
+            output_path: "gradient_map.png".to_string(),
+            settings: GradientMapSettings::default(),
+            source: None,
+            preview_source: None,
+            preview_texture: None,
+            mapped_with: None,
+        }
+    }
+}
+
+impl GradientMapData {
+    fn load(&mut self) {
+        match load_image(&self.input_path) {
+            Ok(source) => {
+                let preview_source = if source.width().max(source.height()) > PREVIEW_MAX_SIZE {
+                    let scale =
+                        PREVIEW_MAX_SIZE as f32 / source.width().max(source.height()) as f32;
+                    image::imageops::resize(
+                        &source,
+                        ((source.width() as f32 * scale) as u32).max(1),
+                        ((source.height() as f32 * scale) as u32).max(1),
+                        FilterType::Triangle,
+                    )
+                } else {
+                    source.clone()
+                };
+                log::info!(
+                    "Loaded {} ({}x{})",
+                    self.input_path,
+                    source.width(),
+                    source.height()
+                );
+                self.source = Some(source);
+                self.preview_source = Some(preview_source);
+                self.mapped_with = None;
+            }
+            Err(e) => log::error!("Failed to load {}: {}", self.input_path, e),
+        }
+    }
+
+    fn update_preview(
+        &mut self,
+        ctx: &egui::Context,
+        control_points: &[ControlPoint],
+        spline_mode: SplineMode,
+        gradient_mode: GradientMode,
+    ) {
+        let Some(preview_source) = &self.preview_source else {
+            return;
+        };
+        let inputs = (
+            control_points.to_vec(),
+            spline_mode,
+            gradient_mode,
+            self.settings,
+        );
+        if self.mapped_with.as_ref() == Some(&inputs) {
+            return;
+        }
+
+        let mapped = gradient_map_image(
+            preview_source,
+            control_points,
+            spline_mode,
+            gradient_mode,
+            &self.settings,
+        );
+        let color_image = ColorImage::from_rgba_unmultiplied(
+            [mapped.width() as usize, mapped.height() as usize],
+            mapped.as_raw(),
+        );
+        match &mut self.preview_texture {
+            Some(texture) => texture.set(color_image, TextureOptions::LINEAR),
+            None => {
+                self.preview_texture = Some(ctx.load_texture(
+                    "gradient_map_preview",
+                    color_image,
+                    TextureOptions::LINEAR,
+                ))
+            }
+        }
+        self.mapped_with = Some(inputs);
+    }
+
+    fn save(
+        &self,
+        control_points: &[ControlPoint],
+        spline_mode: SplineMode,
+        gradient_mode: GradientMode,
+    ) {
+        let Some(source) = &self.source else {
+            log::error!("No image loaded");
+            return;
+        };
+        let mapped = gradient_map_image(
+            source,
+            control_points,
+            spline_mode,
+            gradient_mode,
+            &self.settings,
+        );
+        match save_image(mapped, &self.output_path) {
+            Ok(()) => log::info!("Saved gradient map to {}", self.output_path),
+            Err(e) => log::error!("Failed to save {}: {}", self.output_path, e),
+        }
+    }
+}
+
+/// Loads an image, remaps one of its channels through the gradient and previews the result.
+pub fn ui_gradient_map(
+    ui: &mut Ui,
+    data: &mut GradientMapData,
+    control_points: &[ControlPoint],
+    spline_mode: SplineMode,
+    gradient_mode: GradientMode,
+) {
+    egui::Grid::new("gradient_map_grid")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("Input");
+            ui.horizontal(|ui| {
+                let response = ui.text_edit_singleline(&mut data.input_path);
+                let submitted =
+                    response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("Load").clicked() || submitted {
+                    data.load();
+                }
+            });
+            ui.end_row();
+
+            ui.label("Channel");
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("gradient_map_channel")
+                    .selected_text(data.settings.channel.label())
+                    .show_ui(ui, |ui| {
+                        for channel in MapChannel::ALL {
+                            ui.selectable_value(
+                                &mut data.settings.channel,
+                                channel,
+                                channel.label(),
+                            );
+                        }
+                    });
+                ui.checkbox(&mut data.settings.invert, "Invert");
+            });
+            ui.end_row();
+
+            ui.label("Output");
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut data.output_path);
+                if ui
+                    .add_enabled(data.source.is_some(), egui::Button::new("Save"))
+                    .clicked()
+                {
+                    data.save(control_points, spline_mode, gradient_mode);
+                }
+            });
+            ui.end_row();
+        });
+
+    data.update_preview(ui.ctx(), control_points, spline_mode, gradient_mode);
+
+    if let Some(texture) = &data.preview_texture {
+        let available = ui.available_size();
+        let size = texture.size_vec2();
+        let scale = (available.x / size.x).min(available.y / size.y).min(1.0);
+        ui.image((texture.id(), size * scale));
+    } else {
+        ui.weak("Load a PNG or JPEG to preview it mapped through the gradient");
+    }
+}
@@ -0,0 +1,203 @@
+use eframe::egui::{self, Color32, DragValue, Sense, Ui, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{ColorStringCopy, GradientMode, SplineMode},
+    datatypes::control_point::ControlPoint,
+    harmony::{generate_harmony, HarmonyRule, HarmonySettings},
+    ui_egui::{
+        app::GradientId, color_picker::format_color_as, gradient::paint_color_function_gradient,
+        previewer::CurveSampler, ui_common::ui_spline_mode_combo,
+    },
+};
+
+const PREVIEW_HEIGHT: f32 = 32.0;
+
+pub enum HarmonyAction {
+    /// Save the generated gradient as a new preset and switch to it
+    CreatePreset {
+        name: String,
+        control_points: Vec<ControlPoint>,
+        spline_mode: SplineMode,
+    },
+    Undo,
+}
+
+/// What a created preset replaced, so it can be put back.
+#[derive(Clone, Debug)]
+pub struct HarmonyUndo {
+    pub gradient: GradientId,
+    pub control_points: Vec<ControlPoint>,
+    pub spline_mode: SplineMode,
+    pub gradient_mode: GradientMode,
+    pub source_preset: Option<String>,
+    /// By name, removing the created preset shifts the indices after it
+    pub selected_preset: Option<String>,
+    pub created_preset: String,
+    /// The gradient as the created preset left it
+    pub applied_control_points: Vec<ControlPoint>,
+    pub applied_spline_mode: SplineMode,
+}
+
+impl HarmonyUndo {
+    /// Whether the gradient is still as the created preset left it, restoring it otherwise
+    /// would throw away later edits.
+    pub fn is_unchanged(&self, control_points: &[ControlPoint], spline_mode: SplineMode) -> bool {
+        self.applied_spline_mode == spline_mode && self.applied_control_points == control_points
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HarmonyData {
+    pub settings: HarmonySettings,
+    pub preset_name: String,
+    #[serde(skip)]
+    pub undo: Option<HarmonyUndo>,
+    #[serde(skip)]
+    generated: Vec<ControlPoint>,
+    /// Settings `generated` was computed with
+    #[serde(skip)]
+    generated_with: Option<HarmonySettings>,
+}
+
+impl HarmonyData {
+    fn update_generated(&mut self) {
+        if self.generated_with == Some(self.settings) {
+            return;
+        }
+        self.generated = generate_harmony(&self.settings);
+        self.generated_with = Some(self.settings);
+    }
+
+    fn default_preset_name(&self) -> String {
+        format!(
+            "{} {}",
+            self.settings.rule.label(),
+            format_color_as(self.settings.base, ColorStringCopy::HEXNOA, None)
+        )
+    }
+}
+
+/// Builds a gradient from color-theory rules around a base color, `selected_color` is the
+/// selected control point's color to start from.
+pub fn ui_harmony(
+    ui: &mut Ui,
+    data: &mut HarmonyData,
+    selected_color: Option<Color32>,
+) -> Option<HarmonyAction> {
+    egui::Grid::new("harmony_grid")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("Base color");
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgba(&mut data.settings.base);
+                if ui
+                    .add_enabled(selected_color.is_some(), egui::Button::new("From selected"))
+                    .on_hover_text("Use the selected control point's color")
+                    .clicked()
+                {
+                    data.settings.base = selected_color.unwrap();
+                }
+            });
+            ui.end_row();
+
+            ui.label("Rule");
+            egui::ComboBox::from_id_salt("harmony_rule")
+                .selected_text(data.settings.rule.label())
+                .show_ui(ui, |ui| {
+                    for rule in HarmonyRule::ALL {
+                        ui.selectable_value(&mut data.settings.rule, rule, rule.label());
+                    }
+                });
+            ui.end_row();
+
+            if data.settings.rule.uses_angle() {
+                ui.label("Angle");
+                ui.add(
+                    DragValue::new(&mut data.settings.angle)
+                        .range(5.0..=90.0)
+                        .suffix("°"),
+                );
+                ui.end_row();
+            }
+
+            ui.label("Lightness spread");
+            ui.add(
+                DragValue::new(&mut data.settings.lightness_spread)
+                    .range(-1.0..=1.0)
+                    .speed(0.01),
+            )
+            .on_hover_text("OKLCH lightness from the first color to the last");
+            ui.end_row();
+
+            ui.label("Chroma spread");
+            ui.add(
+                DragValue::new(&mut data.settings.chroma_spread)
+                    .range(-0.4..=0.4)
+                    .speed(0.005),
+            )
+            .on_hover_text("OKLCH chroma from the first color to the last");
+            ui.end_row();
+
+            ui.label("Spline mode");
+            ui_spline_mode_combo(ui, "harmony_spline_mode", &mut data.settings.spline_mode);
+            ui.end_row();
+
+            ui.label("Preset name");
+            let hint = data.default_preset_name();
+            ui.add(egui::TextEdit::singleline(&mut data.preset_name).hint_text(hint));
+            ui.end_row();
+        });
+
+    data.update_generated();
+    let (rect, _) = ui.allocate_exact_size(
+        Vec2::new(ui.available_width(), PREVIEW_HEIGHT),
+        Sense::hover(),
+    );
+    let (gradient_rect, swatches_rect) = rect.split_top_bottom_at_fraction(0.5);
+    let sampler = CurveSampler::new(&data.generated, data.settings.spline_mode);
+    paint_color_function_gradient(ui.painter(), gradient_rect, |x| sampler.sample(x).color());
+    let swatch_width = rect.width() / data.generated.len() as f32;
+    for (i, control_point) in data.generated.iter().enumerate() {
+        let swatch = egui::Rect::from_min_size(
+            swatches_rect.min + Vec2::new(i as f32 * swatch_width, 0.0),
+            Vec2::new(swatch_width, swatches_rect.height()),
+        );
+        ui.painter()
+            .rect_filled(swatch, 0.0, control_point.val().color());
+    }
+
+    let mut action = None;
+    ui.horizontal(|ui| {
+        if ui.button("Create preset").clicked() {
+            let name = if data.preset_name.is_empty() {
+                data.default_preset_name()
+            } else {
+                data.preset_name.clone()
+            };
+            action = Some(HarmonyAction::CreatePreset {
+                name,
+                control_points: data.generated.clone(),
+                spline_mode: data.settings.spline_mode,
+            });
+        }
+        let undo_hover = data.undo.as_ref().map_or(String::new(), |undo| {
+            format!(
+                "Remove preset [{}] and restore the gradient, unless it was edited since",
+                undo.created_preset
+            )
+        });
+        if ui
+            .add_enabled(data.undo.is_some(), egui::Button::new("Undo"))
+            .on_hover_text(undo_hover)
+            .on_disabled_hover_text("Nothing to undo")
+            .clicked()
+        {
+            action = Some(HarmonyAction::Undo);
+        }
+    });
+
+    action
+}
@@ -7,7 +7,26 @@ use std::{
 use eframe::egui;
 use serde::{Deserialize, Serialize};
 
-use crate::{common::ColorStringCopy, logger::ui_log_window, ui_egui::app::ZColorPickerAppContext};
+use crate::{
+    common::{ColorStringCopy, GradientMode},
+    datatypes::control_point::ControlPoint,
+    export::ExportSettings,
+    logger::ui_log_window,
+    ui_egui::{
+        app::{GradientId, ZColorPickerAppContext},
+        blend::{ui_blend, BlendAction, BlendData},
+        channel_curves::ui_channel_curves,
+        color_space_3d::{ui_color_space_3d, ColorSpace3dData},
+        colormaps::{ui_colormaps, ColormapsData},
+        compare::{ui_compare, CompareData},
+        contrast_matrix::{ui_contrast_matrix, ContrastMatrixData},
+        exporter::ui_exporter,
+        gradient_extract::{ui_gradient_extract, GradientExtractData},
+        gradient_fit::{ui_gradient_fit, GradientFitData},
+        gradient_map::{ui_gradient_map, GradientMapData},
+        harmony::{ui_harmony, HarmonyAction, HarmonyData, HarmonyUndo},
+    },
+};
 pub struct TreeBehavior {}
 
 impl egui_tiles::Behavior<Pane> for TreeBehavior {
@@ -15,6 +34,30 @@ impl egui_tiles::Behavior<Pane> for TreeBehavior {
         pane.title().into()
     }
 
+    /// Containers are named after their first pane, so a gradient's tab shows its name.
+    fn tab_title_for_tile(
+        &mut self,
+        tiles: &egui_tiles::Tiles<Pane>,
+        tile_id: egui_tiles::TileId,
+    ) -> egui::WidgetText {
+        match tiles.get(tile_id) {
+            Some(egui_tiles::Tile::Pane(pane)) => self.tab_title_for_pane(pane),
+            Some(egui_tiles::Tile::Container(container)) => match container.children().next() {
+                Some(child) => self.tab_title_for_tile(tiles, *child),
+                None => format!("{:?}", container.kind()).into(),
+            },
+            None => "MISSING TILE".into(),
+        }
+    }
+
+    fn simplification_options(&self) -> egui_tiles::SimplificationOptions {
+        egui_tiles::SimplificationOptions {
+            // Keeps the gradient tab bar, and somewhere to open more, with one gradient open
+            prune_single_child_tabs: false,
+            ..Default::default()
+        }
+    }
+
     fn pane_ui(
         &mut self,
         ui: &mut egui::Ui,
@@ -31,6 +74,51 @@ pub enum Pane {
     ColorPickerOptionsPane(ColorPickerOptionsPane),
     Previewer(PreviewerPane),
     Log(LogPane),
+    ContrastMatrix(ContrastMatrixPane),
+    Exporter(ExporterPane),
+    ColorSpace3d(ColorSpace3dPane),
+    ChannelCurves(ChannelCurvesPane),
+    GradientMap(GradientMapPane),
+    GradientExtract(GradientExtractPane),
+    GradientFit(GradientFitPane),
+    Harmony(HarmonyPane),
+    Colormaps(ColormapsPane),
+    Compare(ComparePane),
+    Blend(BlendPane),
+}
+
+impl Pane {
+    /// The gradient a per-gradient pane shows, `None` for panes working on the active one.
+    pub fn gradient(&self) -> Option<GradientId> {
+        match self {
+            Pane::ColorPicker(pane) => Some(pane.gradient),
+            Pane::ColorPickerOptionsPane(pane) => Some(pane.gradient),
+            Pane::Previewer(pane) => Some(pane.gradient),
+            Pane::ColorSpace3d(pane) => Some(pane.gradient),
+            Pane::ChannelCurves(pane) => Some(pane.gradient),
+            _ => None,
+        }
+    }
+}
+
+/// Makes `gradient` the one tool panes work on when its pane is clicked.
+fn activate_gradient_on_press(
+    ui: &egui::Ui,
+    ctx: &mut ZColorPickerAppContext,
+    gradient: GradientId,
+) {
+    if ui.ui_contains_pointer() && ui.input(|i| i.pointer.any_pressed()) {
+        ctx.active_gradient_id = gradient;
+    }
+}
+
+/// A control point being dragged out of the color picker of `gradient`, with its value from
+/// before the drag so it can be put back when dropped on another gradient.
+#[derive(Clone, Debug)]
+pub struct ControlPointDrag {
+    pub gradient: GradientId,
+    pub index: usize,
+    pub control_point: ControlPoint,
 }
 
 impl ZAppPane for Pane {
@@ -40,6 +128,17 @@ impl ZAppPane for Pane {
             Pane::ColorPickerOptionsPane(pane) => pane.title().into(),
             Pane::Previewer(pane) => pane.title().into(),
             Pane::Log(pane) => pane.title().into(),
+            Pane::ContrastMatrix(pane) => pane.title(),
+            Pane::Exporter(pane) => pane.title(),
+            Pane::ColorSpace3d(pane) => pane.title(),
+            Pane::ChannelCurves(pane) => pane.title(),
+            Pane::GradientMap(pane) => pane.title(),
+            Pane::GradientExtract(pane) => pane.title(),
+            Pane::GradientFit(pane) => pane.title(),
+            Pane::Harmony(pane) => pane.title(),
+            Pane::Colormaps(pane) => pane.title(),
+            Pane::Compare(pane) => pane.title(),
+            Pane::Blend(pane) => pane.title(),
         }
     }
     fn update_ctx(&mut self, new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {
@@ -48,6 +147,17 @@ impl ZAppPane for Pane {
             Pane::ColorPickerOptionsPane(pane) => pane.update_ctx(new_ctx),
             Pane::Previewer(pane) => pane.update_ctx(new_ctx),
             Pane::Log(pane) => pane.update_ctx(new_ctx),
+            Pane::ContrastMatrix(pane) => pane.update_ctx(new_ctx),
+            Pane::Exporter(pane) => pane.update_ctx(new_ctx),
+            Pane::ColorSpace3d(pane) => pane.update_ctx(new_ctx),
+            Pane::ChannelCurves(pane) => pane.update_ctx(new_ctx),
+            Pane::GradientMap(pane) => pane.update_ctx(new_ctx),
+            Pane::GradientExtract(pane) => pane.update_ctx(new_ctx),
+            Pane::GradientFit(pane) => pane.update_ctx(new_ctx),
+            Pane::Harmony(pane) => pane.update_ctx(new_ctx),
+            Pane::Colormaps(pane) => pane.update_ctx(new_ctx),
+            Pane::Compare(pane) => pane.update_ctx(new_ctx),
+            Pane::Blend(pane) => pane.update_ctx(new_ctx),
         }
     }
 
@@ -57,6 +167,17 @@ impl ZAppPane for Pane {
             Pane::ColorPickerOptionsPane(pane) => pane.ui(ui),
             Pane::Previewer(pane) => pane.ui(ui),
             Pane::Log(pane) => pane.ui(ui),
+            Pane::ContrastMatrix(pane) => pane.ui(ui),
+            Pane::Exporter(pane) => pane.ui(ui),
+            Pane::ColorSpace3d(pane) => pane.ui(ui),
+            Pane::ChannelCurves(pane) => pane.ui(ui),
+            Pane::GradientMap(pane) => pane.ui(ui),
+            Pane::GradientExtract(pane) => pane.ui(ui),
+            Pane::GradientFit(pane) => pane.ui(ui),
+            Pane::Harmony(pane) => pane.ui(ui),
+            Pane::Colormaps(pane) => pane.ui(ui),
+            Pane::Compare(pane) => pane.ui(ui),
+            Pane::Blend(pane) => pane.ui(ui),
         }
     }
 }
@@ -85,29 +206,106 @@ pub trait ZAppPane {
 pub struct ColorPickerPane {
     pub title: Option<String>,
     pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub gradient: GradientId,
+}
+
+impl ColorPickerPane {
+    /// Copies a control point dragged from another gradient's color picker into this one, and
+    /// puts the dragged one back where it started.
+    fn drop_control_point(&self, ui: &egui::Ui, ctx: &mut ZColorPickerAppContext) {
+        if !(ui.ui_contains_pointer() && ui.input(|i| i.pointer.any_released())) {
+            return;
+        }
+        let Some(drag) = egui::DragAndDrop::take_payload::<ControlPointDrag>(ui.ctx()) else {
+            return;
+        };
+        if drag.gradient == self.gradient {
+            return;
+        }
+
+        if let Some(source) = ctx.gradient_mut(drag.gradient) {
+            if let Some(control_point) = source.control_points.get_mut(drag.index) {
+                *control_point = drag.control_point.clone();
+            }
+        }
+        if let Some(target) = ctx.gradient_mut(self.gradient) {
+            let count = target.control_points.len() + 1;
+            if !target.gradient_mode.allows_control_point_count(count) {
+                log::warn!(
+                    "{} gradients need an odd number of control points",
+                    target.gradient_mode.label()
+                );
+                return;
+            }
+            target
+                .z_color_picker
+                .borrow_mut()
+                .spawn_control_point(&mut target.control_points, drag.control_point.clone());
+            ctx.active_gradient_id = self.gradient;
+        }
+    }
 }
 
 impl ZAppPane for ColorPickerPane {
     fn title(&self) -> String {
-        self.title.clone().unwrap_or(format!("Pane"))
+        self.title.clone().unwrap_or_else(|| {
+            let ctx = self.ctx.borrow();
+            match ctx.gradient(self.gradient) {
+                Some(gradient)
+                    if ctx.gradients.len() > 1 && ctx.active_gradient_id == self.gradient =>
+                {
+                    format!("▶ {}", gradient.name)
+                }
+                Some(gradient) => gradient.name.clone(),
+                None => "Pane".to_string(),
+            }
+        })
     }
     fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
-        // TODO: Fix this borrowing stuff
-        let mut color_picker = self.ctx.borrow().z_color_picker.borrow().clone();
         let mut mut_ctx = self.ctx.borrow_mut();
+        activate_gradient_on_press(ui, &mut mut_ctx, self.gradient);
+        self.drop_control_point(ui, &mut mut_ctx);
+
         let color_copy_format = mut_ctx.color_copy_format;
-        let mut control_points = mut_ctx.control_points.clone();
-        let spline_mode = mut_ctx.spline_mode;
+        let Some(gradient) = mut_ctx.gradient_mut(self.gradient) else {
+            return egui_tiles::UiResponse::None;
+        };
+        // TODO: Fix this borrowing stuff
+        let mut color_picker = gradient.z_color_picker.borrow().clone();
+        let mut control_points = gradient.control_points.clone();
+        let spline_mode = gradient.spline_mode;
+        let gradient_mode = gradient.gradient_mode;
 
         // ui.painter().rect_filled(ui.max_rect(), 0.0, Color32::WHITE);
         ui.allocate_ui(ui.max_rect().size(), |ui| {
-            let color_picker_response =
-                color_picker.draw_ui(ui, &mut control_points, spline_mode, &color_copy_format);
-            *mut_ctx.z_color_picker.borrow_mut() = color_picker;
-            color_picker_response
+            color_picker.draw_ui(
+                ui,
+                &mut control_points,
+                spline_mode,
+                gradient_mode,
+                &color_copy_format,
+            )
         });
 
-        mut_ctx.control_points = control_points;
+        // Other gradients' color pickers take the point when it is dropped on them
+        if let Some(index) = color_picker.dragging_index {
+            if !egui::DragAndDrop::has_payload_of_type::<ControlPointDrag>(ui.ctx()) {
+                if let Some(control_point) = gradient.control_points.get(index) {
+                    egui::DragAndDrop::set_payload(
+                        ui.ctx(),
+                        ControlPointDrag {
+                            gradient: self.gradient,
+                            index,
+                            control_point: control_point.clone(),
+                        },
+                    );
+                }
+            }
+        }
+
+        *gradient.z_color_picker.borrow_mut() = color_picker;
+        gradient.control_points = control_points;
 
         return egui_tiles::UiResponse::None;
     }
@@ -120,16 +318,24 @@ impl ZAppPane for ColorPickerPane {
 pub struct ColorPickerOptionsPane {
     pub title: Option<String>,
     pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub gradient: GradientId,
 }
 impl ZAppPane for ColorPickerOptionsPane {
     fn title(&self) -> String {
         self.title.clone().unwrap_or(format!("Pane"))
     }
     fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
-        let mut color_picker = self.ctx.borrow().z_color_picker.borrow().clone();
         let mut mut_ctx = self.ctx.borrow_mut();
-        let mut control_points = mut_ctx.control_points.clone();
-        let mut spline_mode = mut_ctx.spline_mode;
+        activate_gradient_on_press(ui, &mut mut_ctx, self.gradient);
+        let Some(gradient) = mut_ctx.gradient(self.gradient) else {
+            return egui_tiles::UiResponse::None;
+        };
+        let mut color_picker = gradient.z_color_picker.borrow().clone();
+        let mut control_points = gradient.control_points.clone();
+        let mut spline_mode = gradient.spline_mode;
+        let mut gradient_mode = gradient.gradient_mode;
+        let mut name = gradient.name.clone();
         let color_copy_format = mut_ctx.color_copy_format;
 
         let mut options = color_picker.options.clone();
@@ -142,16 +348,85 @@ impl ZAppPane for ColorPickerOptionsPane {
             &mut options,
             &mut control_points,
             &mut spline_mode,
+            &mut gradient_mode,
             &mut color_copy_format,
         );
         color_picker.options = options;
 
+        if ui
+            .add_enabled(
+                !mut_ctx.eyedropper.is_active(),
+                egui::Button::new("💧 Pick from screen"),
+            )
+            .on_hover_text("Set the selected control point to a color from anywhere on screen")
+            .clicked()
+        {
+            mut_ctx.active_gradient_id = self.gradient;
+            mut_ctx.eyedropper.start(ui.ctx());
+        }
+
+        let selected_index = color_picker.last_modifying_point_index;
+        ui.horizontal(|ui| {
+            let can_copy = selected_index.is_some_and(|i| i + 1 < control_points.len());
+            if ui
+                .add_enabled(can_copy, egui::Button::new("Copy segment"))
+                .on_hover_text("Copy the selected control point and the next one")
+                .clicked()
+            {
+                let i = selected_index.unwrap();
+                mut_ctx.copied_control_points = control_points[i..=i + 1].to_vec();
+            }
+            let copied_count = mut_ctx.copied_control_points.len();
+            let can_paste = copied_count > 0
+                && gradient_mode.allows_control_point_count(control_points.len() + copied_count);
+            if ui
+                .add_enabled(can_paste, egui::Button::new("Paste"))
+                .on_hover_text("Insert the copied control points next to the selected one")
+                .on_disabled_hover_text(
+                    "Nothing copied, or it would leave a diverging gradient even",
+                )
+                .clicked()
+            {
+                let mut copied = mut_ctx.copied_control_points.clone();
+                // Each point is inserted next to the previous one, on the insert side
+                if !color_picker.options.is_insert_right {
+                    copied.reverse();
+                }
+                for control_point in copied {
+                    color_picker.spawn_control_point(&mut control_points, control_point);
+                }
+            }
+        });
+
+        let mut duplicate = false;
+        let mut close = false;
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut name).desired_width(120.0))
+                .on_hover_text("Gradient name");
+            duplicate = ui
+                .button("Duplicate")
+                .on_hover_text("Open a copy of this gradient in a new tab")
+                .clicked();
+            close = ui
+                .add_enabled(mut_ctx.gradients.len() > 1, egui::Button::new("Close"))
+                .clicked();
+        });
+
         mut_ctx.color_copy_format = color_copy_format;
         mut_ctx.options_window = options_window;
-        mut_ctx.control_points = control_points;
-        mut_ctx.spline_mode = spline_mode;
-
-        *mut_ctx.z_color_picker.borrow_mut() = color_picker;
+        if let Some(gradient) = mut_ctx.gradient_mut(self.gradient) {
+            gradient.control_points = control_points;
+            gradient.spline_mode = spline_mode;
+            gradient.gradient_mode = gradient_mode;
+            gradient.name = name;
+            *gradient.z_color_picker.borrow_mut() = color_picker;
+        }
+        if duplicate {
+            mut_ctx.duplicate_gradient(self.gradient);
+        }
+        if close {
+            mut_ctx.close_gradient(self.gradient);
+        }
 
         return egui_tiles::UiResponse::None;
     }
@@ -164,24 +439,33 @@ impl ZAppPane for ColorPickerOptionsPane {
 pub struct PreviewerPane {
     pub title: Option<String>,
     pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub gradient: GradientId,
 }
 impl ZAppPane for PreviewerPane {
     fn title(&self) -> String {
         self.title.clone().unwrap_or(format!("Pane"))
     }
     fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
-        // TODO: FIX this borrowing
-        let control_points = self.ctx.borrow().control_points.clone();
-        let spline_mode = self.ctx.borrow().spline_mode;
         let mut mut_ctx = self.ctx.borrow_mut();
+        activate_gradient_on_press(ui, &mut mut_ctx, self.gradient);
+        let Some(gradient) = mut_ctx.gradient_mut(self.gradient) else {
+            return egui_tiles::UiResponse::None;
+        };
+        let gamut_settings = gradient.z_color_picker.borrow().options.gamut_settings;
 
-        let mut previewer = mut_ctx.previewer.clone();
+        let mut previewer = gradient.previewer.clone();
 
-        previewer.update(&control_points, spline_mode);
+        previewer.update(
+            &gradient.control_points,
+            gradient.spline_mode,
+            gradient.gradient_mode,
+            gamut_settings,
+        );
         let response = previewer.draw_ui(ui, ColorStringCopy::HEXNOA);
 
+        gradient.previewer = previewer;
         mut_ctx.stored_ui_responses = response;
-        mut_ctx.previewer = previewer;
 
         return egui_tiles::UiResponse::None;
     }
@@ -208,3 +492,564 @@ impl ZAppPane for LogPane {
 
     fn update_ctx(&mut self, _new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {}
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct ContrastMatrixPane {
+    pub title: Option<String>,
+    pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub data: ContrastMatrixData,
+}
+impl ZAppPane for ContrastMatrixPane {
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| "Pane".to_string())
+    }
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        let palette: Vec<egui::Color32> = self
+            .ctx
+            .borrow()
+            .active_gradient()
+            .previewer
+            .data
+            .quantized_colors()
+            .iter()
+            .map(|c| c.color())
+            .collect();
+
+        ui_contrast_matrix(ui, &palette, &mut self.data);
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn update_ctx(&mut self, new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {
+        self.ctx = new_ctx.clone();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExporterPane {
+    pub title: Option<String>,
+    pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub settings: ExportSettings,
+}
+impl ZAppPane for ExporterPane {
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| "Pane".to_string())
+    }
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        let ctx = self.ctx.borrow();
+        let gradient = ctx.active_gradient();
+        let control_points = gradient.control_points.clone();
+        let spline_mode = gradient.spline_mode;
+        let gradient_mode = gradient.gradient_mode;
+        drop(ctx);
+
+        ui_exporter(
+            ui,
+            &mut self.settings,
+            &control_points,
+            spline_mode,
+            gradient_mode,
+        );
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn update_ctx(&mut self, new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {
+        self.ctx = new_ctx.clone();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ColorSpace3dPane {
+    pub title: Option<String>,
+    pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub gradient: GradientId,
+    #[serde(default)]
+    pub data: ColorSpace3dData,
+}
+impl ZAppPane for ColorSpace3dPane {
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| "Pane".to_string())
+    }
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        let mut mut_ctx = self.ctx.borrow_mut();
+        activate_gradient_on_press(ui, &mut mut_ctx, self.gradient);
+        let Some(gradient) = mut_ctx.gradient(self.gradient) else {
+            return egui_tiles::UiResponse::None;
+        };
+        let selected_index = gradient.z_color_picker.borrow().last_modifying_point_index;
+
+        ui_color_space_3d(
+            ui,
+            &mut self.data,
+            &gradient.control_points,
+            gradient.spline_mode,
+            gradient.gradient_mode,
+            selected_index,
+        );
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn update_ctx(&mut self, new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {
+        self.ctx = new_ctx.clone();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChannelCurvesPane {
+    pub title: Option<String>,
+    pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub gradient: GradientId,
+}
+impl ZAppPane for ChannelCurvesPane {
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| "Pane".to_string())
+    }
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        let mut mut_ctx = self.ctx.borrow_mut();
+        activate_gradient_on_press(ui, &mut mut_ctx, self.gradient);
+        let Some(gradient) = mut_ctx.gradient_mut(self.gradient) else {
+            return egui_tiles::UiResponse::None;
+        };
+        let mut control_points = gradient.control_points.clone();
+        let spline_mode = gradient.spline_mode;
+        let selected_index = gradient.z_color_picker.borrow().last_modifying_point_index;
+
+        let response = ui_channel_curves(ui, &mut control_points, spline_mode, selected_index);
+
+        gradient
+            .z_color_picker
+            .borrow_mut()
+            .last_modifying_point_index = response.selected_index;
+        if response.changed {
+            gradient.control_points = control_points;
+        }
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn update_ctx(&mut self, new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {
+        self.ctx = new_ctx.clone();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GradientMapPane {
+    pub title: Option<String>,
+    pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub data: GradientMapData,
+}
+impl ZAppPane for GradientMapPane {
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| "Pane".to_string())
+    }
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        let ctx = self.ctx.borrow();
+        let gradient = ctx.active_gradient();
+
+        ui_gradient_map(
+            ui,
+            &mut self.data,
+            &gradient.control_points,
+            gradient.spline_mode,
+            gradient.gradient_mode,
+        );
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn update_ctx(&mut self, new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {
+        self.ctx = new_ctx.clone();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GradientExtractPane {
+    pub title: Option<String>,
+    pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub data: GradientExtractData,
+}
+impl ZAppPane for GradientExtractPane {
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| "Pane".to_string())
+    }
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        if let Some((control_points, spline_mode)) = ui_gradient_extract(ui, &mut self.data) {
+            let mut mut_ctx = self.ctx.borrow_mut();
+            let gradient = mut_ctx.active_gradient_mut();
+            gradient.control_points = control_points;
+            gradient.spline_mode = spline_mode;
+            gradient.gradient_mode = GradientMode::Open;
+            gradient
+                .z_color_picker
+                .borrow_mut()
+                .last_modifying_point_index = None;
+        }
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn update_ctx(&mut self, new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {
+        self.ctx = new_ctx.clone();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GradientFitPane {
+    pub title: Option<String>,
+    pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub data: GradientFitData,
+}
+impl ZAppPane for GradientFitPane {
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| "Pane".to_string())
+    }
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        if let Some((control_points, spline_mode)) = ui_gradient_fit(ui, &mut self.data) {
+            let mut mut_ctx = self.ctx.borrow_mut();
+            let gradient = mut_ctx.active_gradient_mut();
+            gradient.control_points = control_points;
+            gradient.spline_mode = spline_mode;
+            gradient.gradient_mode = GradientMode::Open;
+            gradient
+                .z_color_picker
+                .borrow_mut()
+                .last_modifying_point_index = None;
+        }
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn update_ctx(&mut self, new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {
+        self.ctx = new_ctx.clone();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HarmonyPane {
+    pub title: Option<String>,
+    pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub data: HarmonyData,
+}
+impl ZAppPane for HarmonyPane {
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| "Pane".to_string())
+    }
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        let selected_color = {
+            let ctx = self.ctx.borrow();
+            let gradient = ctx.active_gradient();
+            let selected = gradient.z_color_picker.borrow().last_modifying_point_index;
+            selected
+                .and_then(|i| gradient.control_points.get(i))
+                .map(|control_point| control_point.val().color())
+        };
+
+        match ui_harmony(ui, &mut self.data, selected_color) {
+            Some(HarmonyAction::CreatePreset {
+                name,
+                control_points,
+                spline_mode,
+            }) => {
+                let mut mut_ctx = self.ctx.borrow_mut();
+                let ctx = &mut *mut_ctx;
+                let gradient = ctx.active_gradient();
+                let undo = HarmonyUndo {
+                    gradient: gradient.id,
+                    control_points: gradient.control_points.clone(),
+                    spline_mode: gradient.spline_mode,
+                    gradient_mode: gradient.gradient_mode,
+                    source_preset: gradient.source_preset.clone(),
+                    selected_preset: ctx
+                        .preset_handler
+                        .preset_selected_index
+                        .and_then(|index| ctx.preset_handler.presets.get(index))
+                        .map(|preset| preset.name.clone()),
+                    created_preset: name.clone(),
+                    applied_control_points: control_points.clone(),
+                    applied_spline_mode: spline_mode,
+                };
+                match ctx.preset_handler.create_preset(
+                    &name,
+                    &control_points,
+                    &spline_mode,
+                    &GradientMode::Open,
+                ) {
+                    Ok(()) => {
+                        log::info!("Created preset [{}]", name);
+                        let gradient = ctx.active_gradient_mut();
+                        gradient.control_points = control_points;
+                        gradient.spline_mode = spline_mode;
+                        gradient.gradient_mode = GradientMode::Open;
+                        gradient.source_preset = Some(name);
+                        gradient
+                            .z_color_picker
+                            .borrow_mut()
+                            .last_modifying_point_index = None;
+                        self.data.undo = Some(undo);
+                    }
+                    Err(e) => log::error!("Failed to create preset [{}]: {}", name, e),
+                }
+            }
+            Some(HarmonyAction::Undo) => {
+                if let Some(undo) = self.data.undo.take() {
+                    let mut mut_ctx = self.ctx.borrow_mut();
+                    let ctx = &mut *mut_ctx;
+                    let preset_handler = &mut ctx.preset_handler;
+                    preset_handler.preset_selected_index = preset_handler
+                        .presets
+                        .iter()
+                        .position(|preset| preset.name == undo.created_preset);
+                    if let Err(e) = preset_handler.delete_selected_preset() {
+                        log::error!("Failed to remove preset [{}]: {}", undo.created_preset, e);
+                    }
+                    preset_handler.preset_selected_index =
+                        undo.selected_preset.as_ref().and_then(|name| {
+                            preset_handler
+                                .presets
+                                .iter()
+                                .position(|preset| &preset.name == name)
+                        });
+                    match ctx.gradient_mut(undo.gradient) {
+                        Some(gradient)
+                            if undo
+                                .is_unchanged(&gradient.control_points, gradient.spline_mode) =>
+                        {
+                            gradient.control_points = undo.control_points;
+                            gradient.spline_mode = undo.spline_mode;
+                            gradient.gradient_mode = undo.gradient_mode;
+                            gradient.source_preset = undo.source_preset;
+                            gradient
+                                .z_color_picker
+                                .borrow_mut()
+                                .last_modifying_point_index = None;
+                            log::info!("Undid preset [{}]", undo.created_preset);
+                        }
+                        Some(_) => log::warn!(
+                            "Removed preset [{}], the gradient was edited since so it is kept",
+                            undo.created_preset
+                        ),
+                        None => log::warn!(
+                            "Removed preset [{}], its gradient was closed",
+                            undo.created_preset
+                        ),
+                    }
+                }
+            }
+            None => {}
+        }
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn update_ctx(&mut self, new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {
+        self.ctx = new_ctx.clone();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ColormapsPane {
+    pub title: Option<String>,
+    pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub data: ColormapsData,
+}
+impl ZAppPane for ColormapsPane {
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| "Pane".to_string())
+    }
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        let applied = {
+            let ctx = self.ctx.borrow();
+            ui_colormaps(ui, &mut self.data, ctx.preset_handler.builtin_presets())
+        };
+        if let Some((control_points, spline_mode, gradient_mode)) = applied {
+            let mut mut_ctx = self.ctx.borrow_mut();
+            mut_ctx.preset_handler.preset_selected_index = None;
+            let gradient = mut_ctx.active_gradient_mut();
+            gradient.control_points = control_points;
+            gradient.spline_mode = spline_mode;
+            gradient.gradient_mode = gradient_mode;
+            gradient.source_preset = None;
+            gradient
+                .z_color_picker
+                .borrow_mut()
+                .last_modifying_point_index = None;
+        }
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn update_ctx(&mut self, new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {
+        self.ctx = new_ctx.clone();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ComparePane {
+    pub title: Option<String>,
+    pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub data: CompareData,
+}
+impl ZAppPane for ComparePane {
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| "Pane".to_string())
+    }
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        let ctx = self.ctx.borrow();
+        let mapping = ctx
+            .active_gradient()
+            .z_color_picker
+            .borrow()
+            .options
+            .gamut_settings
+            .mapping;
+        ui_compare(ui, &mut self.data, &ctx, mapping);
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn update_ctx(&mut self, new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {
+        self.ctx = new_ctx.clone();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BlendPane {
+    pub title: Option<String>,
+    pub ctx: Rc<RefCell<ZColorPickerAppContext>>,
+    #[serde(default)]
+    pub data: BlendData,
+}
+impl ZAppPane for BlendPane {
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| "Pane".to_string())
+    }
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        let action = {
+            let ctx = self.ctx.borrow();
+            ui_blend(ui, &mut self.data, &ctx)
+        };
+        match action {
+            Some(BlendAction::Apply {
+                control_points,
+                spline_mode,
+            }) => {
+                let mut mut_ctx = self.ctx.borrow_mut();
+                mut_ctx.preset_handler.preset_selected_index = None;
+                let gradient = mut_ctx.active_gradient_mut();
+                gradient.control_points = control_points;
+                gradient.spline_mode = spline_mode;
+                gradient.gradient_mode = GradientMode::Open;
+                gradient.source_preset = None;
+                gradient
+                    .z_color_picker
+                    .borrow_mut()
+                    .last_modifying_point_index = None;
+            }
+            Some(BlendAction::CreatePreset {
+                name,
+                control_points,
+                spline_mode,
+            }) => {
+                let mut mut_ctx = self.ctx.borrow_mut();
+                let preset_handler = &mut mut_ctx.preset_handler;
+                // Creating selects the new preset, but the blend is not loaded into the active
+                // gradient so the selection stays
+                let preset_selected_index = preset_handler.preset_selected_index;
+                match preset_handler.create_preset(
+                    &name,
+                    &control_points,
+                    &spline_mode,
+                    &GradientMode::Open,
+                ) {
+                    Ok(()) => log::info!("Created preset [{}]", name),
+                    Err(e) => log::error!("Failed to create preset [{}]: {}", name, e),
+                }
+                preset_handler.preset_selected_index = preset_selected_index;
+            }
+            None => {}
+        }
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn update_ctx(&mut self, new_ctx: Rc<RefCell<ZColorPickerAppContext>>) {
+        self.ctx = new_ctx.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::SplineMode,
+        test_utils::{assert_golden, wrapping_control_points},
+        ui_egui::snapshot::SnapshotRenderer,
+    };
+
+    /// Same state on every machine: no presets from disk and fixed control points.
+    fn fixed_app_context(spline_mode: SplineMode) -> Rc<RefCell<ZColorPickerAppContext>> {
+        let mut ctx = ZColorPickerAppContext::default();
+        ctx.preset_handler.presets.clear();
+        ctx.preset_handler.preset_selected_index = None;
+        let gradient = ctx.active_gradient_mut();
+        gradient.control_points = wrapping_control_points();
+        gradient.spline_mode = spline_mode;
+        gradient
+            .z_color_picker
+            .borrow_mut()
+            .last_modifying_point_index = Some(1);
+        Rc::new(RefCell::new(ctx))
+    }
+
+    fn assert_pane_snapshot(name: &str, pane: &mut impl ZAppPane, width: u32, height: u32) {
+        let image = SnapshotRenderer::new(width, height).render(|ui| {
+            let _ = pane.ui(ui);
+        });
+        assert_golden(name, &image);
+    }
+
+    #[test]
+    fn color_picker_pane_snapshots() {
+        for spline_mode in crate::test_utils::IMPLEMENTED_SPLINE_MODES {
+            let mut pane = ColorPickerPane {
+                title: None,
+                ctx: fixed_app_context(spline_mode),
+                gradient: 0,
+            };
+            let name = format!("pane_color_picker_{:?}", spline_mode).to_lowercase();
+            assert_pane_snapshot(&name, &mut pane, 800, 600);
+        }
+    }
+
+    #[test]
+    fn previewer_pane_snapshot() {
+        let mut pane = PreviewerPane {
+            title: None,
+            ctx: fixed_app_context(SplineMode::Bezier),
+            gradient: 0,
+        };
+        assert_pane_snapshot("pane_previewer", &mut pane, 600, 400);
+    }
+
+    #[test]
+    fn color_picker_options_pane_snapshot() {
+        let mut pane = ColorPickerOptionsPane {
+            title: None,
+            ctx: fixed_app_context(SplineMode::Bezier),
+            gradient: 0,
+        };
+        assert_pane_snapshot("pane_color_picker_options", &mut pane, 600, 300);
+    }
+}
@@ -1,30 +1,83 @@
-use ecolor::HsvaGamma;
 use eframe::{
-    egui::{self, Layout, PointerButton, Response, Sense, Ui, Vec2},
+    egui::{
+        self, lerp, Color32, Layout, Painter, PointerButton, Response, Sense, Stroke, Ui, Vec2,
+    },
     epaint::Rect,
 };
 use serde::{Deserialize, Serialize};
 use splines::Spline;
+use std::cell::RefCell;
 
 #[allow(unused_imports)]
 use crate::error::Result;
 use crate::{
-    common::{ColorStringCopy, SplineMode},
+    color::{
+        cvd::ColorVisionDeficiency, delta_e::min_pairwise_delta_e,
+        distinct::select_distinct_samples, gamut::GamutSettings,
+    },
+    common::{ColorStringCopy, GradientMode, SplineMode},
     datatypes::{
         control_point::{ControlPoint, ControlPointValue},
         hsv_key_value::HsvKeyValue,
     },
     ui_egui::{
-        control_points::{control_points_to_spline, find_spline_max_t, flatten_control_points},
-        gradient::color_function_gradient,
+        control_points::{find_spline_max_t, gradient_spline},
+        curves::OUT_OF_GAMUT_HIGHLIGHT_COLOR,
+        gradient::{color_function_gradient, paint_color_function_gradient},
         ui_common::color_button,
     },
 };
 
+/// Where the simulated colors of `rect` are painted, the bottom half when comparing side by side.
+fn cvd_simulation_rect(rect: Rect, previewer_data: &PreviewerData) -> Option<Rect> {
+    if previewer_data.cvd_simulation.is_none() {
+        return None;
+    }
+
+    if previewer_data.cvd_side_by_side {
+        Some(Rect::from_min_max(rect.left_center(), rect.right_bottom()))
+    } else {
+        Some(rect)
+    }
+}
+
+fn paint_cvd_simulated_color(
+    painter: &Painter,
+    rect: Rect,
+    color: Color32,
+    previewer_data: &PreviewerData,
+) {
+    if let Some(simulated_rect) = cvd_simulation_rect(rect, previewer_data) {
+        painter.rect_filled(
+            simulated_rect,
+            0.0,
+            previewer_data.cvd_simulation.simulate(color),
+        );
+    }
+}
+
+fn paint_out_of_gamut_highlight(
+    painter: &Painter,
+    rect: Rect,
+    val: &HsvKeyValue,
+    previewer_data: &PreviewerData,
+) {
+    let gamut_settings = &previewer_data.gamut_settings;
+    if gamut_settings.highlight_out_of_gamut && gamut_settings.is_out_of_gamut(val) {
+        painter.rect_stroke(
+            rect.shrink(1.0),
+            0.0,
+            Stroke::new(2.0, OUT_OF_GAMUT_HIGHLIGHT_COLOR),
+            egui::StrokeKind::Inside,
+        );
+    }
+}
+
 fn ui_previewer_colors(
     ui: &mut Ui,
     size: Vec2,
     control_points: &[ControlPointValue],
+    previewer_data: &PreviewerData,
     _color_copy_format: ColorStringCopy,
 ) -> Response {
     let rect = Rect::from_min_size(ui.available_rect_before_wrap().min, size);
@@ -43,34 +96,32 @@ fn ui_previewer_colors(
     let size_per_color_x = ui_size.x / (num_control_points as f32);
     let size_per_color_y = ui_size.y;
 
-    let mut points: Vec<Vec2> = Vec::with_capacity(num_control_points);
-    for cp in control_points {
-        points.push(Vec2::new(cp[0], cp[1]));
-    }
-
-    for i in 0..num_control_points {
-        if points.len() <= i {
-            break;
-        }
-        let color_data = &points[i];
-        let color_data_hue = control_points[i][2];
-        let color_at_point: HsvaGamma = HsvaGamma {
-            h: color_data_hue,
-            s: color_data.x,
-            v: color_data.y,
-            a: 1.0,
-        };
+    for control_point in control_points {
+        let color_at_point =
+            control_point.color_gamut_mapped(previewer_data.gamut_settings.mapping);
 
         let size_weight: f32 = 1.0;
-        color_button(
+        let response_button = color_button(
             &mut previewer_ui_control_points,
             Vec2 {
                 x: size_weight * size_per_color_x,
                 y: size_per_color_y,
             },
-            color_at_point.into(),
+            color_at_point,
             true,
         );
+        paint_cvd_simulated_color(
+            previewer_ui_control_points.painter(),
+            response_button.rect,
+            color_at_point,
+            previewer_data,
+        );
+        paint_out_of_gamut_highlight(
+            previewer_ui_control_points.painter(),
+            response_button.rect,
+            control_point,
+            previewer_data,
+        );
     }
 
     response
@@ -100,23 +151,10 @@ fn ui_previewer_control_points_with_drag(
     let size_per_color_y = ui_size.y;
     let previewer_sizes_sum: f32 = previewer_data.points_preview_sizes.iter().sum();
 
-    let mut points: Vec<Vec2> = Vec::with_capacity(num_control_points);
-    for cp in control_points {
-        points.push(Vec2::new(cp.val()[0], cp.val()[1]));
-    }
-
-    for i in 0..num_control_points {
-        if points.len() <= i {
-            break;
-        }
-        let color_data = &points[i];
-        let color_data_hue = control_points[i].val().h();
-        let color_at_point: HsvaGamma = HsvaGamma {
-            h: color_data_hue,
-            s: color_data.x,
-            v: color_data.y,
-            a: 1.0,
-        };
+    for (i, control_point) in control_points.iter().enumerate() {
+        let color_at_point = control_point
+            .val()
+            .color_gamut_mapped(previewer_data.gamut_settings.mapping);
 
         let size_weight: f32 = previewer_data.points_preview_sizes[i] * num_control_points as f32
             / previewer_sizes_sum;
@@ -126,9 +164,21 @@ fn ui_previewer_control_points_with_drag(
                 x: size_weight * size_per_color_x,
                 y: size_per_color_y,
             },
-            color_at_point.into(),
+            color_at_point,
             true,
         );
+        paint_cvd_simulated_color(
+            previewer_ui_control_points.painter(),
+            response_button.rect,
+            color_at_point,
+            previewer_data,
+        );
+        paint_out_of_gamut_highlight(
+            previewer_ui_control_points.painter(),
+            response_button.rect,
+            control_point.val(),
+            previewer_data,
+        );
 
         if response_button.dragged_by(PointerButton::Primary) {
             const PREVIEWER_DRAG_SENSITIVITY: f32 = 0.6;
@@ -176,15 +226,96 @@ fn modify_spline_t_to_preview_sizes(
 
         accum_size += preview_sizes[i];
     }
+    // The closing key of a cyclic gradient starts the next period
+    if let (Some(closing), Some(first_size)) = (
+        spline_as_vec.get_mut(preview_sizes.len()),
+        preview_sizes.first(),
+    ) {
+        closing.t = accum_size + first_size * 0.5;
+    }
 
     Spline::from_vec(spline_as_vec)
 }
 
+/// Samples the gradient over x in [0, 1] the same way the previewer curve is drawn.
+pub struct CurveSampler {
+    flatten_control_points: Vec<ControlPoint>,
+    spline: Spline<f32, ControlPointValue>,
+    spline_mode: SplineMode,
+    max_t: f32,
+}
+
+impl CurveSampler {
+    pub fn new(control_points: &[ControlPoint], spline_mode: SplineMode) -> Self {
+        Self::with_gradient_mode(control_points, spline_mode, GradientMode::Open)
+    }
+
+    /// Cyclic gradients get one more segment, from the last control point back to the first.
+    pub fn with_gradient_mode(
+        control_points: &[ControlPoint],
+        spline_mode: SplineMode,
+        gradient_mode: GradientMode,
+    ) -> Self {
+        let (flatten_control_points, spline) =
+            gradient_spline(control_points, spline_mode, gradient_mode);
+
+        // forgot what this does
+        // match spline_mode {
+        //     SplineMode::HermiteBezier => {}
+        //     _ => spline = modify_spline_t_to_preview_sizes(spline, spline_mode, previewer_data),
+        // };
+
+        let max_t = find_spline_max_t(&spline);
+
+        Self {
+            flatten_control_points,
+            spline,
+            spline_mode,
+            max_t,
+        }
+    }
+
+    pub fn sample(&self, x: f32) -> HsvKeyValue {
+        if self.flatten_control_points.is_empty() {
+            return HsvKeyValue::default();
+        } else if self.flatten_control_points.len() == 1 {
+            return *self.flatten_control_points[0].val();
+        }
+
+        let sample_x = match self.spline_mode {
+            SplineMode::HermiteBezier => {
+                let n_segments = self.flatten_control_points.len().saturating_sub(1) as f32;
+                1.0 + x * n_segments // map [0,1] to [1, len]
+            }
+            _ => x * self.max_t,
+        };
+
+        // CatmullRom needs a key past the end of its segment, so the last key itself samples
+        // as None
+        self.spline.clamped_sample(sample_x).unwrap_or_else(|| {
+            let end = if x < 0.5 {
+                self.flatten_control_points.first()
+            } else {
+                self.flatten_control_points.last()
+            };
+            *end.unwrap().val()
+        })
+    }
+
+    /// `num_samples` evenly spaced samples including both ends.
+    pub fn sample_n(&self, num_samples: usize) -> Vec<HsvKeyValue> {
+        (0..num_samples)
+            .map(|i| self.sample(i as f32 / (num_samples - 1).max(1) as f32))
+            .collect()
+    }
+}
+
 fn ui_previewer_curve(
     ui: &mut Ui,
     size: Vec2,
     control_points: &[ControlPoint],
     spline_mode: SplineMode,
+    previewer_data: &PreviewerData,
 ) -> Response {
     let rect = Rect::from_min_size(ui.available_rect_before_wrap().min, size);
     ui.allocate_rect(rect, Sense::click_and_drag());
@@ -195,54 +326,55 @@ fn ui_previewer_curve(
     );
     previewer_ui_curve.spacing_mut().item_spacing = Vec2::ZERO;
 
-    let flatten_control_points = flatten_control_points(control_points);
-    #[allow(unused_mut)]
-    let mut spline = control_points_to_spline(&flatten_control_points[..], spline_mode);
-
-    // forgot what this does
-    // match spline_mode {
-    //     SplineMode::HermiteBezier => {}
-    //     _ => spline = modify_spline_t_to_preview_sizes(spline, spline_mode, previewer_data),
-    // };
-
-    let max_t = find_spline_max_t(&spline);
+    let sampler =
+        CurveSampler::with_gradient_mode(control_points, spline_mode, previewer_data.gradient_mode);
+    let sample_at = |x: f32| sampler.sample(x);
+    let color_at = |x: f32| sample_at(x).color_gamut_mapped(previewer_data.gamut_settings.mapping);
+    let response = color_function_gradient(&mut previewer_ui_curve, rect.size(), color_at);
 
-    let response = color_function_gradient(&mut previewer_ui_curve, rect.size(), |x| {
-        if flatten_control_points.is_empty() {
-            return HsvaGamma::default().into();
-        } else if flatten_control_points.len() == 1 {
-            return flatten_control_points[0].val().color();
-        }
+    if let Some(simulated_rect) = cvd_simulation_rect(response.rect, previewer_data) {
+        paint_color_function_gradient(previewer_ui_curve.painter(), simulated_rect, |x| {
+            previewer_data.cvd_simulation.simulate(color_at(x))
+        });
+    }
 
-        let sample_x = match spline_mode {
-            SplineMode::HermiteBezier => {
-                let n_segments = flatten_control_points.len().saturating_sub(1) as f32;
-                1.0 + x * n_segments // map [0,1] to [1, len]
+    if previewer_data.gamut_settings.highlight_out_of_gamut {
+        const NUM_GAMUT_CHECKS: usize = 256;
+        let mut run_start: Option<f32> = None;
+        for i in 0..=NUM_GAMUT_CHECKS {
+            let x = i as f32 / NUM_GAMUT_CHECKS as f32;
+            let is_out_of_gamut = i < NUM_GAMUT_CHECKS
+                && previewer_data.gamut_settings.is_out_of_gamut(&sample_at(x));
+            match (run_start, is_out_of_gamut) {
+                (None, true) => run_start = Some(x),
+                (Some(start), false) => {
+                    let highlight_rect = Rect::from_x_y_ranges(
+                        lerp(response.rect.x_range(), start)..=lerp(response.rect.x_range(), x),
+                        response.rect.top()..=(response.rect.top() + OUT_OF_GAMUT_BAR_HEIGHT),
+                    );
+                    previewer_ui_curve.painter().rect_filled(
+                        highlight_rect,
+                        0.0,
+                        OUT_OF_GAMUT_HIGHLIGHT_COLOR,
+                    );
+                    run_start = None;
+                }
+                _ => {}
             }
-            _ => x * max_t,
         }
-        .max(1.0); // clamp lower bound only
-
-        let sample: HsvKeyValue = spline.clamped_sample(sample_x).unwrap_or(HsvKeyValue {
-            val: [1.0, 1.0, 1.0],
-        });
-        sample.color()
-    });
+    }
 
     response
 }
 
-fn ui_previewer_curve_quantized(
-    ui: &mut Ui,
-    size: Vec2,
+pub fn sample_quantized_colors(
     control_points: &[ControlPoint],
     spline_mode: SplineMode,
-    previewer_data: &mut PreviewerData,
-    color_copy_format: ColorStringCopy,
+    previewer_data: &PreviewerData,
     number_levels: usize,
-) -> Response {
-    let flatten_control_points = flatten_control_points(control_points);
-    let mut spline = control_points_to_spline(&flatten_control_points[..], spline_mode);
+) -> Vec<HsvKeyValue> {
+    let (flatten_control_points, mut spline) =
+        gradient_spline(control_points, spline_mode, previewer_data.gradient_mode);
 
     match spline_mode {
         SplineMode::HermiteBezier => {}
@@ -251,6 +383,29 @@ fn ui_previewer_curve_quantized(
 
     let max_t = find_spline_max_t(&spline);
 
+    if previewer_data.quantize_mode == QuantizeMode::Qualitative {
+        let candidates: Vec<HsvKeyValue> = (0..QUALITATIVE_NUM_CANDIDATES)
+            .map(|i| {
+                let x = i as f32 / (QUALITATIVE_NUM_CANDIDATES - 1) as f32;
+                let sample_x = match spline_mode {
+                    SplineMode::HermiteBezier => 1.0 + x * (max_t - 2.0),
+                    _ => x * max_t,
+                };
+                spline
+                    .clamped_sample(sample_x)
+                    .or_else(|| flatten_control_points.last().map(|cp| *cp.val()))
+                    .unwrap_or_default()
+            })
+            .collect();
+        let candidate_colors: Vec<Color32> = candidates
+            .iter()
+            .map(|c| previewer_data.distinctness_color(c.color()))
+            .collect();
+
+        let selection = select_distinct_samples(&candidate_colors, number_levels);
+        return selection.iter().map(|i| candidates[*i]).collect();
+    }
+
     let mut quantized_colors: Vec<HsvKeyValue> = Vec::new();
     for i in 0..number_levels {
         let sample_x = match spline_mode {
@@ -264,15 +419,36 @@ fn ui_previewer_curve_quantized(
         quantized_colors.push(sample);
     }
 
-    let response = ui_previewer_colors(ui, size, &quantized_colors, color_copy_format);
+    quantized_colors
+}
+
+fn ui_previewer_curve_quantized(
+    ui: &mut Ui,
+    size: Vec2,
+    previewer_data: &mut PreviewerData,
+    color_copy_format: ColorStringCopy,
+) -> Response {
+    let quantized_colors = previewer_data.quantized_colors();
+    let compared_colors: Vec<Color32> = quantized_colors
+        .iter()
+        .map(|c| previewer_data.distinctness_color(c.color()))
+        .collect();
+    previewer_data.quantized_min_delta_e = min_pairwise_delta_e(&compared_colors);
+
+    let response = ui_previewer_colors(
+        ui,
+        size,
+        &quantized_colors,
+        previewer_data,
+        color_copy_format,
+    );
     response
 }
 
 fn ui_previewer_options(ui: &mut Ui, _size: Vec2, previewer_data: &mut PreviewerData) {
-    let slider = egui::Slider::new(
-        &mut previewer_data.quantize_num_levels,
-        1..=previewer_data.control_points.len(),
-    );
+    let max_levels = previewer_data.max_quantize_levels();
+    previewer_data.quantize_num_levels = previewer_data.quantize_num_levels.clamp(1, max_levels);
+    let slider = egui::Slider::new(&mut previewer_data.quantize_num_levels, 1..=max_levels);
     let slider_size: Vec2 = Vec2::new(300.0, 25.0);
     let mut slider_button_rect: Rect = Rect::from_min_size(
         egui::Pos2 {
@@ -286,6 +462,63 @@ fn ui_previewer_options(ui: &mut Ui, _size: Vec2, previewer_data: &mut Previewer
     if ui.put(slider_button_rect, slider).clicked() {
         previewer_data.reset_preview_sizes();
     }
+
+    let cvd_options_rect = slider_button_rect.translate(Vec2::new(0.0, 25.0));
+    ui.scope_builder(egui::UiBuilder::new().max_rect(cvd_options_rect), |ui| {
+        ui.horizontal(|ui| {
+            egui::ComboBox::new("previewer_cvd_simulation", "")
+                .selected_text(previewer_data.cvd_simulation.label())
+                .show_ui(ui, |ui| {
+                    for cvd in ColorVisionDeficiency::ALL {
+                        ui.selectable_value(&mut previewer_data.cvd_simulation, cvd, cvd.label());
+                    }
+                })
+                .response
+                .on_hover_text("Color Vision Deficiency Simulation");
+            ui.add_enabled(
+                !previewer_data.cvd_simulation.is_none(),
+                egui::Checkbox::new(&mut previewer_data.cvd_side_by_side, "Side by side"),
+            )
+            .on_hover_text("Show the original colors above the simulated ones");
+        });
+    });
+
+    let quantize_options_rect = cvd_options_rect.translate(Vec2::new(0.0, 25.0));
+    ui.scope_builder(
+        egui::UiBuilder::new().max_rect(quantize_options_rect),
+        |ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::new("previewer_quantize_mode", "")
+                    .selected_text(format!("{:?}", previewer_data.quantize_mode))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut previewer_data.quantize_mode,
+                            QuantizeMode::Uniform,
+                            "Uniform",
+                        );
+                        ui.selectable_value(
+                            &mut previewer_data.quantize_mode,
+                            QuantizeMode::Qualitative,
+                            "Qualitative",
+                        );
+                    })
+                    .response
+                    .on_hover_text("Quantize Mode");
+                // Uniform allows fewer levels than Qualitative
+                previewer_data.quantize_num_levels = previewer_data
+                    .quantize_num_levels
+                    .min(previewer_data.max_quantize_levels());
+                ui.checkbox(&mut previewer_data.distinct_under_cvd, "CVD")
+                    .on_hover_text(
+                        "Measure distinctness under the simulated color vision deficiency",
+                    );
+                if let Some(min_delta_e) = previewer_data.quantized_min_delta_e {
+                    ui.label(format!("min ΔE00 {:.1}", min_delta_e))
+                        .on_hover_text("Smallest color difference between two quantized colors");
+                }
+            });
+        },
+    );
 }
 
 pub fn ui_previewer(
@@ -311,15 +544,13 @@ pub fn ui_previewer(
             previewer_rect.size() * Vec2::new(1.0, 0.25),
             control_points,
             spline_mode,
+            previewer_data,
         );
         let response_curve_quantized = ui_previewer_curve_quantized(
             ui,
             previewer_rect.size() * Vec2::new(1.0, 0.25),
-            control_points,
-            spline_mode,
             previewer_data,
             color_copy_format,
-            previewer_data.quantize_num_levels,
         );
         let _response_curve_options = ui_previewer_options(
             ui,
@@ -384,12 +615,55 @@ impl PreviewerUiResponses {
 }
 
 const PREVIEWER_DEFAULT_VALUE: f32 = 100.0;
+const OUT_OF_GAMUT_BAR_HEIGHT: f32 = 4.0;
+const QUALITATIVE_NUM_CANDIDATES: usize = 128;
+const QUALITATIVE_MAX_LEVELS: usize = 16;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum QuantizeMode {
+    /// Equal steps along the spline
+    #[default]
+    Uniform,
+    /// Steps chosen to maximize the smallest color difference between them
+    Qualitative,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PreviewerData {
     pub control_points: Vec<ControlPoint>,
     pub spline_mode: SplineMode,
+    #[serde(default)]
+    pub gradient_mode: GradientMode,
     pub points_preview_sizes: Vec<f32>,
     pub quantize_num_levels: usize,
+    #[serde(default)]
+    pub cvd_simulation: ColorVisionDeficiency,
+    #[serde(default)]
+    pub cvd_side_by_side: bool,
+    #[serde(default)]
+    pub quantize_mode: QuantizeMode,
+    #[serde(default)]
+    pub distinct_under_cvd: bool,
+    #[serde(skip)]
+    pub quantized_min_delta_e: Option<f32>,
+    #[serde(skip)]
+    pub gamut_settings: GamutSettings,
+    /// Qualitative selection is too slow to redo every frame
+    #[serde(skip)]
+    quantized_cache: RefCell<Option<(QuantizeKey, Vec<HsvKeyValue>)>>,
+}
+
+/// Everything `sample_quantized_colors` depends on.
+#[derive(Debug, Clone, PartialEq)]
+struct QuantizeKey {
+    control_points: Vec<ControlPoint>,
+    spline_mode: SplineMode,
+    gradient_mode: GradientMode,
+    points_preview_sizes: Vec<f32>,
+    quantize_mode: QuantizeMode,
+    quantize_num_levels: usize,
+    cvd_simulation: ColorVisionDeficiency,
+    distinct_under_cvd: bool,
 }
 
 impl PreviewerData {
@@ -398,15 +672,70 @@ impl PreviewerData {
             points_preview_sizes: vec![PREVIEWER_DEFAULT_VALUE; num],
             control_points: vec![ControlPoint::default(); num],
             spline_mode: SplineMode::HermiteBezier,
+            gradient_mode: GradientMode::default(),
             quantize_num_levels: 4,
+            cvd_simulation: ColorVisionDeficiency::default(),
+            cvd_side_by_side: false,
+            quantize_mode: QuantizeMode::default(),
+            distinct_under_cvd: false,
+            quantized_min_delta_e: None,
+            gamut_settings: GamutSettings::default(),
+            quantized_cache: RefCell::new(None),
         }
     }
+
+    /// Largest level count the slider allows in the current quantize mode.
+    pub fn max_quantize_levels(&self) -> usize {
+        match self.quantize_mode {
+            QuantizeMode::Uniform => self.control_points.len(),
+            QuantizeMode::Qualitative => self.control_points.len().max(QUALITATIVE_MAX_LEVELS),
+        }
+        .max(1)
+    }
     pub fn reset_preview_sizes(&mut self) {
         for val in self.points_preview_sizes.iter_mut() {
             *val = PREVIEWER_DEFAULT_VALUE;
         }
     }
 
+    /// The color used when measuring how distinct the quantized colors are.
+    pub fn distinctness_color(&self, color: Color32) -> Color32 {
+        if self.distinct_under_cvd {
+            self.cvd_simulation.simulate(color)
+        } else {
+            color
+        }
+    }
+
+    /// The colors shown in the quantized strip of the previewer, cached until something they
+    /// depend on changes.
+    pub fn quantized_colors(&self) -> Vec<HsvKeyValue> {
+        let key = QuantizeKey {
+            control_points: self.control_points.clone(),
+            spline_mode: self.spline_mode,
+            gradient_mode: self.gradient_mode,
+            points_preview_sizes: self.points_preview_sizes.clone(),
+            quantize_mode: self.quantize_mode,
+            quantize_num_levels: self.quantize_num_levels,
+            cvd_simulation: self.cvd_simulation,
+            distinct_under_cvd: self.distinct_under_cvd,
+        };
+        let mut cache = self.quantized_cache.borrow_mut();
+        match &*cache {
+            Some((cached_key, colors)) if *cached_key == key => colors.clone(),
+            _ => {
+                let colors = sample_quantized_colors(
+                    &self.control_points,
+                    self.spline_mode,
+                    self,
+                    self.quantize_num_levels,
+                );
+                *cache = Some((key, colors.clone()));
+                colors
+            }
+        }
+    }
+
     pub fn enforce_min_size(&mut self, min_size: f32) {
         for point_ref in &mut self.points_preview_sizes {
             *point_ref = point_ref.max(min_size);
@@ -426,8 +755,16 @@ impl ZPreviewer {
         }
     }
 
-    pub fn update(&mut self, control_points: &[ControlPoint], spline_mode: SplineMode) {
+    pub fn update(
+        &mut self,
+        control_points: &[ControlPoint],
+        spline_mode: SplineMode,
+        gradient_mode: GradientMode,
+        gamut_settings: GamutSettings,
+    ) {
         self.data.spline_mode = spline_mode;
+        self.data.gradient_mode = gradient_mode;
+        self.data.gamut_settings = gamut_settings;
 
         let old_size = self.data.control_points.len();
         let new_size = control_points.len();
@@ -456,3 +793,134 @@ impl Default for ZPreviewer {
         Self::default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::control_point::HueInterpolation;
+    use crate::test_utils::{
+        arb_control_points, arb_spline_mode, assert_golden, assert_hsv_close,
+        hue_interpolation_control_points, wrapping_control_points, IMPLEMENTED_SPLINE_MODES,
+    };
+    use image::{Rgb, RgbImage};
+    use proptest::prelude::*;
+
+    const GOLDEN_WIDTH: u32 = 256;
+    const GOLDEN_HEIGHT: u32 = 16;
+
+    proptest! {
+        #[test]
+        fn sampler_passes_through_control_points(
+            control_points in arb_control_points(8),
+            spline_mode in arb_spline_mode(),
+        ) {
+            let sampler = CurveSampler::new(&control_points, spline_mode);
+            let last_index = control_points.len() - 1;
+            for (i, control_point) in control_points.iter().enumerate() {
+                let sample = sampler.sample(i as f32 / last_index as f32);
+                assert_hsv_close(&sample, control_point.val());
+            }
+        }
+
+        #[test]
+        fn sampler_hue_stays_in_unit_range(
+            control_points in arb_control_points(8),
+            spline_mode in arb_spline_mode(),
+        ) {
+            let sampler = CurveSampler::new(&control_points, spline_mode);
+            for sample in sampler.sample_n(64) {
+                let hue = sample.hsv().h;
+                prop_assert!((0.0..1.0).contains(&hue), "{}", hue);
+            }
+        }
+    }
+
+    #[test]
+    fn sampler_handles_degenerate_input() {
+        for spline_mode in IMPLEMENTED_SPLINE_MODES {
+            assert_eq!(
+                CurveSampler::new(&[], spline_mode).sample(0.5),
+                HsvKeyValue::default()
+            );
+            let single = &wrapping_control_points()[..1];
+            assert_eq!(
+                CurveSampler::new(single, spline_mode).sample(0.5),
+                *single[0].val()
+            );
+        }
+    }
+
+    #[test]
+    fn quantized_colors_follow_setting_changes() {
+        let mut previewer = ZPreviewer::default();
+        previewer.update(
+            &wrapping_control_points(),
+            SplineMode::Linear,
+            GradientMode::Open,
+            GamutSettings::default(),
+        );
+        let mut data = previewer.data;
+
+        data.quantize_num_levels = 3;
+        assert_eq!(data.quantized_colors().len(), 3);
+        data.quantize_mode = QuantizeMode::Qualitative;
+        data.quantize_num_levels = 8;
+        assert_eq!(data.quantized_colors().len(), 8);
+        assert!(data.max_quantize_levels() >= 8);
+        data.quantize_mode = QuantizeMode::Uniform;
+        assert_eq!(data.max_quantize_levels(), 4);
+    }
+
+    fn render_gradient(control_points: &[ControlPoint], spline_mode: SplineMode) -> RgbImage {
+        let sampler = CurveSampler::new(control_points, spline_mode);
+        let colors: Vec<Color32> = sampler
+            .sample_n(GOLDEN_WIDTH as usize)
+            .iter()
+            .map(|sample| sample.color())
+            .collect();
+        RgbImage::from_fn(GOLDEN_WIDTH, GOLDEN_HEIGHT, |x, _| {
+            let color = colors[x as usize];
+            Rgb([color.r(), color.g(), color.b()])
+        })
+    }
+
+    #[test]
+    fn golden_gradients() {
+        let mut control_points = wrapping_control_points();
+        // Quadratic handles are initialised to half the segment by the picker
+        for i in 0..control_points.len() - 1 {
+            let half_segment = (*control_points[i + 1].val() - *control_points[i].val()) * 0.5;
+            control_points[i].tangents_mut()[1] = Some(half_segment);
+        }
+
+        for spline_mode in IMPLEMENTED_SPLINE_MODES {
+            let name = format!("gradient_{:?}", spline_mode).to_lowercase();
+            assert_golden(&name, &render_gradient(&control_points, spline_mode));
+        }
+    }
+
+    #[test]
+    fn golden_gradients_hue_interpolation() {
+        let mut control_points = hue_interpolation_control_points();
+        let mut rendered = Vec::new();
+        for hue_interpolation in HueInterpolation::ALL {
+            for control_point in control_points.iter_mut() {
+                *control_point.hue_interpolation_mut() = hue_interpolation;
+            }
+            let name = format!("gradient_hue_{:?}", hue_interpolation).to_lowercase();
+            let image = render_gradient(&control_points, SplineMode::Linear);
+            assert_golden(&name, &image);
+            rendered.push((hue_interpolation, image));
+        }
+
+        for (i, (method_a, image_a)) in rendered.iter().enumerate() {
+            for (method_b, image_b) in &rendered[i + 1..] {
+                assert_ne!(
+                    image_a, image_b,
+                    "{:?} renders like {:?}",
+                    method_a, method_b
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Renders egui ui offscreen on the CPU so panes can be compared against stored images without a
+//! display or GPU.
+
+use std::collections::HashMap;
+
+use eframe::egui::{
+    self,
+    epaint::{ClippedPrimitive, ImageData, Primitive, Vertex},
+    Color32, Pos2, Rect, TextureId, Vec2,
+};
+use image::{Rgb, RgbImage};
+
+/// Frames run before the captured one, so widgets that size themselves from the previous frame
+/// settle.
+const WARMUP_FRAMES: usize = 2;
+
+struct Texture {
+    size: [usize; 2],
+    pixels: Vec<Color32>,
+}
+
+impl Texture {
+    fn sample(&self, uv: Pos2) -> Color32 {
+        let x = ((uv.x * self.size[0] as f32) as usize).min(self.size[0] - 1);
+        let y = ((uv.y * self.size[1] as f32) as usize).min(self.size[1] - 1);
+        self.pixels[y * self.size[0] + x]
+    }
+}
+
+/// Headless egui context with a software rasterizer, at one pixel per point.
+pub struct SnapshotRenderer {
+    ctx: egui::Context,
+    size: Vec2,
+    textures: HashMap<TextureId, Texture>,
+}
+
+impl SnapshotRenderer {
+    pub fn new(width: u32, height: u32) -> Self {
+        let ctx = egui::Context::default();
+        ctx.set_visuals(egui::Visuals::dark());
+        Self {
+            ctx,
+            size: Vec2::new(width as f32, height as f32),
+            textures: HashMap::new(),
+        }
+    }
+
+    fn raw_input(&self, frame: usize) -> egui::RawInput {
+        egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, self.size)),
+            // Fixed time so animations and double click detection are deterministic
+            time: Some(frame as f64),
+            ..Default::default()
+        }
+    }
+
+    fn update_textures(&mut self, textures_delta: egui::TexturesDelta) {
+        for (id, delta) in textures_delta.set {
+            let (size, pixels): ([usize; 2], Vec<Color32>) = match &delta.image {
+                ImageData::Color(image) => (image.size, image.pixels.clone()),
+                ImageData::Font(image) => (image.size, image.srgba_pixels(None).collect()),
+            };
+            match delta.pos {
+                Some([x0, y0]) => {
+                    let texture = self.textures.get_mut(&id).unwrap();
+                    for y in 0..size[1] {
+                        for x in 0..size[0] {
+                            texture.pixels[(y0 + y) * texture.size[0] + x0 + x] =
+                                pixels[y * size[0] + x];
+                        }
+                    }
+                }
+                None => {
+                    self.textures.insert(id, Texture { size, pixels });
+                }
+            }
+        }
+        for id in textures_delta.free {
+            self.textures.remove(&id);
+        }
+    }
+
+    /// Runs `add_contents` in a central panel filling the screen and returns the last frame.
+    pub fn render(&mut self, mut add_contents: impl FnMut(&mut egui::Ui)) -> RgbImage {
+        let mut primitives = Vec::new();
+        for frame in 0..=WARMUP_FRAMES {
+            let output = self.ctx.run(self.raw_input(frame), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| add_contents(ui));
+            });
+            self.update_textures(output.textures_delta);
+            primitives = self.ctx.tessellate(output.shapes, output.pixels_per_point);
+        }
+
+        let mut canvas = Canvas::new(self.size.x as u32, self.size.y as u32);
+        for ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in &primitives
+        {
+            let Primitive::Mesh(mesh) = primitive else {
+                continue;
+            };
+            let Some(texture) = self.textures.get(&mesh.texture_id) else {
+                continue;
+            };
+            for triangle in mesh.indices.chunks_exact(3) {
+                canvas.fill_triangle(
+                    [
+                        &mesh.vertices[triangle[0] as usize],
+                        &mesh.vertices[triangle[1] as usize],
+                        &mesh.vertices[triangle[2] as usize],
+                    ],
+                    *clip_rect,
+                    texture,
+                );
+            }
+        }
+
+        canvas.into_image()
+    }
+}
+
+/// Premultiplied RGBA in gamma space, blended like egui's own renderers do.
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0.0, 0.0, 0.0, 1.0]; (width * height) as usize],
+        }
+    }
+
+    fn fill_triangle(&mut self, vertices: [&Vertex; 3], clip_rect: Rect, texture: &Texture) {
+        let [a, b, c] = vertices.map(|v| v.pos);
+        let area = (b - a).x * (c - a).y - (b - a).y * (c - a).x;
+        if area.abs() < f32::EPSILON {
+            return;
+        }
+
+        let bounds = Rect::from_points(&[a, b, c])
+            .intersect(clip_rect)
+            .intersect(Rect::from_min_size(
+                Pos2::ZERO,
+                Vec2::new(self.width as f32, self.height as f32),
+            ));
+        if !bounds.is_positive() {
+            return;
+        }
+
+        let edge =
+            |p0: Pos2, p1: Pos2, p: Pos2| (p1 - p0).x * (p - p0).y - (p1 - p0).y * (p - p0).x;
+        for y in bounds.top().floor() as u32..bounds.bottom().ceil() as u32 {
+            for x in bounds.left().floor() as u32..bounds.right().ceil() as u32 {
+                let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                if !clip_rect.contains(p) {
+                    continue;
+                }
+                let w = [
+                    edge(b, c, p) / area,
+                    edge(c, a, p) / area,
+                    edge(a, b, p) / area,
+                ];
+                if w.iter().any(|w| *w < 0.0) {
+                    continue;
+                }
+
+                let uv = vertices[0].uv.to_vec2() * w[0]
+                    + vertices[1].uv.to_vec2() * w[1]
+                    + vertices[2].uv.to_vec2() * w[2];
+                let texel = texture.sample(uv.to_pos2());
+                let mut src = [0.0; 4];
+                for (channel, src) in src.iter_mut().enumerate() {
+                    let vertex_channel: f32 = vertices
+                        .iter()
+                        .zip(w)
+                        .map(|(v, w)| v.color.to_array()[channel] as f32 * w)
+                        .sum();
+                    *src = vertex_channel / 255.0 * texel.to_array()[channel] as f32 / 255.0;
+                }
+
+                let dst = &mut self.pixels[(y * self.width + x) as usize];
+                for (dst, src_channel) in dst.iter_mut().zip(src) {
+                    *dst = src_channel + *dst * (1.0 - src[3]);
+                }
+            }
+        }
+    }
+
+    fn into_image(self) -> RgbImage {
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let pixel = self.pixels[(y * self.width + x) as usize];
+            Rgb([pixel[0], pixel[1], pixel[2]].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+        })
+    }
+}
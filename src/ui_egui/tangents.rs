@@ -5,26 +5,84 @@ use eframe::{
     epaint::PathShape,
 };
 
-use crate::{datatypes::control_point::ControlPoint, ui_egui::control_points::control_point_pos};
+use crate::{
+    datatypes::control_point::{ControlPoint, ControlPointTangent, HueInterpolation, TangentMode},
+    ui_egui::control_points::control_point_pos,
+};
 
 pub struct TangentUiResult {
     pub selected_by_tangent: bool,
     pub selected_tangent: Option<usize>,
     pub dragged_tangent: Option<egui::Response>,
+    pub new_tangent_mode: Option<TangentMode>,
 }
 
+/// Context menu entries for switching `tangent_mode`, returns the picked mode.
+pub fn ui_tangent_mode_menu(ui: &mut Ui, tangent_mode: TangentMode) -> Option<TangentMode> {
+    let mut new_tangent_mode = None;
+    ui.label("Tangent mode");
+    for mode in TangentMode::ALL {
+        if ui
+            .radio(tangent_mode == mode, mode.label())
+            .clicked_by(egui::PointerButton::Primary)
+        {
+            new_tangent_mode = Some(mode);
+            ui.close_menu();
+        }
+    }
+    new_tangent_mode
+}
+
+/// Handle shape hints at the mode, like vector editors do.
+fn tangent_handle_shape(
+    tangent_mode: TangentMode,
+    pos: Pos2,
+    radius: f32,
+    stroke: Stroke,
+) -> Shape {
+    match tangent_mode {
+        TangentMode::Free => Shape::rect_stroke(
+            Rect::from_center_size(pos, Vec2::splat(radius * 1.6)),
+            0.0,
+            stroke,
+            egui::StrokeKind::Middle,
+        ),
+        TangentMode::AutoSmooth => Shape::circle_filled(pos, radius, stroke.color),
+        TangentMode::Vector => Shape::convex_polygon(
+            vec![
+                pos + Vec2::new(0.0, -radius),
+                pos + Vec2::new(radius, 0.0),
+                pos + Vec2::new(0.0, radius),
+                pos + Vec2::new(-radius, 0.0),
+            ],
+            Color32::TRANSPARENT,
+            stroke,
+        ),
+        TangentMode::Aligned | TangentMode::Mirrored => Shape::circle_stroke(pos, radius, stroke),
+    }
+}
+
+/// How control points and their handles are drawn, shared by every point of a curve.
+#[derive(Clone, Copy)]
+pub struct TangentDrawStyle {
+    pub control_point_draw_size: Vec2,
+    pub control_point_radius: f32,
+    pub inactive_stroke: Stroke,
+}
+
+/// `quadratic_next` is set in `SplineMode::QuadraticBezier`, where only the out tangent is a
+/// handle and it is shared with the next control point.
 pub fn ui_control_point_tangents(
     ui: &mut Ui,
     cp_index: usize,
     cp: &ControlPoint,
+    quadratic_next: Option<&ControlPoint>,
     is_first: bool,
     is_last: bool,
     is_selected: bool,
     to_screen: &RectTransform,
     parent_response: &egui::Response,
-    control_point_draw_size: Vec2,
-    control_point_radius: f32,
-    inactive_stroke: Stroke,
+    style: TangentDrawStyle,
     tangent_shapes: &mut Vec<Shape>,
     tangent_paths: &mut Vec<PathShape>,
 ) -> TangentUiResult {
@@ -35,6 +93,11 @@ pub fn ui_control_point_tangents(
     const INACTIVE_LINE_ALPHA: f32 = 0.002;
     const INACTIVE_RADIUS_RATIO: f32 = 0.2 / 0.7;
 
+    let TangentDrawStyle {
+        control_point_draw_size,
+        control_point_radius,
+        inactive_stroke,
+    } = style;
     let cp_screen = to_screen.transform_pos(control_point_pos(cp));
     let parent_size = parent_response.rect.size();
 
@@ -45,10 +108,13 @@ pub fn ui_control_point_tangents(
         selected_by_tangent: false,
         selected_tangent: None,
         dragged_tangent: None,
+        new_tangent_mode: None,
     };
 
     for (tangent_index, tangent) in cp.tangents().iter().enumerate() {
-        if (tangent_index == 0 && is_first) || (tangent_index == 1 && is_last) {
+        if (tangent_index == 0 && (is_first || quadratic_next.is_some()))
+            || (tangent_index == 1 && is_last)
+        {
             continue;
         }
 
@@ -63,8 +129,13 @@ pub fn ui_control_point_tangents(
             let response = ui.interact(
                 Rect::from_center_size(tang_screen, control_point_draw_size),
                 parent_response.id.with((cp_index, tangent_index)),
-                Sense::drag(),
+                Sense::click_and_drag(),
             );
+            response.context_menu(|ui| {
+                if let Some(mode) = ui_tangent_mode_menu(ui, cp.tangent_mode()) {
+                    result.new_tangent_mode = Some(mode);
+                }
+            });
 
             if result.dragged_tangent.is_none() && response.dragged_by(Primary) {
                 tang_screen += response.drag_delta() / parent_size;
@@ -73,21 +144,31 @@ pub fn ui_control_point_tangents(
                 result.dragged_tangent = Some(response.clone());
             }
 
+            let mut path = vec![cp_screen, tang_screen];
+            if let Some(next) = quadratic_next {
+                path.push(to_screen.transform_pos(control_point_pos(next)));
+            }
             tangent_paths.push(PathShape::line(
-                vec![cp_screen, tang_screen],
+                path,
                 Stroke::new(1.0, Color32::WHITE.linear_multiply(ACTIVE_LINE_ALPHA)),
             ));
-            tangent_shapes.push(Shape::circle_stroke(
+            tangent_shapes.push(tangent_handle_shape(
+                cp.tangent_mode(),
                 tang_screen,
                 active_radius,
                 inactive_stroke,
             ));
         } else {
+            let mut path = vec![cp_screen, tang_screen];
+            if let Some(next) = quadratic_next {
+                path.push(to_screen.transform_pos(control_point_pos(next)));
+            }
             tangent_paths.push(PathShape::line(
-                vec![cp_screen, tang_screen],
+                path,
                 Stroke::new(1.0, Color32::WHITE.linear_multiply(INACTIVE_LINE_ALPHA)),
             ));
-            tangent_shapes.push(Shape::circle_stroke(
+            tangent_shapes.push(tangent_handle_shape(
+                cp.tangent_mode(),
                 tang_screen,
                 inactive_radius,
                 inactive_stroke,
@@ -96,3 +177,204 @@ pub fn ui_control_point_tangents(
     }
     result
 }
+
+fn tangent_length(tangent: &ControlPointTangent) -> f32 {
+    tangent.val.iter().map(|c| c * c).sum::<f32>().sqrt()
+}
+
+/// Aligns the in and out tangents of every interior control point so the Bezier curve is G1,
+/// keeping each handle's length. `anchor` is the (control point, tangent) being dragged, that
+/// handle keeps its direction and the opposite one follows.
+pub fn enforce_g1_tangents(control_points: &mut [ControlPoint], anchor: Option<(usize, usize)>) {
+    const MIN_TANGENT_LENGTH: f32 = 1e-6;
+
+    let num_control_points = control_points.len();
+    for (i, cp) in control_points.iter_mut().enumerate() {
+        if i == 0 || i + 1 == num_control_points {
+            continue;
+        }
+        let [Some(tangent_in), Some(tangent_out)] = *cp.tangents() else {
+            continue;
+        };
+
+        let length_in = tangent_length(&tangent_in);
+        let length_out = tangent_length(&tangent_out);
+        if length_in < MIN_TANGENT_LENGTH || length_out < MIN_TANGENT_LENGTH {
+            continue;
+        }
+
+        let direction_in = tangent_in.val.map(|c| -c / length_in);
+        let direction_out = tangent_out.val.map(|c| c / length_out);
+        let direction = match anchor {
+            Some((index, 0)) if index == i => direction_in,
+            Some((index, 1)) if index == i => direction_out,
+            _ => {
+                let sum = ControlPointTangent {
+                    val: [
+                        direction_in[0] + direction_out[0],
+                        direction_in[1] + direction_out[1],
+                        direction_in[2] + direction_out[2],
+                    ],
+                };
+                let sum_length = tangent_length(&sum);
+                if sum_length < MIN_TANGENT_LENGTH {
+                    // Handles point the same way, there is no meaningful average
+                    direction_out
+                } else {
+                    sum.val.map(|c| c / sum_length)
+                }
+            }
+        };
+
+        let tangents = cp.tangents_mut();
+        tangents[0] = Some(ControlPointTangent {
+            val: direction.map(|c| -c * length_in),
+        });
+        tangents[1] = Some(ControlPointTangent {
+            val: direction.map(|c| c * length_out),
+        });
+    }
+}
+
+/// Difference `b - a` per channel, hue goes the way `hue_interpolation` takes from `a` to `b`.
+fn value_delta(
+    a: &ControlPointTangent,
+    b: &ControlPointTangent,
+    hue_interpolation: HueInterpolation,
+) -> [f32; 3] {
+    [
+        b.val[0] - a.val[0],
+        b.val[1] - a.val[1],
+        hue_interpolation.unwrap(a.val[2], b.val[2]) - a.val[2],
+    ]
+}
+
+/// Applies each control point's `TangentMode` to its handles. `anchor` is the
+/// (control point, tangent) being dragged, the opposite handle follows it.
+pub fn apply_tangent_modes(control_points: &mut [ControlPoint], anchor: Option<(usize, usize)>) {
+    // Catmull-Rom handles for cubic Bezier segments with unit spacing
+    const AUTO_SMOOTH_FACTOR: f32 = 1.0 / 6.0;
+    const VECTOR_FACTOR: f32 = 1.0 / 3.0;
+
+    let num_control_points = control_points.len();
+    for i in 0..num_control_points {
+        let value = *control_points[i].val();
+        // Each segment's hue direction belongs to the control point it starts at
+        let prev = (i > 0).then(|| {
+            let prev = &control_points[i - 1];
+            value_delta(prev.val(), &value, prev.hue_interpolation()).map(|c| -c)
+        });
+        let next = (i + 1 < num_control_points).then(|| {
+            let next = control_points[i + 1].val();
+            value_delta(&value, next, control_points[i].hue_interpolation())
+        });
+        let dragged_tangent = anchor.filter(|(index, _)| *index == i).map(|(_, t)| t);
+
+        let cp = &mut control_points[i];
+        let [Some(tangent_in), Some(tangent_out)] = *cp.tangents() else {
+            continue;
+        };
+
+        let (new_in, new_out) = match cp.tangent_mode() {
+            TangentMode::Free => continue,
+            TangentMode::Aligned => {
+                let length_in = tangent_length(&tangent_in);
+                let length_out = tangent_length(&tangent_out);
+                let direction = match dragged_tangent {
+                    Some(0) => tangent_in.val.map(|c| -c / length_in.max(f32::EPSILON)),
+                    _ => tangent_out.val.map(|c| c / length_out.max(f32::EPSILON)),
+                };
+                (
+                    direction.map(|c| -c * length_in),
+                    direction.map(|c| c * length_out),
+                )
+            }
+            TangentMode::Mirrored => match dragged_tangent {
+                Some(0) => (tangent_in.val, tangent_in.val.map(|c| -c)),
+                _ => (tangent_out.val.map(|c| -c), tangent_out.val),
+            },
+            TangentMode::AutoSmooth => match (prev, next) {
+                (Some(prev), Some(next)) => {
+                    let out: [f32; 3] =
+                        std::array::from_fn(|c| (next[c] - prev[c]) * AUTO_SMOOTH_FACTOR);
+                    (out.map(|c| -c), out)
+                }
+                (None, Some(next)) => (tangent_in.val, next.map(|c| c * VECTOR_FACTOR)),
+                (Some(prev), None) => (prev.map(|c| c * VECTOR_FACTOR), tangent_out.val),
+                (None, None) => continue,
+            },
+            TangentMode::Vector => (
+                prev.map_or(tangent_in.val, |prev| prev.map(|c| c * VECTOR_FACTOR)),
+                next.map_or(tangent_out.val, |next| next.map(|c| c * VECTOR_FACTOR)),
+            ),
+        };
+
+        let tangents = cp.tangents_mut();
+        tangents[0] = Some(ControlPointTangent { val: new_in });
+        tangents[1] = Some(ControlPointTangent { val: new_out });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::control_point::{create_tangent_for_control_point, ControlPointValue};
+
+    fn with_tangents(hues: &[f32], tangent_mode: TangentMode) -> Vec<ControlPoint> {
+        hues.iter()
+            .enumerate()
+            .map(|(i, h)| {
+                let mut cp =
+                    ControlPoint::new_simple(ControlPointValue::new(0.5, 0.5, *h), i as f32);
+                *cp.tangents_mut() = [Some(create_tangent_for_control_point()); 2];
+                *cp.tangent_mode_mut() = tangent_mode;
+                cp
+            })
+            .collect()
+    }
+
+    #[test]
+    fn handles_follow_the_hue_direction() {
+        for tangent_mode in [TangentMode::AutoSmooth, TangentMode::Vector] {
+            let mut control_points = with_tangents(&[0.1, 0.2], tangent_mode);
+            *control_points[0].hue_interpolation_mut() = HueInterpolation::Longest;
+            apply_tangent_modes(&mut control_points, None);
+            // 0.9 backwards the long way, a third of it on each end
+            let out_hue = control_points[0].tangents()[1].unwrap().val[2];
+            let in_hue = control_points[1].tangents()[0].unwrap().val[2];
+            assert!(
+                (out_hue + 0.3).abs() < 1e-5,
+                "{:?}: {}",
+                tangent_mode,
+                out_hue
+            );
+            assert!(
+                (in_hue - 0.3).abs() < 1e-5,
+                "{:?}: {}",
+                tangent_mode,
+                in_hue
+            );
+        }
+    }
+
+    #[test]
+    fn mirrored_handles_are_equal_and_opposite() {
+        for (anchor, dragged) in [(Some((0, 0)), 0), (Some((0, 1)), 1), (None, 1)] {
+            let mut control_points = with_tangents(&[0.5], TangentMode::Mirrored);
+            control_points[0].tangents_mut()[dragged] = Some(ControlPointTangent {
+                val: [0.2, -0.1, 0.05],
+            });
+            apply_tangent_modes(&mut control_points, anchor);
+
+            let [Some(tangent_in), Some(tangent_out)] = *control_points[0].tangents() else {
+                panic!("handles were removed");
+            };
+            // The dragged handle stays put and the other one mirrors it
+            assert_eq!(
+                control_points[0].tangents()[dragged].unwrap().val,
+                [0.2, -0.1, 0.05]
+            );
+            assert_eq!(tangent_in.val, tangent_out.val.map(|c| -c), "{:?}", anchor);
+        }
+    }
+}
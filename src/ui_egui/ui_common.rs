@@ -1,5 +1,6 @@
-use crate::common::ColorStringCopy;
+use crate::common::{ColorStringCopy, SplineMode};
 use crate::egui::TextStyle;
+use eframe::egui::ComboBox;
 use eframe::egui::InnerResponse;
 use eframe::egui::Window;
 use eframe::{
@@ -229,6 +230,17 @@ pub fn color_text_ui(
     })
 }
 
+/// Picks one of the implemented spline modes, for panes that generate control points.
+pub fn ui_spline_mode_combo(ui: &mut Ui, id_salt: &str, spline_mode: &mut SplineMode) {
+    ComboBox::from_id_salt(id_salt)
+        .selected_text(format!("{:?}", spline_mode))
+        .show_ui(ui, |ui| {
+            for mode in SplineMode::IMPLEMENTED {
+                ui.selectable_value(spline_mode, mode, format!("{:?}", mode));
+            }
+        });
+}
+
 pub trait ContentWindow {
     fn is_open(&self) -> bool;
     fn close(&mut self);